@@ -1,5 +1,4 @@
 use rust_engine::core;
-use rust_engine::platform::PollResult;
 use rust_engine::platform::WinitBackend;
 use rust_engine::InputPlugin;
 
@@ -9,8 +8,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     app.set_fixed_dt(1.0 / 60.0);
     app.add_plugin(&InputPlugin::new());
 
-    let mut winit_backend = WinitBackend::try_new()?;
-    while winit_backend.poll_once(&mut app) != PollResult::Exit {}
+    app.set_runner(WinitBackend::try_new()?);
+    app.run();
 
     Ok(())
 }
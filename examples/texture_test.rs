@@ -1,7 +1,6 @@
 use rust_engine::core;
 use rust_engine::core::ConfigContainer;
 use rust_engine::core::TextureManager;
-use rust_engine::platform::PollResult;
 use rust_engine::platform::WinitBackend;
 use rust_engine::{InputPlugin, Sprite, Transform2D};
 
@@ -29,8 +28,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // テクスチャをロードしてスプライトエンティティを作成
     setup_sprites(&mut app);
 
-    let mut winit_backend = WinitBackend::try_new()?;
-    while winit_backend.poll_once(&mut app) != PollResult::Exit {}
+    app.set_runner(WinitBackend::try_new()?);
+    app.run();
 
     Ok(())
 }
@@ -1,2 +1,2 @@
 pub mod system;
-pub use system::{Camera2D, Sprite, Transform2D};
+pub use system::{Camera2D, Children, GlobalTransform2D, Parent, Sprite, Transform2D};
@@ -1,4 +1,5 @@
 use crate::components::Transform2D;
+use crate::core::ecs::Component;
 
 #[derive(Debug, Clone)]
 pub struct Camera2D {
@@ -7,6 +8,8 @@ pub struct Camera2D {
     viewport: (f32, f32),
 }
 
+impl Component for Camera2D {}
+
 impl Camera2D {
     pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
         Self {
@@ -0,0 +1,50 @@
+use crate::components::Transform2D;
+use crate::core::ecs::Component;
+
+/// ワールド空間での変換。親の `GlobalTransform2D` に自身の `Transform2D` を掛けたものです。
+///
+/// 伝播システム（`TransformPlugin`）が毎フレーム `Stage::PreRender` の先頭で更新します。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform2D(glam::Mat3);
+
+impl Component for GlobalTransform2D {}
+
+impl Default for GlobalTransform2D {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl From<Transform2D> for GlobalTransform2D {
+    fn from(transform: Transform2D) -> Self {
+        Self(transform.matrix())
+    }
+}
+
+impl GlobalTransform2D {
+    pub fn identity() -> Self {
+        Self(glam::Mat3::IDENTITY)
+    }
+
+    pub fn from_matrix(matrix: glam::Mat3) -> Self {
+        Self(matrix)
+    }
+
+    pub fn matrix(&self) -> glam::Mat3 {
+        self.0
+    }
+
+    /// ワールド空間での位置。
+    pub fn translation(&self) -> glam::Vec2 {
+        self.0.z_axis.truncate()
+    }
+
+    /// 子の `Transform2D` をこの変換の下に置いたときのワールド変換を返します。
+    pub fn mul_transform(&self, local: &Transform2D) -> Self {
+        Self(self.0 * local.matrix())
+    }
+
+    pub fn transform_point(&self, point: glam::Vec2) -> glam::Vec2 {
+        self.0.transform_point2(point)
+    }
+}
@@ -0,0 +1,130 @@
+//! エンティティの親子関係。
+//!
+//! `Parent` と `Children` は常に対になるように `World::set_parent` / `World::remove_parent` /
+//! `World::despawn_recursive` を通して更新します。直接 `insert` / `remove` すると
+//! 片方だけが残るため、これらのコンポーネントは外部から構築できないようにしています。
+//!
+//! 子を `World::despawn` した場合や `Parent` を `remove` した場合は、`Parent` の on_remove フックが
+//! 親の `Children` から子を取り除きます。親だけを `despawn` した場合、子の `Parent` は削除済みの
+//! エンティティを指したまま残り、`propagate_transforms` はその子を根として扱います。
+
+use crate::core::ecs::{Component, Entity, World};
+
+/// 親エンティティ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+impl Component for Parent {}
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// 子エンティティの一覧（追加順）。
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Children(Vec<Entity>);
+
+impl Component for Children {}
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.0.contains(&entity)
+    }
+}
+
+impl World {
+    /// `child` の親を `parent` にします。既に別の親がいれば付け替えます。
+    ///
+    /// どちらかが存在しない場合や、`parent` が `child` 自身またはその子孫である場合
+    /// （循環になる場合）は何もせずに `false` を返します。
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> bool {
+        if !self.contains(child) || !self.contains(parent) || self.is_ancestor(child, parent) {
+            return false;
+        }
+        self.register_hierarchy_hooks();
+        self.detach_from_parent(child);
+        self.insert(child, Parent(parent));
+        if let Some(mut children) = self.get_mut::<Children>(parent) {
+            children.0.push(child);
+            return true;
+        }
+        self.insert(parent, Children(vec![child]));
+        true
+    }
+
+    /// `child` を親から切り離し、元の親を返します。
+    pub fn remove_parent(&mut self, child: Entity) -> Option<Entity> {
+        let parent = self.get::<Parent>(child)?.get();
+        // 親の `Children` からは on_remove フックが取り除きます。
+        self.remove::<Parent>(child);
+        Some(parent)
+    }
+
+    /// `entity` とその子孫をすべて削除します。
+    pub fn despawn_recursive(&mut self, entity: Entity) -> bool {
+        if !self.contains(entity) {
+            return false;
+        }
+        let mut stack = vec![entity];
+        while let Some(current) = stack.pop() {
+            if let Some(children) = self.get::<Children>(current) {
+                stack.extend(children.iter());
+            }
+            self.despawn(current);
+        }
+        true
+    }
+
+    /// `ancestor` が `entity` 自身またはその祖先であれば `true`。
+    fn is_ancestor(&self, ancestor: Entity, entity: Entity) -> bool {
+        let mut current = Some(entity);
+        while let Some(e) = current {
+            if e == ancestor {
+                return true;
+            }
+            current = self.get::<Parent>(e).map(|parent| parent.get());
+        }
+        false
+    }
+
+    /// `Parent` が取り除かれたときに親の `Children` から外すフックを登録します（初回のみ）。
+    fn register_hierarchy_hooks(&mut self) {
+        if self.first_install::<Parent>() {
+            self.on_remove::<Parent>(detach_on_remove);
+        }
+    }
+
+    /// 親の `Children` から `child` を取り除きます。`child` 側の `Parent` はそのままです。
+    fn detach_from_parent(&mut self, child: Entity) -> Option<Entity> {
+        let parent = self.get::<Parent>(child)?.get();
+        let now_empty = match self.get_mut::<Children>(parent) {
+            Some(mut children) => {
+                children.0.retain(|&e| e != child);
+                children.0.is_empty()
+            }
+            None => false,
+        };
+        if now_empty {
+            self.remove::<Children>(parent);
+        }
+        Some(parent)
+    }
+}
+
+fn detach_on_remove(world: &mut World, entity: Entity) {
+    world.detach_from_parent(entity);
+}
@@ -4,3 +4,7 @@ pub mod camera2d;
 pub use camera2d::Camera2D;
 pub mod transform2d;
 pub use transform2d::Transform2D;
+pub mod global_transform2d;
+pub use global_transform2d::GlobalTransform2D;
+pub mod hierarchy;
+pub use hierarchy::{Children, Parent};
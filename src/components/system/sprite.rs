@@ -1,3 +1,4 @@
+use crate::core::ecs::Component;
 use crate::core::TextureHandle;
 
 #[derive(Debug, Clone)]
@@ -8,6 +9,8 @@ pub struct Sprite {
     visible: bool,
 }
 
+impl Component for Sprite {}
+
 impl Sprite {
     pub fn new(handle: TextureHandle) -> Self {
         Self {
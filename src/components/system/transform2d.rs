@@ -1,3 +1,5 @@
+use crate::core::ecs::Component;
+
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct Transform2D {
     position: glam::Vec2,
@@ -5,6 +7,8 @@ pub struct Transform2D {
     scale: glam::Vec2,
 }
 
+impl Component for Transform2D {}
+
 impl Transform2D {
     pub fn identity() -> Self {
         Self {
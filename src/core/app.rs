@@ -1,7 +1,13 @@
 use crate::core::ecs;
 use crate::core::plugin::Plugin;
-use crate::core::schedule::{Schedule, Stage};
-use crate::core::{DiContainer, Time, TimeFixed, TimeState};
+use crate::core::runner::{AppExit, FixedStepOrder, FixedStepPolicy, HeadlessRunner, Runner};
+use crate::core::schedule::{
+    Condition, Executor, IntoSystem, Schedule, ScheduleCommands, ScheduleDiagnostics,
+    ScheduleError, ScheduleGraph, Stage, System, SystemError, SystemHandle, SystemLabel,
+};
+use crate::core::state::{State, StateSchedule, States};
+use crate::core::{DiContainer, Time, TimeFixed};
+use std::time::Duration;
 
 use crate::core::config::{Config, ConfigContainer};
 
@@ -9,9 +15,14 @@ pub struct App {
     // App implementation
     dicontainer: DiContainer,
     world: ecs::World,
-    timer_state: TimeState,
     schedule: Schedule,
+    // `add_plugin` で構築中のプラグインの型名。登録したシステムに記録します。
+    current_plugin: Option<&'static str>,
     run_startup: bool,
+    runner: Option<Box<dyn Runner>>,
+    fixed_step: FixedStepPolicy,
+    // `FixedUpdate` にまだ回していない時間
+    accumulator: Duration,
 }
 
 impl App {
@@ -20,12 +31,19 @@ impl App {
         dicontainer.insert(Time::default());
         dicontainer.insert(TimeFixed::new(1.0 / 60.0)); // 固定更新用の時間間隔を追加
         dicontainer.insert(ConfigContainer::empty());
+        let world = ecs::World::new();
+        dicontainer.insert(ecs::Commands::new(&world));
+        dicontainer.insert(ScheduleCommands::default());
+        dicontainer.insert(AppExit::default());
         Self {
             dicontainer,
-            world: ecs::World::new(),
-            timer_state: TimeState::new(),
+            world,
             schedule: Schedule::new(),
+            current_plugin: None,
             run_startup: false,
+            runner: None,
+            fixed_step: FixedStepPolicy::default(),
+            accumulator: Duration::ZERO,
         }
     }
 
@@ -35,16 +53,291 @@ impl App {
         }
     }
     /// 指定した優先度でスケジュールにシステムを登録します。
-    pub fn add_system<I: Into<usize>>(
+    ///
+    /// `fn` のほか、状態を捕捉した `FnMut` クロージャ、`Res<T>` / `Query<Q>` などを引数に取る
+    /// 関数、`System` を実装した型を渡せます。足りないリソースや引数同士の借用の競合は
+    /// 登録時に型名付きで panic します。
+    pub fn add_system<I: Into<usize>, M>(
         &mut self,
         stage: Stage,
         priority: I,
-        system: fn(&mut DiContainer, &mut ecs::World),
+        system: impl IntoSystem<M>,
     ) -> &mut Self {
-        self.schedule.add_system(stage, priority, system);
+        self.try_add_system(stage, priority, system)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// `add_system` と同じですが、登録時の検査に失敗したらシステムを登録せずに返します。
+    pub fn try_add_system<I: Into<usize>, M>(
+        &mut self,
+        stage: Stage,
+        priority: I,
+        system: impl IntoSystem<M>,
+    ) -> Result<&mut Self, SystemError> {
+        self.try_register_system(stage, priority, system)?;
+        Ok(self)
+    }
+
+    /// `add_system` と同じですが、後から `disable_system` / `remove_system` などで
+    /// 操作するためのハンドルを返します。
+    pub fn register_system<I: Into<usize>, M>(
+        &mut self,
+        stage: Stage,
+        priority: I,
+        system: impl IntoSystem<M>,
+    ) -> SystemHandle {
+        self.try_register_system(stage, priority, system)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// `register_system` と同じですが、登録時の検査に失敗したらシステムを登録せずに返します。
+    pub fn try_register_system<I: Into<usize>, M>(
+        &mut self,
+        stage: Stage,
+        priority: I,
+        system: impl IntoSystem<M>,
+    ) -> Result<SystemHandle, SystemError> {
+        let system = system.into_system();
+        system.validate(&self.dicontainer)?;
+        Ok(self.schedule.add_boxed_system(
+            stage,
+            priority.into(),
+            Box::new(system),
+            self.current_plugin,
+        ))
+    }
+
+    /// `stage` で `label` の付いたシステムを、`condition` が成り立つときだけ実行します。
+    pub fn add_label_condition(
+        &mut self,
+        stage: Stage,
+        label: impl Into<SystemLabel>,
+        condition: impl Condition,
+    ) -> &mut Self {
+        self.schedule.add_label_condition(stage, label, condition);
+        self
+    }
+
+    /// 全ステージと状態のスケジュールの実行順を解決し、循環や未知のラベルを報告します。
+    ///
+    /// 呼ばなくても各ステージの最初の実行で解決されますが、その場合は失敗すると panic します。
+    pub fn build_schedule(&mut self) -> Result<(), ScheduleError> {
+        self.schedule.build()
+    }
+
+    /// `State<S>` リソースを `initial` で登録し、`S` の `OnEnter` / `OnExit` / `OnUpdate` の
+    /// スケジュールを使えるようにします。
+    ///
+    /// `initial` の `OnEnter` は `startup` で `Stage::Startup` の後に実行されます。
+    pub fn add_state<S: States>(&mut self, initial: S) -> &mut Self {
+        self.dicontainer.insert(State::new(initial));
+        self.schedule.add_state::<S>();
+        self
+    }
+
+    /// `OnEnter(state)` / `OnExit(state)` / `OnUpdate(state)` のスケジュールにシステムを登録します。
+    ///
+    /// `add_system` と同じく登録時に検査し、失敗すると panic します。先に `add_state` で
+    /// 状態の型を登録してください。
+    pub fn add_state_system<T: StateSchedule, I: Into<usize>, M>(
+        &mut self,
+        schedule: T,
+        priority: I,
+        system: impl IntoSystem<M>,
+    ) -> &mut Self {
+        self.register_state_system(schedule, priority, system);
+        self
+    }
+
+    /// `add_state_system` と同じですが、システムのハンドルを返します。
+    pub fn register_state_system<T: StateSchedule, I: Into<usize>, M>(
+        &mut self,
+        schedule: T,
+        priority: I,
+        system: impl IntoSystem<M>,
+    ) -> SystemHandle {
+        let system = system.into_system();
+        if let Err(err) = system.validate(&self.dicontainer) {
+            panic!("{err}");
+        }
+        let plugin = self.current_plugin;
+        match self.schedule.state_schedules::<T::State>() {
+            Some(schedules) => {
+                schedules.add_system(schedule, priority.into(), Box::new(system), plugin)
+            }
+            None => panic!(
+                "state {} is not registered; call App::add_state first",
+                std::any::type_name::<T::State>()
+            ),
+        }
+    }
+
+    /// システムを有効に戻します。見つからなければ `false` を返します。
+    pub fn enable_system(&mut self, handle: SystemHandle) -> bool {
+        self.schedule.enable_system(handle)
+    }
+
+    /// システムを無効にします。`Schedule::disable_system` を参照してください。
+    pub fn disable_system(&mut self, handle: SystemHandle) -> bool {
+        self.schedule.disable_system(handle)
+    }
+
+    /// システムを取り除きます。`Schedule::remove_system` を参照してください。
+    pub fn remove_system(&mut self, handle: SystemHandle) -> bool {
+        self.schedule.remove_system(handle)
+    }
+
+    /// システムが有効かどうか。取り除かれていれば `None` を返します。
+    pub fn is_system_enabled(&mut self, handle: SystemHandle) -> Option<bool> {
+        self.schedule.is_system_enabled(handle)
+    }
+
+    /// プラグイン `P` が登録したシステム（状態のスケジュールのものを含む）をまとめて有効にし、
+    /// その数を返します。
+    pub fn enable_plugin<P: Plugin>(&mut self) -> usize {
+        self.schedule.set_plugin_enabled::<P>(true)
+    }
+
+    /// プラグイン `P` が登録したシステムをまとめて無効にし、その数を返します。
+    pub fn disable_plugin<P: Plugin>(&mut self) -> usize {
+        self.schedule.set_plugin_enabled::<P>(false)
+    }
+
+    /// プラグイン `P` が登録したシステムをまとめて取り除き、その数を返します。
+    /// プラグインが登録したリソースやイベントはそのまま残ります。
+    pub fn remove_plugin<P: Plugin>(&mut self) -> usize {
+        self.schedule.remove_plugin_systems::<P>()
+    }
+
+    /// システムから `ScheduleCommands` で要求された切り替えを適用します。
+    fn apply_schedule_commands(&mut self) {
+        self.schedule.apply_commands(&mut self.dicontainer);
+    }
+
+    /// 借用が競合するのに順序が決まっていないシステムの組をエラーにします。
+    /// `Schedule::set_deny_ambiguities` を参照してください。
+    pub fn set_deny_ambiguities(&mut self, deny: bool) -> &mut Self {
+        self.schedule.set_deny_ambiguities(deny);
         self
     }
 
+    /// 全ステージと状態のスケジュールの解決済みの実行順。`Schedule::graph` を参照してください。
+    ///
+    /// `add_state` した状態の `OnEnter` / `OnExit` / `OnUpdate` は `state_schedules` に入ります。
+    pub fn schedule_graph(&mut self) -> Result<ScheduleGraph, ScheduleError> {
+        self.schedule.graph()
+    }
+
+    /// `stage` を `existing` の直後に追加します。`Schedule::add_stage_after` を参照してください。
+    pub fn add_stage_after(&mut self, existing: Stage, stage: Stage) -> &mut Self {
+        self.schedule.add_stage_after(existing, stage);
+        self
+    }
+
+    /// `stage` を `existing` の直前に追加します。
+    pub fn add_stage_before(&mut self, existing: Stage, stage: Stage) -> &mut Self {
+        self.schedule.add_stage_before(existing, stage);
+        self
+    }
+
+    /// システムの実行方法を切り替えます。`Schedule::set_executor` を参照してください。
+    pub fn set_executor(&mut self, executor: Executor) -> &mut Self {
+        self.schedule.set_executor(executor);
+        self
+    }
+
+    /// `App::run` で使うメインループを設定します。
+    pub fn set_runner(&mut self, runner: impl Runner) -> &mut Self {
+        self.runner = Some(Box::new(runner));
+        self
+    }
+
+    /// `run_frame` の固定ステップの進め方を設定します。
+    pub fn set_fixed_step_policy(&mut self, policy: FixedStepPolicy) -> &mut Self {
+        self.fixed_step = policy;
+        self
+    }
+
+    pub fn fixed_step_policy(&self) -> FixedStepPolicy {
+        self.fixed_step
+    }
+
+    /// `startup` を実行してから、`set_runner` で設定したメインループを終了まで動かします。
+    ///
+    /// ランナーを設定していなければ `HeadlessRunner::new()` を使います。これはシステムが
+    /// `AppExit::request` を呼ぶまで 1/60 秒ごとにフレームを回し続けます。
+    pub fn run(&mut self) {
+        self.startup();
+        let mut runner = self
+            .runner
+            .take()
+            .unwrap_or_else(|| Box::new(HeadlessRunner::new()));
+        runner.run(self);
+        self.runner = Some(runner);
+    }
+
+    /// システムから `AppExit::request` で終了が要求されていれば `true`。
+    pub fn exit_requested(&self) -> bool {
+        self.dicontainer
+            .get::<AppExit>()
+            .is_some_and(AppExit::is_requested)
+    }
+
+    /// 前のフレームから `frame_time` だけ経ったものとして 1 フレームを実行します。
+    ///
+    /// `process_input` の後、`Time` を `frame_time` だけ進め、`FixedStepPolicy` に従って
+    /// `update_logic` と `fixed_update` を実行します。`fixed_update` は積み増した時間を
+    /// `TimeFixed::delta_seconds` で割った回数だけ実行し、残りの割合を `alpha` として
+    /// `render` に渡してから `late_update` を実行します。
+    pub fn run_frame(&mut self, frame_time: Duration) {
+        self.process_input();
+        if let Some(time) = self.dicontainer.get_mut::<Time>() {
+            time.advance(frame_time);
+        }
+        self.accumulator += frame_time.min(self.fixed_step.max_frame_time);
+
+        match self.fixed_step.order {
+            FixedStepOrder::UpdateFirst => {
+                self.update_logic();
+                self.run_fixed_steps();
+            }
+            FixedStepOrder::FixedFirst => {
+                self.run_fixed_steps();
+                self.update_logic();
+            }
+        }
+
+        let alpha = match self.fixed_dt() {
+            Some(fixed_dt) => self.accumulator.as_secs_f32() / fixed_dt.as_secs_f32(),
+            None => 0.0,
+        };
+        self.render(alpha);
+        self.late_update();
+    }
+
+    fn fixed_dt(&self) -> Option<Duration> {
+        let fixed = self.dicontainer.get::<TimeFixed>()?;
+        (fixed.delta_seconds > 0.0).then(|| Duration::from_secs_f32(fixed.delta_seconds))
+    }
+
+    fn run_fixed_steps(&mut self) {
+        let Some(fixed_dt) = self.fixed_dt() else {
+            return;
+        };
+        let mut steps = 0;
+        while self.accumulator >= fixed_dt {
+            if self.fixed_step.max_steps.is_some_and(|max| steps >= max) {
+                // 追いつけない分は捨て、端数だけを持ち越します。
+                let remainder = self.accumulator.as_secs_f64() % fixed_dt.as_secs_f64();
+                self.accumulator = Duration::from_secs_f64(remainder);
+                break;
+            }
+            self.fixed_update();
+            self.accumulator -= fixed_dt;
+            steps += 1;
+        }
+    }
+
     pub fn get_di_container(&mut self) -> &mut DiContainer {
         &mut self.dicontainer
     }
@@ -54,7 +347,9 @@ impl App {
     }
 
     pub fn add_plugin<P: Plugin>(&mut self, plugin: &P) -> &mut Self {
+        let outer = self.current_plugin.replace(std::any::type_name::<P>());
         plugin.build(self);
+        self.current_plugin = outer;
         self
     }
 
@@ -74,8 +369,45 @@ impl App {
             }
         }
         self.dicontainer.insert(event);
-        self.schedule
-            .add_system(update_stage, priority, update_event::<T>);
+        self.schedule.add_boxed_system(
+            update_stage,
+            priority.into(),
+            Box::new(update_event::<T>.into_system()),
+            self.current_plugin,
+        );
+        self
+    }
+
+    /// `T` の追加/書き込み/削除を `Events<ComponentAdded<T>>` / `Events<ComponentInserted<T>>` /
+    /// `Events<ComponentRemoved<T>>` として受け取れるようにします。
+    ///
+    /// イベントは入力イベントと同じく `Stage::LateUpdate` で切り替わり、次のフレームで読めます。
+    pub fn add_component_observer<T: ecs::Component>(&mut self) -> &mut Self {
+        use crate::core::events::Events;
+        use crate::core::schedule::Priority;
+
+        if self
+            .dicontainer
+            .get::<Events<ecs::ComponentAdded<T>>>()
+            .is_none()
+        {
+            self.add_event(
+                Events::<ecs::ComponentAdded<T>>::new(),
+                Stage::LateUpdate,
+                Priority::Normal,
+            );
+            self.add_event(
+                Events::<ecs::ComponentInserted<T>>::new(),
+                Stage::LateUpdate,
+                Priority::Normal,
+            );
+            self.add_event(
+                Events::<ecs::ComponentRemoved<T>>::new(),
+                Stage::LateUpdate,
+                Priority::Normal,
+            );
+        }
+        self.world.observe::<T>();
         self
     }
 
@@ -84,45 +416,59 @@ impl App {
             return;
         }
         self.schedule
-            .run_stage(Stage::Startup, &mut self.dicontainer, &mut self.world);
+            .run_anchored(Stage::Startup, &mut self.dicontainer, &mut self.world);
+        self.schedule
+            .enter_initial_states(&mut self.dicontainer, &mut self.world);
+        self.apply_schedule_commands();
         self.run_startup = true;
     }
 
-    pub fn tick_timer(&mut self) {
-        let t = self.timer_state.tick();
-
-        if let Some(time) = self.dicontainer.get_mut::<Time>() {
-            *time = t;
-        }
-    }
-
     pub fn process_input(&mut self) {
         self.schedule
-            .run_stage(Stage::ProcessInput, &mut self.dicontainer, &mut self.world);
+            .run_anchored(Stage::ProcessInput, &mut self.dicontainer, &mut self.world);
+        self.apply_schedule_commands();
     }
 
+    /// `Stage::Update`（と前後に追加したステージ）を実行し、続けて現在の状態の `OnUpdate` を実行します。
     pub fn update_logic(&mut self) {
         self.schedule
-            .run_stage(Stage::Update, &mut self.dicontainer, &mut self.world);
+            .run_anchored(Stage::Update, &mut self.dicontainer, &mut self.world);
+        self.schedule
+            .run_state_updates(&mut self.dicontainer, &mut self.world);
+        self.apply_schedule_commands();
     }
 
-    pub fn render(&mut self, _alpha: f32) {
-        // レンダリングロジック（必要に応じて実装）
+    /// `TimeFixed::alpha` を `alpha` にして `Stage::PreRender` と `Stage::Render` を実行します。
+    pub fn render(&mut self, alpha: f32) {
+        if let Some(fixed_time) = self.dicontainer.get_mut::<TimeFixed>() {
+            fixed_time.set_alpha(alpha);
+        }
         self.schedule
-            .run_stage(Stage::PreRender, &mut self.dicontainer, &mut self.world);
+            .run_anchored(Stage::PreRender, &mut self.dicontainer, &mut self.world);
         self.schedule
-            .run_stage(Stage::Render, &mut self.dicontainer, &mut self.world);
+            .run_anchored(Stage::Render, &mut self.dicontainer, &mut self.world);
+        self.apply_schedule_commands();
     }
 
+    /// `Stage::LateUpdate`（と前後に追加したステージ）を実行し、要求された状態の切り替えを適用します。
     pub fn late_update(&mut self) {
         self.schedule
-            .run_stage(Stage::LateUpdate, &mut self.dicontainer, &mut self.world);
+            .run_anchored(Stage::LateUpdate, &mut self.dicontainer, &mut self.world);
+        self.schedule
+            .apply_state_transitions(&mut self.dicontainer, &mut self.world);
+        self.apply_schedule_commands();
+        if let Some(diagnostics) = self.dicontainer.get_mut::<ScheduleDiagnostics>() {
+            diagnostics.end_frame();
+        }
+        // 削除の記録はフレーム末で古いものから破棄します。
+        self.world.clear_trackers();
     }
 
     pub fn fixed_update(&mut self) {
         // 固定更新ロジック（必要に応じて実装）
         self.schedule
-            .run_stage(Stage::FixedUpdate, &mut self.dicontainer, &mut self.world);
+            .run_anchored(Stage::FixedUpdate, &mut self.dicontainer, &mut self.world);
+        self.apply_schedule_commands();
     }
 
     /// Get a reference to the loaded Config.
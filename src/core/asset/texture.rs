@@ -1,4 +1,5 @@
 use crate::core::config::TextureConfig;
+#[cfg(feature = "image")]
 use image::GenericImageView;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -8,11 +9,15 @@ use thiserror::Error;
 pub enum TextureError {
     #[error("texture_dir is not configured")]
     MissingTextureDir,
+    #[cfg(feature = "image")]
     #[error("failed to load texture: {path}")]
     LoadImage {
         path: PathBuf,
         source: image::ImageError,
     },
+    #[cfg(not(feature = "image"))]
+    #[error("image decoding is disabled (enable the `image` feature): {path}")]
+    ImageSupportDisabled { path: PathBuf },
 }
 
 type TextureId = u32;
@@ -84,6 +89,7 @@ impl TextureManager {
         Ok(handle)
     }
 
+    #[cfg(feature = "image")]
     fn _load_impl(&mut self, path: &Path) -> Result<TextureData, TextureError> {
         // ここで実際のファイル読み込みとデコードを行う
         let img = match image::open(path) {
@@ -107,6 +113,21 @@ impl TextureManager {
         })
     }
 
+    #[cfg(not(feature = "image"))]
+    fn _load_impl(&mut self, path: &Path) -> Result<TextureData, TextureError> {
+        Err(TextureError::ImageSupportDisabled {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// `load` に渡したパスを返します。シーンの保存でハンドルをパスに戻すときに使います。
+    pub fn path(&self, handle: &TextureHandle) -> Option<&str> {
+        self.path_cache
+            .iter()
+            .find(|(_, cached)| *cached == handle)
+            .map(|(path, _)| path.as_str())
+    }
+
     pub fn get(&self, handle: &TextureHandle) -> Option<&TextureData> {
         self.textures.get(&handle.id)
     }
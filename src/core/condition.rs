@@ -0,0 +1,115 @@
+//! システムを実行するかどうかを決める条件。
+//!
+//! `run_if` でシステムに、`add_label_condition` で同じラベルのシステムにまとめて付けます。
+//! 条件は `and` / `or` / `not` で組み合わせられます。
+//!
+//! ```ignore
+//! app.add_system(
+//!     Stage::Update,
+//!     Priority::Normal,
+//!     spawn_enemies.run_if(resource_exists::<Level>().and(every_seconds(2.0))),
+//! );
+//! app.add_label_condition(Stage::Update, "gameplay", |di: &DiContainer| {
+//!     !di.get::<Paused>().is_some_and(|paused| paused.0)
+//! });
+//! ```
+
+use crate::core::{DiContainer, Time};
+
+/// システムを実行するかどうかの判定。
+pub trait Condition: Send + 'static {
+    fn evaluate(&mut self, di: &DiContainer) -> bool;
+
+    /// 両方が成り立つときに実行します。`self` が成り立たなければ `other` は評価しません。
+    fn and<C: Condition>(self, other: C) -> And<Self, C>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// どちらかが成り立つときに実行します。`self` が成り立てば `other` は評価しません。
+    fn or<C: Condition>(self, other: C) -> Or<Self, C>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// 成り立たないときに実行します。
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F> Condition for F
+where
+    F: FnMut(&DiContainer) -> bool + Send + 'static,
+{
+    fn evaluate(&mut self, di: &DiContainer) -> bool {
+        self(di)
+    }
+}
+
+impl Condition for Box<dyn Condition> {
+    fn evaluate(&mut self, di: &DiContainer) -> bool {
+        (**self).evaluate(di)
+    }
+}
+
+pub struct And<A, B>(A, B);
+
+impl<A: Condition, B: Condition> Condition for And<A, B> {
+    fn evaluate(&mut self, di: &DiContainer) -> bool {
+        self.0.evaluate(di) && self.1.evaluate(di)
+    }
+}
+
+pub struct Or<A, B>(A, B);
+
+impl<A: Condition, B: Condition> Condition for Or<A, B> {
+    fn evaluate(&mut self, di: &DiContainer) -> bool {
+        self.0.evaluate(di) || self.1.evaluate(di)
+    }
+}
+
+pub struct Not<C>(C);
+
+impl<C: Condition> Condition for Not<C> {
+    fn evaluate(&mut self, di: &DiContainer) -> bool {
+        !self.0.evaluate(di)
+    }
+}
+
+/// `condition` が成り立たないときに実行します。
+pub fn not<C: Condition>(condition: C) -> Not<C> {
+    Not(condition)
+}
+
+/// `DiContainer` に `T` があるときに実行します。
+pub fn resource_exists<T: Send + Sync + 'static>() -> impl Condition {
+    |di: &DiContainer| di.get::<T>().is_some()
+}
+
+/// `Time` の経過時間で `seconds` 秒ごとに 1 回実行します。
+///
+/// 1 フレームで何周期分進んでも 1 回だけ成り立ちます。`Time` がなければ実行しません。
+pub fn every_seconds(seconds: f32) -> impl Condition {
+    let mut next = seconds;
+    move |di: &DiContainer| {
+        let Some(time) = di.get::<Time>() else {
+            return false;
+        };
+        let elapsed = time.elapsed_seconds();
+        if elapsed < next {
+            return false;
+        }
+        if seconds > 0.0 {
+            next += ((elapsed - next) / seconds).floor() * seconds + seconds;
+        }
+        true
+    }
+}
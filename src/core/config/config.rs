@@ -20,6 +20,7 @@ pub enum ConfigError {
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct Paths {
     pub texture_dir: Option<String>,
+    pub prefab_dir: Option<String>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -27,6 +28,11 @@ pub struct TextureConfig {
     pub texture_dir: Option<String>,
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct PrefabConfig {
+    pub prefab_dir: Option<String>,
+}
+
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct Config {
     pub paths: Option<Paths>,
@@ -41,6 +47,15 @@ impl Config {
                 .and_then(|paths| paths.texture_dir.clone()),
         }
     }
+
+    pub fn prefab_config(&self) -> PrefabConfig {
+        PrefabConfig {
+            prefab_dir: self
+                .paths
+                .as_ref()
+                .and_then(|paths| paths.prefab_dir.clone()),
+        }
+    }
 }
 
 pub struct ConfigContainer {
@@ -1,3 +1,3 @@
 #[allow(clippy::module_inception)]
 mod config;
-pub use config::{Config, ConfigContainer, ConfigError, TextureConfig};
+pub use config::{Config, ConfigContainer, ConfigError, PrefabConfig, TextureConfig};
@@ -0,0 +1,303 @@
+//! システムとステージの実行時間の計測。
+//!
+//! `DiContainer` に `ScheduleDiagnostics` を入れると、スケジュールがシステムとステージの
+//! 実行時間を記録します。直近のフレームの平均とパーセンタイル、最後のフレームの内訳を
+//! 調べられます。フレームの区切りは `App::late_update` が `end_frame` で付けます。
+//!
+//! ```ignore
+//! app.get_di_container().insert(ScheduleDiagnostics::new(120));
+//! // ...
+//! let diagnostics = app.get_di_container().get::<ScheduleDiagnostics>().unwrap();
+//! for series in diagnostics.slowest_systems(5) {
+//!     println!("{} {:?}", series.name(), series.average());
+//! }
+//! ```
+
+use crate::core::schedule::SystemHandle;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 記録するフレーム数の既定値。
+pub const DEFAULT_DIAGNOSTICS_WINDOW: usize = 120;
+
+/// 1 つのシステムまたはステージの、直近の実行時間。
+#[derive(Debug, Clone)]
+pub struct TimingSeries {
+    stage: String,
+    system: Option<Arc<str>>,
+    samples: VecDeque<Duration>,
+}
+
+impl TimingSeries {
+    fn new(stage: &str, system: Option<Arc<str>>) -> Self {
+        Self {
+            stage: stage.to_string(),
+            system,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, duration: Duration, window: usize) {
+        if self.samples.len() >= window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    pub fn stage(&self) -> &str {
+        &self.stage
+    }
+
+    /// システムの名前。ステージの系列では `None` です。
+    pub fn system(&self) -> Option<&str> {
+        self.system.as_deref()
+    }
+
+    /// 表示用の名前（`Update/my_crate::movement` または `Update`）。
+    pub fn name(&self) -> String {
+        match &self.system {
+            Some(system) => format!("{}/{system}", self.stage),
+            None => self.stage.clone(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        self.samples.back().copied()
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.iter().copied().max().unwrap_or_default()
+    }
+
+    /// `percentile`（0〜100）パーセンタイルの実行時間。最近傍順位法で求めます。
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    fn matches(&self, stage: &str, system: Option<&str>) -> bool {
+        if self.stage != stage {
+            return false;
+        }
+        match (self.system.as_deref(), system) {
+            (None, None) => true,
+            (Some(name), Some(system)) => {
+                name == system
+                    || name
+                        .strip_suffix(system)
+                        .is_some_and(|path| path.ends_with("::"))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 1 つのステージの、あるフレームでの実行時間と各システムの内訳。
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageTiming {
+    pub stage: String,
+    pub duration: Duration,
+    pub systems: Vec<(Arc<str>, Duration)>,
+}
+
+/// 1 フレーム分の実行時間の内訳。ステージは実行した順に並びます。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameTimings {
+    pub frame: u64,
+    pub stages: Vec<StageTiming>,
+}
+
+impl FrameTimings {
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.duration).sum()
+    }
+}
+
+impl fmt::Display for FrameTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "frame {} ({:?})", self.frame, self.total())?;
+        for stage in &self.stages {
+            writeln!(f, "  {} {:?}", stage.stage, stage.duration)?;
+            for (system, duration) in &stage.systems {
+                writeln!(f, "    {system} {duration:?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// システムとステージの実行時間を集めるリソース。
+#[derive(Debug, Clone)]
+pub struct ScheduleDiagnostics {
+    window: usize,
+    systems: Vec<TimingSeries>,
+    // システムのハンドルから `systems` の位置
+    system_index: HashMap<SystemHandle, usize>,
+    stages: Vec<TimingSeries>,
+    // ステージの名前から `stages` の位置
+    stage_index: HashMap<String, usize>,
+    current: FrameTimings,
+    // 実行中のステージで記録したシステム
+    stage_systems: Vec<(Arc<str>, Duration)>,
+    last_frame: Option<FrameTimings>,
+    log_next_frame: bool,
+}
+
+impl ScheduleDiagnostics {
+    /// 直近 `window` 回分の実行時間を保持します。
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            systems: Vec::new(),
+            system_index: HashMap::new(),
+            stages: Vec::new(),
+            stage_index: HashMap::new(),
+            current: FrameTimings::default(),
+            stage_systems: Vec::new(),
+            last_frame: None,
+            log_next_frame: false,
+        }
+    }
+
+    /// `stage` の `system` の実行時間。名前はパスを除いたものでも構いません。
+    pub fn system(&self, stage: impl fmt::Display, system: &str) -> Option<&TimingSeries> {
+        let stage = stage.to_string();
+        self.systems
+            .iter()
+            .find(|series| series.matches(&stage, Some(system)))
+    }
+
+    /// ステージ全体（コマンドの適用を含む）の実行時間。
+    pub fn stage(&self, stage: impl fmt::Display) -> Option<&TimingSeries> {
+        let index = self.stage_index.get(&stage.to_string())?;
+        Some(&self.stages[*index])
+    }
+
+    pub fn systems(&self) -> impl Iterator<Item = &TimingSeries> {
+        self.systems.iter()
+    }
+
+    pub fn stages(&self) -> impl Iterator<Item = &TimingSeries> {
+        self.stages.iter()
+    }
+
+    /// 平均の実行時間が長い順に `count` 個のシステム。
+    pub fn slowest_systems(&self, count: usize) -> Vec<&TimingSeries> {
+        let mut systems: Vec<_> = self.systems.iter().collect();
+        systems.sort_by_key(|series| std::cmp::Reverse(series.average()));
+        systems.truncate(count);
+        systems
+    }
+
+    /// 最後に `end_frame` したフレームの内訳。
+    pub fn last_frame(&self) -> Option<&FrameTimings> {
+        self.last_frame.as_ref()
+    }
+
+    /// 次の `end_frame` でそのフレームの内訳を `log::info!` に書き出します。
+    pub fn log_next_frame(&mut self) {
+        self.log_next_frame = true;
+    }
+
+    /// 各システムとステージの平均、p50 / p95 / p99、最大の一覧。
+    pub fn summary(&self) -> String {
+        let mut text = String::new();
+        for series in self.stages.iter().chain(self.systems.iter()) {
+            let _ = writeln!(
+                text,
+                "{} avg {:?} p50 {:?} p95 {:?} p99 {:?} max {:?} ({} samples)",
+                series.name(),
+                series.average(),
+                series.percentile(50.0),
+                series.percentile(95.0),
+                series.percentile(99.0),
+                series.max(),
+                series.len()
+            );
+        }
+        text
+    }
+
+    /// フレームを区切ります。記録中の内訳を `last_frame` にします。
+    pub fn end_frame(&mut self) {
+        let frame = self.current.frame;
+        let finished = std::mem::replace(
+            &mut self.current,
+            FrameTimings {
+                frame: frame + 1,
+                stages: Vec::new(),
+            },
+        );
+        if std::mem::take(&mut self.log_next_frame) {
+            log::info!("{finished}");
+        }
+        self.last_frame = Some(finished);
+    }
+
+    pub(crate) fn record_system(
+        &mut self,
+        stage: &str,
+        handle: SystemHandle,
+        system: &str,
+        duration: Duration,
+    ) {
+        let index = *self.system_index.entry(handle).or_insert_with(|| {
+            self.systems
+                .push(TimingSeries::new(stage, Some(Arc::from(system))));
+            self.systems.len() - 1
+        });
+        let series = &mut self.systems[index];
+        series.push(duration, self.window);
+        // 内訳の名前は系列と共有し、フレームごとに確保し直しません。
+        let name = series.system.clone().unwrap_or_else(|| Arc::from(system));
+        self.stage_systems.push((name, duration));
+    }
+
+    /// ステージの実行時間を記録し、それまでに記録したシステムをそのステージの内訳にします。
+    pub(crate) fn record_stage(&mut self, stage: &str, duration: Duration) {
+        let index = match self.stage_index.get(stage) {
+            Some(&index) => index,
+            None => {
+                self.stages.push(TimingSeries::new(stage, None));
+                self.stage_index
+                    .insert(stage.to_string(), self.stages.len() - 1);
+                self.stages.len() - 1
+            }
+        };
+        let series = &mut self.stages[index];
+        series.push(duration, self.window);
+        self.current.stages.push(StageTiming {
+            stage: series.stage.clone(),
+            duration,
+            systems: std::mem::take(&mut self.stage_systems),
+        });
+    }
+}
+
+impl Default for ScheduleDiagnostics {
+    fn default() -> Self {
+        Self::new(DEFAULT_DIAGNOSTICS_WINDOW)
+    }
+}
@@ -35,6 +35,10 @@ mod dicontainer_impl {
                 .and_then(|b| b.downcast_mut::<T>())
         }
 
+        pub(crate) fn contains_type(&self, id: std::any::TypeId) -> bool {
+            self.map.contains_key(&id)
+        }
+
         pub fn remove<T: 'static + Send + Sync>(&mut self) -> Option<T> {
             self.map
                 .remove(&std::any::TypeId::of::<T>())
@@ -0,0 +1,105 @@
+use super::Component;
+
+/// `World::spawn` / `World::insert` / `World::remove_bundle` でまとめて扱うコンポーネントの組。
+///
+/// 単体のコンポーネントと、コンポーネントのタプル（12 要素まで）が `Bundle` です。
+/// タプルの各要素はそれぞれ独立したコンポーネントとして格納されます。
+pub trait Bundle: Sized + Send + Sync + 'static {
+    /// バンドルに含まれるコンポーネント型を順に `visitor` へ渡します。
+    fn visit_types<V: TypeVisitor>(visitor: &mut V);
+    /// コンポーネントを順に `writer` へ書き出します。
+    fn write<W: ComponentWriter>(self, writer: &mut W);
+    /// `reader` からコンポーネントを取り出してバンドルを組み立てます。
+    ///
+    /// 呼び出し側は全コンポーネントが存在することを事前に確認しておく必要があります。
+    fn read<R: ComponentReader>(reader: &mut R) -> Self;
+}
+
+pub trait TypeVisitor {
+    fn visit<T: Component>(&mut self);
+}
+
+pub trait ComponentWriter {
+    fn write<T: Component>(&mut self, component: T);
+}
+
+pub trait ComponentReader {
+    fn read<T: Component>(&mut self) -> T;
+}
+
+impl<T: Component> Bundle for T {
+    fn visit_types<V: TypeVisitor>(visitor: &mut V) {
+        visitor.visit::<T>();
+    }
+    fn write<W: ComponentWriter>(self, writer: &mut W) {
+        writer.write(self);
+    }
+    fn read<R: ComponentReader>(reader: &mut R) -> Self {
+        reader.read::<T>()
+    }
+}
+
+macro_rules! tuple_bundle {
+    ($($name:ident),*) => {
+        impl<$($name: Component),*> Bundle for ($($name,)*) {
+            #[allow(unused_variables)]
+            fn visit_types<V: TypeVisitor>(visitor: &mut V) {
+                $(visitor.visit::<$name>();)*
+            }
+            #[allow(unused_variables, non_snake_case)]
+            fn write<W: ComponentWriter>(self, writer: &mut W) {
+                let ($($name,)*) = self;
+                $(writer.write($name);)*
+            }
+            #[allow(unused_variables, clippy::unused_unit)]
+            fn read<R: ComponentReader>(reader: &mut R) -> Self {
+                ($(reader.read::<$name>(),)*)
+            }
+        }
+    };
+}
+
+tuple_bundle!();
+tuple_bundle!(A);
+tuple_bundle!(A, B);
+tuple_bundle!(A, B, C);
+tuple_bundle!(A, B, C, D);
+tuple_bundle!(A, B, C, D, E);
+tuple_bundle!(A, B, C, D, E, F);
+tuple_bundle!(A, B, C, D, E, F, G);
+tuple_bundle!(A, B, C, D, E, F, G, H);
+tuple_bundle!(A, B, C, D, E, F, G, H, I);
+tuple_bundle!(A, B, C, D, E, F, G, H, I, J);
+tuple_bundle!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_bundle!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// バンドルのうちアーキタイプに格納する（疎集合でない）型の ID をソートして返します。
+/// 同じ型が重複している場合は panic します。
+#[cfg(feature = "ecs-custom")]
+pub(crate) fn sorted_type_ids<B: Bundle>() -> Vec<std::any::TypeId> {
+    use super::storage::is_sparse;
+    use std::any::TypeId;
+
+    struct Collect(Vec<(TypeId, bool)>);
+    impl TypeVisitor for Collect {
+        fn visit<T: Component>(&mut self) {
+            self.0.push((TypeId::of::<T>(), is_sparse::<T>()));
+        }
+    }
+    let mut collect = Collect(Vec::new());
+    B::visit_types(&mut collect);
+    let mut ids = collect.0;
+    ids.sort_unstable();
+    let len = ids.len();
+    ids.dedup();
+    assert_eq!(
+        len,
+        ids.len(),
+        "bundle {} contains the same component type more than once",
+        std::any::type_name::<B>()
+    );
+    ids.into_iter()
+        .filter(|(_, sparse)| !sparse)
+        .map(|(id, _)| id)
+        .collect()
+}
@@ -0,0 +1,292 @@
+//! コンポーネントの変更検出。
+//!
+//! `World` は単調増加する変更ティックを持ち、`Schedule` はシステムを実行するたびに
+//! ティックを進めます。コンポーネントは追加されたティックと最後に可変アクセスされたティックを
+//! 記録し、`Added<T>` / `Changed<T>` フィルタは「そのシステムが前回実行されてから」
+//! 追加/変更されたものに一致します。
+//!
+//! 変更は可変アクセス（`World::get_mut`、`World::insert` による上書き、`&mut T` を含む
+//! クエリでの取得）の時点で記録され、値が実際に書き換えられたかどうかは区別しません。
+
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+use super::query::Stored;
+#[cfg(feature = "ecs-custom")]
+use super::query::{has_type, ColumnSource};
+use super::storage::{is_sparse, SparseStorages};
+use super::{Access, Component, Entity, QueryFilter};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+use hecs as h;
+
+/// コンポーネントが追加/変更されたティック。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentTicks {
+    added: u64,
+    changed: u64,
+}
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: u64) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    pub fn added(&self) -> u64 {
+        self.added
+    }
+
+    pub fn changed(&self) -> u64 {
+        self.changed
+    }
+
+    /// `last_run` より後に追加されていれば `true`。
+    pub fn is_added(&self, last_run: u64) -> bool {
+        self.added > last_run
+    }
+
+    /// `last_run` より後に追加または変更されていれば `true`。
+    pub fn is_changed(&self, last_run: u64) -> bool {
+        self.changed > last_run
+    }
+
+    pub(crate) fn set_changed(&mut self, tick: u64) {
+        self.changed = tick;
+    }
+}
+
+/// クエリが比較に使うティックの組。
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct Ticks {
+    /// 読み手が前回実行されたティック。これより新しい変更が検出対象です。
+    pub last_run: u64,
+    /// 現在のティック。可変アクセスはこの値で記録されます。
+    pub this_run: u64,
+}
+
+/// 前回の実行以降に `T` が追加されたエンティティに絞り込むフィルタ。
+pub struct Added<T>(PhantomData<fn() -> T>);
+
+/// 前回の実行以降に `T` が追加または変更されたエンティティに絞り込むフィルタ。
+pub struct Changed<T>(PhantomData<fn() -> T>);
+
+/// hecs バックエンドでコンポーネント `T` と並べて格納するティック。
+///
+/// `&mut T` のクエリと `Changed<T>` / `Added<T>` のフィルタが同じクエリで同じ列を読むため、
+/// hecs には共有の借用だけを要求し、変更ティックはアトミックに書き込みます。
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+#[doc(hidden)]
+pub struct HecsTicks<T> {
+    added: u64,
+    changed: AtomicU64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+impl<T> HecsTicks<T> {
+    pub(crate) fn new(ticks: ComponentTicks) -> Self {
+        Self {
+            added: ticks.added,
+            changed: AtomicU64::new(ticks.changed),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn get(&self) -> ComponentTicks {
+        ComponentTicks {
+            added: self.added,
+            changed: self.changed.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn set_changed(&self, tick: u64) {
+        self.changed.store(tick, Ordering::Relaxed);
+    }
+}
+
+/// バックエンド共通の変更検出の状態。
+///
+/// 2 つのティックは、並列に実行するシステムの引数を取り出す間 `Schedule` が共有参照から
+/// 設定できるようにアトミックにしています。
+pub(crate) struct ChangeTrackers {
+    change_tick: AtomicU64,
+    last_change_tick: AtomicU64,
+    last_clear_tick: u64,
+    pub(crate) removed: RemovedLog,
+}
+
+impl Default for ChangeTrackers {
+    fn default() -> Self {
+        // 0 を「一度も実行されていない」読み手の基準にするため、ティックは 1 から始めます。
+        Self {
+            change_tick: AtomicU64::new(1),
+            last_change_tick: AtomicU64::new(0),
+            last_clear_tick: 0,
+            removed: RemovedLog::default(),
+        }
+    }
+}
+
+impl ChangeTrackers {
+    pub(crate) fn change_tick(&self) -> u64 {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn last_change_tick(&self) -> u64 {
+        self.last_change_tick.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn ticks(&self) -> Ticks {
+        Ticks {
+            last_run: self.last_change_tick(),
+            this_run: self.change_tick(),
+        }
+    }
+
+    pub(crate) fn increment_change_tick(&mut self) -> u64 {
+        let tick = self.change_tick.get_mut();
+        *tick += 1;
+        *tick
+    }
+
+    pub(crate) fn set_last_change_tick(&mut self, tick: u64) {
+        *self.last_change_tick.get_mut() = tick;
+    }
+
+    /// 次に取り出す引数が使うティックを設定します。
+    pub(crate) fn set_ticks(&self, ticks: Ticks) {
+        self.last_change_tick
+            .store(ticks.last_run, Ordering::Relaxed);
+        self.change_tick.store(ticks.this_run, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_removed(&mut self, id: TypeId, entity: Entity) {
+        let tick = self.change_tick();
+        self.removed.record(id, entity, tick);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.removed.prune(self.last_clear_tick);
+        self.last_clear_tick = self.change_tick();
+    }
+}
+
+/// `World::remove` / `World::remove_bundle` / `World::despawn` で取り除かれたコンポーネントの記録。
+///
+/// 記録は `World::clear_trackers` 2 回分（通常は 2 フレーム）保持されます。
+#[derive(Default)]
+pub(crate) struct RemovedLog {
+    entries: HashMap<TypeId, Vec<(Entity, u64)>>,
+}
+
+impl RemovedLog {
+    pub(crate) fn record(&mut self, id: TypeId, entity: Entity, tick: u64) {
+        self.entries.entry(id).or_default().push((entity, tick));
+    }
+
+    /// `cutoff` より前に記録されたものを破棄します。
+    pub(crate) fn prune(&mut self, cutoff: u64) {
+        for entries in self.entries.values_mut() {
+            entries.retain(|(_, tick)| *tick >= cutoff);
+        }
+    }
+
+    pub(crate) fn reader<T: Component>(&self, last_run: u64) -> RemovedComponents<'_, T> {
+        RemovedComponents {
+            entries: self
+                .entries
+                .get(&TypeId::of::<T>())
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+            last_run,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// 前回の実行以降に `T` が取り除かれたエンティティの一覧。`World::removed` で取得します。
+pub struct RemovedComponents<'w, T> {
+    entries: &'w [(Entity, u64)],
+    last_run: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RemovedComponents<'_, T> {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entries
+            .iter()
+            .filter(move |(_, tick)| *tick > self.last_run)
+            .map(|(entity, _)| *entity)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+macro_rules! tick_filter {
+    ($filter:ident, $check:ident) => {
+        impl<T: Component> QueryFilter for $filter<T> {
+            fn access(access: &mut Access) {
+                access.read_filter::<T>();
+            }
+
+            fn filter_sparse(entity: Entity, sparse: &SparseStorages, ticks: Ticks) -> bool {
+                !is_sparse::<T>()
+                    || sparse
+                        .get::<T>()
+                        .and_then(|set| set.ticks(entity))
+                        .is_some_and(|component_ticks| component_ticks.$check(ticks.last_run))
+            }
+
+            // 疎集合の `T` ではティックが `None` になり、判定は `filter_sparse` に任せます。
+            #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+            type Hecs<Q: h::Query> = (Q, Stored<T, &'static HecsTicks<T>, true>);
+
+            #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+            fn unwrap_hecs<'q, Q: h::Query>(
+                item: <Self::Hecs<Q> as h::Query>::Item<'q>,
+                ticks: Ticks,
+            ) -> Option<<Q as h::Query>::Item<'q>> {
+                let (item, component_ticks) = item;
+                component_ticks
+                    .map_or(true, |component_ticks| {
+                        component_ticks.get().$check(ticks.last_run)
+                    })
+                    .then_some(item)
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            type State = (*const ComponentTicks, u64);
+
+            #[cfg(feature = "ecs-custom")]
+            fn matches_archetype(types: &[TypeId]) -> bool {
+                is_sparse::<T>() || has_type::<T>(types)
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            fn init_state<S: ColumnSource>(source: &mut S, ticks: Ticks) -> Self::State {
+                if is_sparse::<T>() {
+                    return (std::ptr::null(), ticks.last_run);
+                }
+                (source.ticks_ptr::<T>(), ticks.last_run)
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            unsafe fn filter_row(state: Self::State, row: usize) -> bool {
+                let (component_ticks, last_run) = state;
+                // 疎集合の `T` は `filter_sparse` で判定します。
+                component_ticks.is_null() || (*component_ticks.add(row)).$check(last_run)
+            }
+        }
+    };
+}
+
+tick_filter!(Added, is_added);
+tick_filter!(Changed, is_changed);
@@ -0,0 +1,104 @@
+//! システムから `World` への構造変更を遅延させるコマンドバッファ。
+//!
+//! `Commands` は `DiContainer` のリソースとして置き、`Schedule::run_stage` がステージの
+//! 最後に `apply` します。クエリの反復中でも spawn / insert / remove / despawn を記録でき、
+//! `spawn` は予約した `Entity` をその場で返します。適用せずに捨てたバッファの予約は、
+//! drop したときに解放されます。
+
+use super::entities::EntityAllocator;
+use super::{Bundle, Component, Entity, World};
+
+type Command = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+/// `World` への変更の記録。記録した順に適用されます。
+pub struct Commands {
+    allocator: EntityAllocator,
+    queue: Vec<Command>,
+    // `spawn` で予約し、まだ適用していない ID
+    reserved: Vec<Entity>,
+}
+
+impl Commands {
+    /// `world` 用のコマンドバッファを作ります。予約する ID は `world` と共有されます。
+    pub fn new(world: &World) -> Self {
+        Self {
+            allocator: world.allocator().clone(),
+            queue: Vec::new(),
+            reserved: Vec::new(),
+        }
+    }
+
+    /// バンドルを持つエンティティの生成を記録し、予約した ID を返します。
+    ///
+    /// 返した ID は適用前でも `insert` / `despawn` などのコマンドに使えます。
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = Entity::from_allocator(&self.allocator);
+        self.reserved.push(entity);
+        self.add(move |world| {
+            world.spawn_reserved(entity, bundle);
+        });
+        entity
+    }
+
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        self.add(move |world| {
+            world.insert(entity, bundle);
+        });
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) {
+        self.add(move |world| {
+            world.remove::<T>(entity);
+        });
+    }
+
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) {
+        self.add(move |world| {
+            world.remove_bundle::<B>(entity);
+        });
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.add(move |world| {
+            world.despawn(entity);
+        });
+    }
+
+    /// 任意の変更を記録します。
+    pub fn add<F: FnOnce(&mut World) + Send + Sync + 'static>(&mut self, command: F) {
+        self.queue.push(Box::new(command));
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// 記録したコマンドを順に適用して空にします。
+    ///
+    /// # Panics
+    ///
+    /// `Commands::new` に渡したものとは別の `World` を渡した場合。
+    pub fn apply(&mut self, world: &mut World) {
+        assert!(
+            self.allocator.same_as(world.allocator()),
+            "Commands applied to a World other than the one it was created for"
+        );
+        self.reserved.clear();
+        for command in self.queue.drain(..) {
+            command(world);
+        }
+    }
+}
+
+impl Drop for Commands {
+    /// 適用しなかった `spawn` の ID を割り当てに返します。
+    fn drop(&mut self) {
+        for entity in self.reserved.drain(..) {
+            self.allocator.release(entity.index(), entity.generation());
+        }
+    }
+}
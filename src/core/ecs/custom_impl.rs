@@ -0,0 +1,975 @@
+//! 自前のアーキタイプ型 ECS バックエンド。
+//!
+//! 同じコンポーネント型の組み合わせを持つエンティティを 1 つの `Archetype` にまとめ、
+//! コンポーネントは型ごとの列 (`Vec<T>`) に格納します。エンティティの行はアーキタイプ内で
+//! 詰めて配置され、削除時は `swap_remove` で穴を埋めます。
+//! `StorageType::SparseSet` のコンポーネントはアーキタイプに含めず、`SparseStorages` に格納します。
+
+use super::bundle::sorted_type_ids;
+use super::change::{ChangeTrackers, Ticks};
+use super::entities::EntityAllocator;
+use super::hooks::{bundle_type_ids, ComponentHooks};
+use super::introspection::ComponentRegistry;
+use super::name::NameIndex;
+use super::query::{check_access, ColumnSource};
+use super::storage::{is_sparse, ColumnCell, SparseStorages};
+use super::{
+    assert_mutable, Bundle, Component, ComponentReader, ComponentTicks, ComponentWriter, QueryData,
+    QueryError, QueryFilter, ReadOnlyQueryData, RemovedComponents, TypeVisitor, With, Without,
+};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub(crate) fn from_allocator(allocator: &EntityAllocator) -> Self {
+        let (index, generation) = allocator.alloc();
+        Self { index, generation }
+    }
+
+    /// 世代を除いたインデックス。
+    pub(crate) fn index(self) -> u32 {
+        self.index
+    }
+
+    pub(crate) fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+pub struct Ref<'a, T: ?Sized>(&'a T);
+pub struct RefMut<'a, T: ?Sized>(&'a mut T);
+
+impl<T: ?Sized> Deref for Ref<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T: ?Sized> Deref for RefMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T: ?Sized> DerefMut for RefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0
+    }
+}
+
+/// 型消去されたコンポーネント列。実体は `ColumnCell<T>` です。
+trait Column: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// 同じ要素型の空の列を作ります。新しいアーキタイプの列を用意するときに使います。
+    fn new_empty(&self) -> Box<dyn Column>;
+    fn swap_remove_drop(&mut self, row: usize);
+    /// `row` の要素を取り出して `dst`（同じ要素型の列）の末尾に移します。
+    fn swap_remove_into(&mut self, row: usize, dst: &mut dyn Column);
+}
+
+impl<T: Component> Column for ColumnCell<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(ColumnCell::<T>::new())
+    }
+    fn swap_remove_drop(&mut self, row: usize) {
+        self.get_mut().swap_remove(row);
+    }
+    fn swap_remove_into(&mut self, row: usize, dst: &mut dyn Column) {
+        let value = self.get_mut().swap_remove(row);
+        dst.as_any_mut()
+            .downcast_mut::<ColumnCell<T>>()
+            .expect("column type mismatch")
+            .get_mut()
+            .push(value);
+    }
+}
+
+struct Archetype {
+    // `types` は TypeId でソート済みで、`columns` は同じ順序で並びます。
+    types: Vec<TypeId>,
+    columns: Vec<Box<dyn Column>>,
+    // `columns` と同じ並びで、各行のコンポーネントのティックを持ちます。
+    ticks: Vec<ColumnCell<ComponentTicks>>,
+    entities: Vec<Entity>,
+    // バンドル型 (`TypeId::of::<B>()`) ごとの追加/削除後の移動先アーキタイプのキャッシュ。
+    insert_edges: HashMap<TypeId, usize>,
+    remove_edges: HashMap<TypeId, usize>,
+}
+
+impl Archetype {
+    fn empty() -> Self {
+        Self {
+            types: Vec::new(),
+            columns: Vec::new(),
+            ticks: Vec::new(),
+            entities: Vec::new(),
+            insert_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
+        }
+    }
+
+    fn column_index(&self, id: TypeId) -> Option<usize> {
+        self.types.binary_search(&id).ok()
+    }
+
+    fn column<T: Component>(&self) -> Option<&ColumnCell<T>> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        self.columns[index].as_any().downcast_ref::<ColumnCell<T>>()
+    }
+
+    /// `T` の列とそのティックを同時に借用します。
+    fn column_with_ticks_mut<T: Component>(
+        &mut self,
+    ) -> Option<(&mut Vec<T>, &mut Vec<ComponentTicks>)> {
+        let index = self.column_index(TypeId::of::<T>())?;
+        let column = self.columns[index]
+            .as_any_mut()
+            .downcast_mut::<ColumnCell<T>>()?;
+        Some((column.get_mut(), self.ticks[index].get_mut()))
+    }
+
+    /// 行を削除し、空いた位置に移動してきたエンティティがあれば返します。
+    fn swap_remove_entity(&mut self, row: usize) -> Option<Entity> {
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+}
+
+#[derive(Copy, Clone)]
+struct EntityLocation {
+    archetype: usize,
+    row: usize,
+}
+
+struct EntityMeta {
+    generation: u32,
+    location: Option<EntityLocation>,
+}
+
+/// エンティティの位置。ID の割り当ては `EntityAllocator` が行い、`meta` は配置済みの
+/// エンティティの分だけ伸びます（予約されただけの ID は範囲外か `location: None`）。
+#[derive(Default)]
+struct Entities {
+    meta: Vec<EntityMeta>,
+    allocator: EntityAllocator,
+}
+
+impl Entities {
+    fn reserve(&self) -> Entity {
+        Entity::from_allocator(&self.allocator)
+    }
+
+    /// 予約済みの `entity` を `location` に配置します。
+    fn place(&mut self, entity: Entity, location: EntityLocation) {
+        let index = entity.index as usize;
+        if self.meta.len() <= index {
+            self.meta.resize_with(index + 1, || EntityMeta {
+                generation: 0,
+                location: None,
+            });
+        }
+        self.meta[index] = EntityMeta {
+            generation: entity.generation,
+            location: Some(location),
+        };
+    }
+
+    /// 予約済みで、まだ配置されていなければ `true`。
+    fn is_reserved(&self, entity: Entity) -> bool {
+        self.allocator.is_allocated(entity.index, entity.generation)
+            && self
+                .meta
+                .get(entity.index as usize)
+                .is_none_or(|meta| meta.location.is_none())
+    }
+
+    fn free(&mut self, entity: Entity) {
+        self.meta[entity.index as usize].location = None;
+        self.allocator.free(entity.index);
+    }
+
+    fn location(&self, entity: Entity) -> Option<EntityLocation> {
+        self.meta
+            .get(entity.index as usize)
+            .filter(|meta| meta.generation == entity.generation)
+            .and_then(|meta| meta.location)
+    }
+
+    fn set_location(&mut self, entity: Entity, location: EntityLocation) {
+        self.meta[entity.index as usize].location = Some(location);
+    }
+}
+
+pub struct World {
+    entities: Entities,
+    // index 0 は常にコンポーネントを持たない空のアーキタイプです。
+    archetypes: Vec<Archetype>,
+    archetype_index: HashMap<Vec<TypeId>, usize>,
+    trackers: ChangeTrackers,
+    hooks: ComponentHooks,
+    names: NameIndex,
+    sparse: SparseStorages,
+    components: ComponentRegistry,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        let mut archetype_index = HashMap::new();
+        archetype_index.insert(Vec::new(), 0);
+        let mut world = Self {
+            entities: Entities::default(),
+            archetypes: vec![Archetype::empty()],
+            archetype_index,
+            trackers: ChangeTrackers::default(),
+            hooks: ComponentHooks::default(),
+            names: NameIndex::default(),
+            sparse: SparseStorages::default(),
+            components: ComponentRegistry::default(),
+        };
+        world.register_name_hooks();
+        world
+    }
+
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.entities.reserve();
+        self.spawn_reserved(entity, bundle);
+        entity
+    }
+
+    /// `reserve_entity` / `Commands::spawn` で予約した ID にバンドルを配置します。
+    ///
+    /// 予約済みでない（既に生存している、または解放済みの）ID なら何もせずに `false` を返します。
+    pub fn spawn_reserved<B: Bundle>(&mut self, entity: Entity, bundle: B) -> bool {
+        if !self.entities.is_reserved(entity) {
+            return false;
+        }
+        self.register_components::<B>();
+        let targets = self.insert_targets::<B>(entity);
+        let archetype = self.archetype_with::<B>(0);
+        let row = self.archetypes[archetype].entities.len();
+        self.entities
+            .place(entity, EntityLocation { archetype, row });
+        let dst = &mut self.archetypes[archetype];
+        dst.entities.push(entity);
+        bundle.write(&mut RowWriter {
+            archetype: dst,
+            row,
+            entity,
+            sparse: &mut self.sparse,
+            tick: self.trackers.change_tick(),
+        });
+        self.run_insert_hooks(entity, targets);
+        true
+    }
+
+    /// まだ何も配置されていない ID を予約します。`spawn_reserved` で配置するまで `contains` は `false` です。
+    pub fn reserve_entity(&self) -> Entity {
+        self.entities.reserve()
+    }
+
+    pub(crate) fn allocator(&self) -> &EntityAllocator {
+        &self.entities.allocator
+    }
+
+    pub(crate) fn hooks(&self) -> &ComponentHooks {
+        &self.hooks
+    }
+
+    pub(crate) fn hooks_mut(&mut self) -> &mut ComponentHooks {
+        &mut self.hooks
+    }
+
+    pub(crate) fn names(&self) -> &NameIndex {
+        &self.names
+    }
+
+    pub(crate) fn names_mut(&mut self) -> &mut NameIndex {
+        &mut self.names
+    }
+
+    pub(crate) fn sparse(&self) -> &SparseStorages {
+        &self.sparse
+    }
+
+    pub(crate) fn components(&self) -> &ComponentRegistry {
+        &self.components
+    }
+
+    pub(crate) fn components_mut(&mut self) -> &mut ComponentRegistry {
+        &mut self.components
+    }
+
+    /// 生存しているエンティティの数。予約しただけの ID は含みません。
+    pub fn entity_count(&self) -> usize {
+        self.archetypes
+            .iter()
+            .map(|archetype| archetype.entities.len())
+            .sum()
+    }
+
+    /// `entity` のアーキタイプに格納されたコンポーネント型。
+    pub(crate) fn table_types_of(&self, entity: Entity) -> Option<Vec<TypeId>> {
+        let location = self.entities.location(entity)?;
+        Some(self.archetypes[location.archetype].types.clone())
+    }
+
+    /// アーキタイプごとのコンポーネント型とエンティティ数。
+    pub(crate) fn table_layouts(&self) -> Vec<(Vec<TypeId>, usize)> {
+        self.archetypes
+            .iter()
+            .map(|archetype| (archetype.types.clone(), archetype.entities.len()))
+            .collect()
+    }
+
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        let Some(location) = self.entities.location(entity) else {
+            return false;
+        };
+        let mut types = self.archetypes[location.archetype].types.clone();
+        types.extend(self.sparse.types_of(entity));
+        self.run_remove_hooks(entity, &types);
+        // フックが構造を変えている場合があるので取り直します。
+        let Some(location) = self.entities.location(entity) else {
+            return true;
+        };
+        let archetype = &mut self.archetypes[location.archetype];
+        for ((id, column), ticks) in archetype
+            .types
+            .iter()
+            .zip(archetype.columns.iter_mut())
+            .zip(archetype.ticks.iter_mut())
+        {
+            column.swap_remove_drop(location.row);
+            ticks.get_mut().swap_remove(location.row);
+            self.trackers.record_removed(*id, entity);
+        }
+        if let Some(moved) = archetype.swap_remove_entity(location.row) {
+            self.entities.set_location(moved, location);
+        }
+        for id in self.sparse.remove_all(entity) {
+            self.trackers.record_removed(id, entity);
+        }
+        self.entities.free(entity);
+        true
+    }
+
+    /// フック、オブザーバー、削除の記録を通さずに全エンティティを取り除きます。
+    /// ID はアロケーターに残るので、スナップショットの復元で戻します。
+    pub(crate) fn clear_raw(&mut self) {
+        for archetype in &mut self.archetypes {
+            archetype.columns = archetype
+                .columns
+                .iter()
+                .map(|column| column.new_empty())
+                .collect();
+            archetype.ticks = archetype.types.iter().map(|_| ColumnCell::new()).collect();
+            archetype.entities.clear();
+        }
+        self.entities.meta.clear();
+        self.sparse.clear();
+    }
+
+    /// フックを呼ばずに `component` を `ticks` のまま追加します。スナップショットの復元で使います。
+    pub(crate) fn insert_raw<T: Component>(
+        &mut self,
+        entity: Entity,
+        component: T,
+        ticks: ComponentTicks,
+    ) -> bool {
+        let Some(location) = self.entities.location(entity) else {
+            return false;
+        };
+        self.register_components::<T>();
+        if is_sparse::<T>() {
+            self.sparse
+                .get_or_insert::<T>()
+                .insert_with_ticks(entity, component, ticks);
+            return true;
+        }
+        let target = self.archetype_with::<T>(location.archetype);
+        let row = if target == location.archetype {
+            location.row
+        } else {
+            self.move_entity(entity, location, target).row
+        };
+        let (column, column_ticks) = self.archetypes[target]
+            .column_with_ticks_mut::<T>()
+            .expect("archetype is missing a bundle column");
+        if column.len() == row {
+            column.push(component);
+            column_ticks.push(ticks);
+        } else {
+            column[row] = component;
+            column_ticks[row] = ticks;
+        }
+        true
+    }
+
+    /// バンドルの各コンポーネントを追加します。既に持っているコンポーネントは上書きされます。
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) -> bool {
+        let Some(location) = self.entities.location(entity) else {
+            return false;
+        };
+        self.register_components::<B>();
+        let targets = self.insert_targets::<B>(entity);
+        let target = self.archetype_with::<B>(location.archetype);
+        let row = if target == location.archetype {
+            location.row
+        } else {
+            self.move_entity(entity, location, target).row
+        };
+        bundle.write(&mut RowWriter {
+            archetype: &mut self.archetypes[target],
+            row,
+            entity,
+            sparse: &mut self.sparse,
+            tick: self.trackers.change_tick(),
+        });
+        self.run_insert_hooks(entity, targets);
+        true
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.remove_bundle::<T>(entity)
+    }
+
+    /// バンドルのコンポーネントをすべて取り除いて返します。
+    ///
+    /// 1 つでも欠けている場合は何もせずに `None` を返します。
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) -> Option<B> {
+        struct HasAll<'a>(&'a Archetype, &'a SparseStorages, Entity, bool);
+        impl TypeVisitor for HasAll<'_> {
+            fn visit<T: Component>(&mut self) {
+                self.3 &= if is_sparse::<T>() {
+                    self.1.contains::<T>(self.2)
+                } else {
+                    self.0.column_index(TypeId::of::<T>()).is_some()
+                };
+            }
+        }
+
+        let has_all = |world: &World| {
+            let location = world.entities.location(entity)?;
+            let archetype = &world.archetypes[location.archetype];
+            let mut has_all = HasAll(archetype, &world.sparse, entity, true);
+            B::visit_types(&mut has_all);
+            has_all.3.then_some(location)
+        };
+
+        has_all(self)?;
+        self.run_remove_hooks(entity, &bundle_type_ids::<B>());
+        // フックが先にコンポーネントを取り除いた場合は何もしません。
+        let location = has_all(self)?;
+        let target = self.archetype_without::<B>(location.archetype);
+        let bundle = B::read(&mut RowReader {
+            archetype: &mut self.archetypes[location.archetype],
+            row: location.row,
+            entity,
+            sparse: &mut self.sparse,
+            trackers: &mut self.trackers,
+        });
+        // 疎集合のコンポーネントだけならアーキタイプは変わりません。
+        if target != location.archetype {
+            self.move_entity(entity, location, target);
+        }
+        Some(bundle)
+    }
+
+    /// エンティティが生存していれば `true`。
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.location(entity).is_some()
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        let location = self.entities.location(entity)?;
+        if is_sparse::<T>() {
+            return self.sparse.get::<T>()?.get(entity).map(Ref);
+        }
+        let column = self.archetypes[location.archetype].column::<T>()?;
+        Some(Ref(&column.get()[location.row]))
+    }
+
+    /// 可変参照を返します。呼び出した時点でコンポーネントは変更済みとして記録されます。
+    ///
+    /// `Component::MUTABLE` が `false` の型（`Name` など）では panic します。
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<RefMut<'_, T>> {
+        assert_mutable::<T>();
+        let location = self.entities.location(entity)?;
+        let tick = self.trackers.change_tick();
+        if is_sparse::<T>() {
+            return self
+                .sparse
+                .get_mut::<T>()?
+                .get_mut(entity, tick)
+                .map(RefMut);
+        }
+        let (column, ticks) = self.archetypes[location.archetype].column_with_ticks_mut::<T>()?;
+        ticks[location.row].set_changed(tick);
+        Some(RefMut(&mut column[location.row]))
+    }
+
+    pub fn component_ticks<T: Component>(&self, entity: Entity) -> Option<ComponentTicks> {
+        let location = self.entities.location(entity)?;
+        if is_sparse::<T>() {
+            return self.sparse.get::<T>()?.ticks(entity);
+        }
+        let archetype = &self.archetypes[location.archetype];
+        let index = archetype.column_index(TypeId::of::<T>())?;
+        Some(archetype.ticks[index].get()[location.row])
+    }
+
+    pub fn change_tick(&self) -> u64 {
+        self.trackers.change_tick()
+    }
+
+    pub fn last_change_tick(&self) -> u64 {
+        self.trackers.last_change_tick()
+    }
+
+    /// 変更ティックを 1 進めて新しい値を返します。`Schedule` がシステムの実行後に呼びます。
+    pub fn increment_change_tick(&mut self) -> u64 {
+        self.trackers.increment_change_tick()
+    }
+
+    /// `Added` / `Changed` / `removed` が比較に使う「前回の実行」のティックを設定します。
+    pub fn set_last_change_tick(&mut self, tick: u64) {
+        self.trackers.set_last_change_tick(tick);
+    }
+
+    /// `Schedule` が並列に実行するシステムの引数を取り出す前に、そのシステムのティックを
+    /// 設定します。取り出し済みの引数が `World` を借用していても呼べます。
+    pub(crate) fn set_system_ticks(&self, ticks: Ticks) {
+        self.trackers.set_ticks(ticks);
+    }
+
+    /// 前回の `clear_trackers` より前に記録された削除を破棄します。フレームの終わりに呼びます。
+    pub fn clear_trackers(&mut self) {
+        self.trackers.clear();
+    }
+
+    /// 前回の実行以降に `T` が取り除かれた（despawn を含む）エンティティ。
+    pub fn removed<T: Component>(&self) -> RemovedComponents<'_, T> {
+        self.trackers
+            .removed
+            .reader(self.trackers.last_change_tick())
+    }
+
+    pub fn query_ref<Q: ReadOnlyQueryData>(&self) -> QueryRef<'_, Q> {
+        QueryRef {
+            archetypes: &self.archetypes,
+            sparse: &self.sparse,
+            ticks: self.trackers.ticks(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// 書き込みを含むクエリを作ります。借用が競合するクエリは構築時に panic します。
+    pub fn query_mut<Q: QueryData>(&mut self) -> QueryMut<'_, Q> {
+        self.try_query_mut().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_query_mut<Q: QueryData>(&mut self) -> Result<QueryMut<'_, Q>, QueryError> {
+        self.try_query_filtered_mut()
+    }
+
+    /// フィルタ `F` を型引数で指定して書き込みを含むクエリを作ります。
+    pub fn try_query_filtered_mut<Q: QueryData, F: QueryFilter>(
+        &mut self,
+    ) -> Result<QueryMut<'_, Q, F>, QueryError> {
+        // SAFETY: `&mut self` なので他の借用はありません。
+        unsafe { self.try_query_filtered_unchecked() }
+    }
+
+    /// 共有参照から書き込みを含むクエリを作ります。システムの引数を並列に取り出すときに
+    /// 使い、`&mut World` を作らないので他のシステムの借用と重なりません。
+    ///
+    /// # Safety
+    /// クエリを使う間、`Q` が書き込むコンポーネントへの他の借用と、`Q` が読むコンポーネント
+    /// への書き込みがなく、構造の変更も起きない必要があります。
+    pub(crate) unsafe fn try_query_filtered_unchecked<Q: QueryData, F: QueryFilter>(
+        &self,
+    ) -> Result<QueryMut<'_, Q, F>, QueryError> {
+        check_access::<Q>()?;
+        Ok(QueryMut {
+            archetypes: &self.archetypes,
+            sparse: &self.sparse,
+            ticks: self.trackers.ticks(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// `source` のコンポーネントに `B` を加えたアーキタイプを返します（なければ作成）。
+    fn archetype_with<B: Bundle>(&mut self, source: usize) -> usize {
+        type ColumnFactory = fn() -> Box<dyn Column>;
+        struct Factories(Vec<(TypeId, ColumnFactory)>);
+        impl TypeVisitor for Factories {
+            fn visit<T: Component>(&mut self) {
+                self.0
+                    .push((TypeId::of::<T>(), || Box::new(ColumnCell::<T>::new())));
+            }
+        }
+
+        let key = TypeId::of::<B>();
+        if let Some(&target) = self.archetypes[source].insert_edges.get(&key) {
+            return target;
+        }
+        let src = &self.archetypes[source];
+        let mut types = src.types.clone();
+        for id in sorted_type_ids::<B>() {
+            if let Err(position) = types.binary_search(&id) {
+                types.insert(position, id);
+            }
+        }
+        let target = match self.archetype_index.get(&types) {
+            Some(&target) => target,
+            None => {
+                let mut factories = Factories(Vec::new());
+                B::visit_types(&mut factories);
+                let columns = types
+                    .iter()
+                    .map(|id| match src.column_index(*id) {
+                        Some(index) => src.columns[index].new_empty(),
+                        None => factories
+                            .0
+                            .iter()
+                            .find(|(factory_id, _)| factory_id == id)
+                            .map(|(_, factory)| factory())
+                            .expect("bundle type without a column factory"),
+                    })
+                    .collect();
+                self.push_archetype(types, columns)
+            }
+        };
+        self.archetypes[source].insert_edges.insert(key, target);
+        target
+    }
+
+    /// `source` のコンポーネントから `B` を除いたアーキタイプを返します（なければ作成）。
+    ///
+    /// `source` は `B` のコンポーネントをすべて持っている必要があります。
+    fn archetype_without<B: Bundle>(&mut self, source: usize) -> usize {
+        let key = TypeId::of::<B>();
+        if let Some(&target) = self.archetypes[source].remove_edges.get(&key) {
+            return target;
+        }
+        let removed = sorted_type_ids::<B>();
+        let src = &self.archetypes[source];
+        let kept: Vec<usize> = (0..src.types.len())
+            .filter(|&index| removed.binary_search(&src.types[index]).is_err())
+            .collect();
+        let types: Vec<TypeId> = kept.iter().map(|&index| src.types[index]).collect();
+        let target = match self.archetype_index.get(&types) {
+            Some(&target) => target,
+            None => {
+                let columns = kept
+                    .iter()
+                    .map(|&index| src.columns[index].new_empty())
+                    .collect();
+                self.push_archetype(types, columns)
+            }
+        };
+        self.archetypes[source].remove_edges.insert(key, target);
+        target
+    }
+
+    fn push_archetype(&mut self, types: Vec<TypeId>, columns: Vec<Box<dyn Column>>) -> usize {
+        let index = self.archetypes.len();
+        self.archetype_index.insert(types.clone(), index);
+        self.archetypes.push(Archetype {
+            ticks: types.iter().map(|_| ColumnCell::new()).collect(),
+            types,
+            columns,
+            entities: Vec::new(),
+            insert_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
+        });
+        index
+    }
+
+    /// エンティティの行を `target` アーキタイプへ移します。
+    ///
+    /// 移動元にあって移動先にない列は、呼び出し側があらかじめ該当行を取り除いておく必要があります。
+    fn move_entity(
+        &mut self,
+        entity: Entity,
+        location: EntityLocation,
+        target: usize,
+    ) -> EntityLocation {
+        let (src, dst) = pair_mut(&mut self.archetypes, location.archetype, target);
+        for ((id, column), ticks) in src
+            .types
+            .iter()
+            .zip(src.columns.iter_mut())
+            .zip(src.ticks.iter_mut())
+        {
+            if let Some(index) = dst.column_index(*id) {
+                column.swap_remove_into(location.row, dst.columns[index].as_mut());
+                dst.ticks[index]
+                    .get_mut()
+                    .push(ticks.get_mut().swap_remove(location.row));
+            }
+        }
+        if let Some(moved) = src.swap_remove_entity(location.row) {
+            self.entities.set_location(moved, location);
+        }
+        let new_location = EntityLocation {
+            archetype: target,
+            row: dst.entities.len(),
+        };
+        dst.entities.push(entity);
+        self.entities.set_location(entity, new_location);
+        new_location
+    }
+}
+
+/// バンドルのコンポーネントをアーキタイプの `row` 行目に書き込みます。
+///
+/// 列の長さが `row` と等しければ末尾に追加し（追加として記録）、そうでなければ既存の値を
+/// 上書きします（変更として記録）。疎集合のコンポーネントは `sparse` に書き込みます。
+struct RowWriter<'a> {
+    archetype: &'a mut Archetype,
+    row: usize,
+    entity: Entity,
+    sparse: &'a mut SparseStorages,
+    tick: u64,
+}
+
+impl ComponentWriter for RowWriter<'_> {
+    fn write<T: Component>(&mut self, component: T) {
+        if is_sparse::<T>() {
+            self.sparse
+                .get_or_insert::<T>()
+                .insert(self.entity, component, self.tick);
+            return;
+        }
+        let (column, ticks) = self
+            .archetype
+            .column_with_ticks_mut::<T>()
+            .expect("archetype is missing a bundle column");
+        if column.len() == self.row {
+            column.push(component);
+            ticks.push(ComponentTicks::new(self.tick));
+        } else {
+            column[self.row] = component;
+            ticks[self.row].set_changed(self.tick);
+        }
+    }
+}
+
+/// アーキタイプの `row` 行目からコンポーネントを取り出し、削除として記録します。
+struct RowReader<'a> {
+    archetype: &'a mut Archetype,
+    row: usize,
+    entity: Entity,
+    sparse: &'a mut SparseStorages,
+    trackers: &'a mut ChangeTrackers,
+}
+
+impl ComponentReader for RowReader<'_> {
+    fn read<T: Component>(&mut self) -> T {
+        if is_sparse::<T>() {
+            let component = self
+                .sparse
+                .get_mut::<T>()
+                .and_then(|set| set.remove(self.entity))
+                .expect("entity is missing a sparse bundle component");
+            self.trackers.record_removed(TypeId::of::<T>(), self.entity);
+            return component;
+        }
+        let (column, ticks) = self
+            .archetype
+            .column_with_ticks_mut::<T>()
+            .expect("archetype is missing a bundle column");
+        ticks.swap_remove(self.row);
+        self.trackers.record_removed(TypeId::of::<T>(), self.entity);
+        column.swap_remove(self.row)
+    }
+}
+
+fn pair_mut<T>(items: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    assert_ne!(a, b);
+    if a < b {
+        let (left, right) = items.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = items.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+/// アーキタイプの列。書き込みも共有参照から行い、同じ列の借用が重ならないことは
+/// クエリとシステムの借用の検査が保証します。
+struct ArchetypeColumns<'a>(&'a Archetype);
+
+impl ArchetypeColumns<'_> {
+    fn column<T: Component>(&self) -> &ColumnCell<T> {
+        self.0
+            .column::<T>()
+            .expect("query column is missing from a matched archetype")
+    }
+
+    fn ticks<T: Component>(&self) -> &ColumnCell<ComponentTicks> {
+        let index = self
+            .0
+            .column_index(TypeId::of::<T>())
+            .expect("query column is missing from a matched archetype");
+        &self.0.ticks[index]
+    }
+}
+
+impl ColumnSource for ArchetypeColumns<'_> {
+    fn types(&self) -> &[TypeId] {
+        &self.0.types
+    }
+    fn column_ptr<T: Component>(&self) -> *const T {
+        self.column::<T>().get().as_ptr()
+    }
+    fn ticks_ptr<T: Component>(&self) -> *const ComponentTicks {
+        self.ticks::<T>().get().as_ptr()
+    }
+    unsafe fn column_ptr_mut<T: Component>(&self) -> *mut T {
+        self.column::<T>().ptr_mut()
+    }
+    unsafe fn ticks_ptr_mut<T: Component>(&self) -> *mut ComponentTicks {
+        self.ticks::<T>().ptr_mut()
+    }
+}
+
+fn matches<Q: QueryData, F: QueryFilter>(archetype: &Archetype) -> bool {
+    Q::matches_archetype(&archetype.types) && F::matches_archetype(&archetype.types)
+}
+
+pub struct QueryRef<'w, Q: ReadOnlyQueryData, F: QueryFilter = ()> {
+    archetypes: &'w [Archetype],
+    sparse: &'w SparseStorages,
+    ticks: Ticks,
+    _marker: PhantomData<fn() -> (Q, F)>,
+}
+
+impl<'w, Q: ReadOnlyQueryData, F: QueryFilter> QueryRef<'w, Q, F> {
+    pub fn filter<G: QueryFilter>(self) -> QueryRef<'w, Q, (F, G)> {
+        QueryRef {
+            archetypes: self.archetypes,
+            sparse: self.sparse,
+            ticks: self.ticks,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with<T: Component>(self) -> QueryRef<'w, Q, (F, With<T>)> {
+        self.filter()
+    }
+
+    pub fn without<T: Component>(self) -> QueryRef<'w, Q, (F, Without<T>)> {
+        self.filter()
+    }
+
+    pub fn iter<'a>(
+        &'a mut self,
+    ) -> impl Iterator<Item = (Entity, Q::Item<'a>)> + 'a + use<'a, 'w, Q, F> {
+        let ticks = self.ticks;
+        let sparse = self.sparse;
+        self.archetypes
+            .iter()
+            .filter(|archetype| matches::<Q, F>(archetype))
+            .flat_map(move |archetype| {
+                let state = Q::init_state(&mut ArchetypeColumns(archetype), ticks);
+                let filter = F::init_state(&mut ArchetypeColumns(archetype), ticks);
+                archetype
+                    .entities
+                    .iter()
+                    .enumerate()
+                    // SAFETY: アーキタイプはクエリに一致し、`row` は行数の範囲内です。
+                    // 読み取り専用のクエリなので共有借用同士が重なっても問題ありません。
+                    .filter(move |(row, &entity)| {
+                        let row_matches = unsafe { F::filter_row(filter, *row) };
+                        row_matches
+                            && Q::matches_sparse(entity, sparse)
+                            && F::filter_sparse(entity, sparse, ticks)
+                    })
+                    .map(move |(row, &entity)| {
+                        (entity, unsafe { Q::fetch(state, row, entity, sparse) })
+                    })
+            })
+    }
+}
+
+// 列へは共有参照から書き込みます。`&mut World` から作るか、`SystemAccess` で競合がないことを
+// 検査したシステムの引数として作るので、書き込む列を他の誰かが同時に借用することはありません。
+pub struct QueryMut<'w, Q: QueryData, F: QueryFilter = ()> {
+    archetypes: &'w [Archetype],
+    sparse: &'w SparseStorages,
+    ticks: Ticks,
+    _marker: PhantomData<fn() -> (Q, F)>,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> QueryMut<'w, Q, F> {
+    pub fn filter<G: QueryFilter>(self) -> QueryMut<'w, Q, (F, G)> {
+        QueryMut {
+            archetypes: self.archetypes,
+            sparse: self.sparse,
+            ticks: self.ticks,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with<T: Component>(self) -> QueryMut<'w, Q, (F, With<T>)> {
+        self.filter()
+    }
+
+    pub fn without<T: Component>(self) -> QueryMut<'w, Q, (F, Without<T>)> {
+        self.filter()
+    }
+
+    pub fn iter(&mut self) -> impl Iterator<Item = (Entity, Q::Item<'_>)> + '_ + use<'_, 'w, Q, F> {
+        let ticks = self.ticks;
+        let sparse = self.sparse;
+        self.archetypes
+            .iter()
+            .filter(|archetype| matches::<Q, F>(archetype))
+            .flat_map(move |archetype| {
+                let filter = F::init_state(&mut ArchetypeColumns(archetype), ticks);
+                let state = Q::init_state(&mut ArchetypeColumns(archetype), ticks);
+                archetype
+                    .entities
+                    .iter()
+                    .enumerate()
+                    // SAFETY: 構築時に借用の競合がないことを検査済みで、各行は一度しか
+                    // 返さないため可変参照が重なることはありません。フィルタはティックを
+                    // 読むだけで、`fetch` より先に評価されます。反復中は `'w` の間
+                    // `World` の構造が変わらないので、疎集合の追加/削除は起きません。
+                    .filter(move |(row, &entity)| {
+                        let row_matches = unsafe { F::filter_row(filter, *row) };
+                        row_matches
+                            && Q::matches_sparse(entity, sparse)
+                            && F::filter_sparse(entity, sparse, ticks)
+                    })
+                    .map(move |(row, &entity)| {
+                        (entity, unsafe { Q::fetch(state, row, entity, sparse) })
+                    })
+            })
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// エンティティ ID（インデックスと世代）の割り当て。
+///
+/// `World` と、その `World` から作った `Commands` で共有します。`Commands` は `World` を
+/// 借用せずに ID を予約でき、予約した ID は適用時にそのまま使われます。
+/// 世代は 1 から始まり、0 にはなりません（hecs の `Entity` と互換にするため）。
+#[derive(Clone, Default)]
+pub(crate) struct EntityAllocator(Arc<Mutex<AllocatorState>>);
+
+#[derive(Clone, Default)]
+struct AllocatorState {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+#[derive(Clone)]
+struct Slot {
+    generation: u32,
+    allocated: bool,
+}
+
+impl EntityAllocator {
+    /// 新しい `(index, generation)` を割り当てます。解放済みのインデックスを優先して再利用します。
+    pub(crate) fn alloc(&self) -> (u32, u32) {
+        let mut state = self.lock();
+        if let Some(index) = state.free.pop() {
+            let slot = &mut state.slots[index as usize];
+            slot.allocated = true;
+            (index, slot.generation)
+        } else {
+            let index = u32::try_from(state.slots.len()).expect("too many entities");
+            state.slots.push(Slot {
+                generation: 1,
+                allocated: true,
+            });
+            (index, 1)
+        }
+    }
+
+    /// `(index, generation)` が割り当て済み（予約中を含む）で、まだ解放されていなければ `true`。
+    pub(crate) fn is_allocated(&self, index: u32, generation: u32) -> bool {
+        self.lock()
+            .slots
+            .get(index as usize)
+            .is_some_and(|slot| slot.allocated && slot.generation == generation)
+    }
+
+    /// インデックスを解放し、次に割り当てるときの世代を進めます。
+    pub(crate) fn free(&self, index: u32) {
+        let mut state = self.lock();
+        let slot = &mut state.slots[index as usize];
+        slot.allocated = false;
+        slot.generation = slot.generation.checked_add(1).unwrap_or(1);
+        state.free.push(index);
+    }
+
+    /// 予約したまま使われなかった `(index, generation)` を解放します。すでに解放されているか、
+    /// `restore` で巻き戻されていれば何もしません。
+    pub(crate) fn release(&self, index: u32, generation: u32) {
+        if self.is_allocated(index, generation) {
+            self.free(index);
+        }
+    }
+
+    /// 現在の割り当て状態を写し取ります。
+    pub(crate) fn snapshot(&self) -> AllocatorSnapshot {
+        AllocatorSnapshot(self.lock().clone())
+    }
+
+    /// `snapshot` の時点の割り当て状態に戻します。その後に予約した ID は無効になります。
+    pub(crate) fn restore(&self, snapshot: &AllocatorSnapshot) {
+        *self.lock() = snapshot.0.clone();
+    }
+
+    /// 同じ `World` の割り当てを共有していれば `true`。
+    pub(crate) fn same_as(&self, other: &EntityAllocator) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, AllocatorState> {
+        // 割り当て中に panic しても状態は壊れないので、poison は無視します。
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// `EntityAllocator::snapshot` で写し取った割り当て状態。
+#[derive(Clone)]
+pub(crate) struct AllocatorSnapshot(AllocatorState);
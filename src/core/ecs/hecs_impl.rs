@@ -0,0 +1,604 @@
+use super::change::{ChangeTrackers, HecsTicks, Ticks};
+use super::entities::EntityAllocator;
+use super::hooks::{bundle_type_ids, ComponentHooks};
+use super::introspection::ComponentRegistry;
+use super::name::NameIndex;
+use super::query::check_access;
+use super::storage::{is_sparse, SparseStorages};
+use super::{
+    assert_mutable, Bundle, Component, ComponentReader, ComponentTicks, ComponentWriter, QueryData,
+    QueryError, QueryFilter, ReadOnlyQueryData, RemovedComponents, TypeVisitor, With, Without,
+};
+use hecs as h;
+use std::any::TypeId;
+use std::ops::{Deref, DerefMut};
+
+// ID は hecs ではなく `EntityAllocator` で割り当て、`spawn_at` で配置します。
+pub struct World {
+    inner: h::World,
+    allocator: EntityAllocator,
+    trackers: ChangeTrackers,
+    hooks: ComponentHooks,
+    names: NameIndex,
+    sparse: SparseStorages,
+    components: ComponentRegistry,
+}
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Entity(h::Entity);
+pub struct Ref<'a, T: ?Sized>(RefInner<'a, T>);
+
+// 疎集合のコンポーネントは hecs の外にあるので、通常の参照で返します。
+enum RefInner<'a, T: ?Sized> {
+    Table(h::Ref<'a, T>),
+    Sparse(&'a T),
+}
+
+impl Entity {
+    pub(crate) fn from_allocator(allocator: &EntityAllocator) -> Self {
+        let (index, generation) = allocator.alloc();
+        let bits = (u64::from(generation) << 32) | u64::from(index);
+        Self(h::Entity::from_bits(bits).expect("entity generation is never zero"))
+    }
+
+    /// 世代を除いたインデックス。
+    pub(crate) fn index(self) -> u32 {
+        self.0.id()
+    }
+
+    pub(crate) fn generation(self) -> u32 {
+        (self.0.to_bits().get() >> 32) as u32
+    }
+}
+pub struct RefMut<'a, T: ?Sized>(RefMutInner<'a, T>);
+
+enum RefMutInner<'a, T: ?Sized> {
+    Table(h::RefMut<'a, T>),
+    Sparse(&'a mut T),
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized> Deref for Ref<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        match &self.0 {
+            RefInner::Table(component) => component,
+            RefInner::Sparse(component) => component,
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for RefMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        match &self.0 {
+            RefMutInner::Table(component) => component,
+            RefMutInner::Sparse(component) => component,
+        }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match &mut self.0 {
+            RefMutInner::Table(component) => component,
+            RefMutInner::Sparse(component) => component,
+        }
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        let mut world = Self {
+            inner: h::World::new(),
+            allocator: EntityAllocator::default(),
+            trackers: ChangeTrackers::default(),
+            hooks: ComponentHooks::default(),
+            names: NameIndex::default(),
+            sparse: SparseStorages::default(),
+            components: ComponentRegistry::default(),
+        };
+        world.register_name_hooks();
+        world
+    }
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.reserve_entity();
+        self.spawn_reserved(entity, bundle);
+        entity
+    }
+
+    /// `reserve_entity` / `Commands::spawn` で予約した ID にバンドルを配置します。
+    ///
+    /// 予約済みでない（既に生存している、または解放済みの）ID なら何もせずに `false` を返します。
+    pub fn spawn_reserved<B: Bundle>(&mut self, entity: Entity, bundle: B) -> bool {
+        let reserved = self
+            .allocator
+            .is_allocated(entity.index(), entity.generation());
+        if !reserved || self.inner.contains(entity.0) {
+            return false;
+        }
+        self.register_components::<B>();
+        let targets = self.insert_targets::<B>(entity);
+        let mut builder = builder_from(
+            bundle,
+            entity,
+            self.trackers.change_tick(),
+            None,
+            &mut self.sparse,
+        );
+        self.inner.spawn_at(entity.0, builder.build());
+        self.run_insert_hooks(entity, targets);
+        true
+    }
+
+    /// まだ何も配置されていない ID を予約します。`spawn_reserved` で配置するまで `contains` は `false` です。
+    pub fn reserve_entity(&self) -> Entity {
+        Entity::from_allocator(&self.allocator)
+    }
+
+    pub(crate) fn allocator(&self) -> &EntityAllocator {
+        &self.allocator
+    }
+
+    pub(crate) fn hooks(&self) -> &ComponentHooks {
+        &self.hooks
+    }
+
+    pub(crate) fn hooks_mut(&mut self) -> &mut ComponentHooks {
+        &mut self.hooks
+    }
+
+    pub(crate) fn names(&self) -> &NameIndex {
+        &self.names
+    }
+
+    pub(crate) fn names_mut(&mut self) -> &mut NameIndex {
+        &mut self.names
+    }
+
+    pub(crate) fn sparse(&self) -> &SparseStorages {
+        &self.sparse
+    }
+
+    pub(crate) fn components(&self) -> &ComponentRegistry {
+        &self.components
+    }
+
+    pub(crate) fn components_mut(&mut self) -> &mut ComponentRegistry {
+        &mut self.components
+    }
+
+    /// 生存しているエンティティの数。予約しただけの ID は含みません。
+    pub fn entity_count(&self) -> usize {
+        self.inner.len() as usize
+    }
+
+    /// `entity` のアーキタイプに格納されたコンポーネント型。`HecsTicks` の型も含みます。
+    pub(crate) fn table_types_of(&self, entity: Entity) -> Option<Vec<TypeId>> {
+        let entity_ref = self.inner.entity(entity.0).ok()?;
+        Some(entity_ref.component_types().collect())
+    }
+
+    /// アーキタイプごとのコンポーネント型とエンティティ数。
+    pub(crate) fn table_layouts(&self) -> Vec<(Vec<TypeId>, usize)> {
+        self.inner
+            .archetypes()
+            .map(|archetype| {
+                (
+                    archetype.component_types().collect(),
+                    archetype.len() as usize,
+                )
+            })
+            .collect()
+    }
+
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        let Ok(entity_ref) = self.inner.entity(entity.0) else {
+            return false;
+        };
+        // ティック用のコンポーネントの型も含まれますが、読み手が問い合わせることはありません。
+        let mut types: Vec<TypeId> = entity_ref.component_types().collect();
+        types.extend(self.sparse.types_of(entity));
+        self.run_remove_hooks(entity, &types);
+        // フックが構造を変えている場合があるので取り直します。
+        let Ok(entity_ref) = self.inner.entity(entity.0) else {
+            return true;
+        };
+        let mut types: Vec<TypeId> = entity_ref.component_types().collect();
+        types.extend(self.sparse.remove_all(entity));
+        for id in types {
+            self.trackers.record_removed(id, entity);
+        }
+        self.inner
+            .despawn(entity.0)
+            .expect("entity was checked to be alive");
+        self.allocator.free(entity.0.id());
+        true
+    }
+
+    /// フック、オブザーバー、削除の記録を通さずに全エンティティを取り除きます。
+    /// ID はアロケーターに残るので、スナップショットの復元で戻します。
+    pub(crate) fn clear_raw(&mut self) {
+        self.inner.clear();
+        self.sparse.clear();
+    }
+
+    /// フックを呼ばずに `component` を `ticks` のまま追加します。スナップショットの復元で使います。
+    pub(crate) fn insert_raw<T: Component>(
+        &mut self,
+        entity: Entity,
+        component: T,
+        ticks: ComponentTicks,
+    ) -> bool {
+        if !self.inner.contains(entity.0) {
+            return false;
+        }
+        self.register_components::<T>();
+        if is_sparse::<T>() {
+            self.sparse
+                .get_or_insert::<T>()
+                .insert_with_ticks(entity, component, ticks);
+        } else {
+            self.inner
+                .insert(entity.0, (component, HecsTicks::<T>::new(ticks)))
+                .expect("entity was checked to be alive");
+        }
+        true
+    }
+
+    /// バンドルの各コンポーネントを追加します。既に持っているコンポーネントは上書きされます。
+    pub fn insert<B: Bundle>(&mut self, entity: Entity, bundle: B) -> bool {
+        if !self.inner.contains(entity.0) {
+            return false;
+        }
+        self.register_components::<B>();
+        let existing = self
+            .inner
+            .entity(entity.0)
+            .expect("entity was checked to be alive");
+        let targets = self.insert_targets::<B>(entity);
+        let mut builder = builder_from(
+            bundle,
+            entity,
+            self.trackers.change_tick(),
+            Some(existing),
+            &mut self.sparse,
+        );
+        self.inner
+            .insert(entity.0, builder.build())
+            .expect("entity was checked to be alive");
+        self.run_insert_hooks(entity, targets);
+        true
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        if !self.has::<T>(entity) {
+            return None;
+        }
+        self.run_remove_hooks(entity, &[TypeId::of::<T>()]);
+        self.take::<T>(entity)
+    }
+
+    /// 格納方法によらず `entity` が `T` を持っていれば `true`。
+    fn has<T: Component>(&self, entity: Entity) -> bool {
+        if is_sparse::<T>() {
+            self.inner.contains(entity.0) && self.sparse.contains::<T>(entity)
+        } else {
+            self.inner.satisfies::<&T>(entity.0).unwrap_or(false)
+        }
+    }
+
+    /// フックを呼ばずに `T` を取り除きます。
+    fn take<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        let component = if is_sparse::<T>() {
+            self.sparse.get_mut::<T>()?.remove(entity)?
+        } else {
+            self.inner.remove::<(T, HecsTicks<T>)>(entity.0).ok()?.0
+        };
+        self.trackers.record_removed(TypeId::of::<T>(), entity);
+        Some(component)
+    }
+
+    /// バンドルのコンポーネントをすべて取り除いて返します。
+    ///
+    /// 1 つでも欠けている場合は何もせずに `None` を返します。
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) -> Option<B> {
+        struct HasAll<'a>(&'a World, Entity, bool);
+        impl TypeVisitor for HasAll<'_> {
+            fn visit<T: Component>(&mut self) {
+                self.2 &= self.0.has::<T>(self.1);
+            }
+        }
+        struct Take<'a>(&'a mut World, Entity);
+        impl ComponentReader for Take<'_> {
+            fn read<T: Component>(&mut self) -> T {
+                self.0
+                    .take::<T>(self.1)
+                    .expect("bundle component disappeared during removal")
+            }
+        }
+        let has_all = |world: &World| {
+            if !world.inner.contains(entity.0) {
+                return false;
+            }
+            let mut has_all = HasAll(world, entity, true);
+            B::visit_types(&mut has_all);
+            has_all.2
+        };
+
+        if !has_all(self) {
+            return None;
+        }
+        self.run_remove_hooks(entity, &bundle_type_ids::<B>());
+        // フックが先にコンポーネントを取り除いた場合は何もしません。
+        if !has_all(self) {
+            return None;
+        }
+        Some(B::read(&mut Take(self, entity)))
+    }
+
+    /// エンティティが生存していれば `true`。
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.inner.contains(entity.0)
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        if is_sparse::<T>() {
+            let component = self.sparse.get::<T>()?.get(entity)?;
+            return Some(Ref(RefInner::Sparse(component)));
+        }
+        self.inner
+            .get::<&T>(entity.0)
+            .ok()
+            .map(|component| Ref(RefInner::Table(component)))
+    }
+
+    /// 可変参照を返します。呼び出した時点でコンポーネントは変更済みとして記録されます。
+    ///
+    /// `Component::MUTABLE` が `false` の型（`Name` など）では panic します。
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<RefMut<'_, T>> {
+        assert_mutable::<T>();
+        let tick = self.trackers.change_tick();
+        if is_sparse::<T>() {
+            let component = self.sparse.get_mut::<T>()?.get_mut(entity, tick)?;
+            return Some(RefMut(RefMutInner::Sparse(component)));
+        }
+        let component = self.inner.get::<&mut T>(entity.0).ok()?;
+        if let Ok(ticks) = self.inner.get::<&HecsTicks<T>>(entity.0) {
+            ticks.set_changed(tick);
+        }
+        Some(RefMut(RefMutInner::Table(component)))
+    }
+
+    pub fn component_ticks<T: Component>(&self, entity: Entity) -> Option<ComponentTicks> {
+        if is_sparse::<T>() {
+            return self.sparse.get::<T>()?.ticks(entity);
+        }
+        self.inner
+            .get::<&HecsTicks<T>>(entity.0)
+            .ok()
+            .map(|ticks| ticks.get())
+    }
+
+    pub fn change_tick(&self) -> u64 {
+        self.trackers.change_tick()
+    }
+
+    pub fn last_change_tick(&self) -> u64 {
+        self.trackers.last_change_tick()
+    }
+
+    /// 変更ティックを 1 進めて新しい値を返します。`Schedule` がシステムの実行後に呼びます。
+    pub fn increment_change_tick(&mut self) -> u64 {
+        self.trackers.increment_change_tick()
+    }
+
+    /// `Added` / `Changed` / `removed` が比較に使う「前回の実行」のティックを設定します。
+    pub fn set_last_change_tick(&mut self, tick: u64) {
+        self.trackers.set_last_change_tick(tick);
+    }
+
+    /// `Schedule` が並列に実行するシステムの引数を取り出す前に、そのシステムのティックを
+    /// 設定します。取り出し済みの引数が `World` を借用していても呼べます。
+    pub(crate) fn set_system_ticks(&self, ticks: Ticks) {
+        self.trackers.set_ticks(ticks);
+    }
+
+    /// 前回の `clear_trackers` より前に記録された削除を破棄します。フレームの終わりに呼びます。
+    pub fn clear_trackers(&mut self) {
+        self.trackers.clear();
+    }
+
+    /// 前回の実行以降に `T` が取り除かれた（despawn を含む）エンティティ。
+    pub fn removed<T: Component>(&self) -> RemovedComponents<'_, T> {
+        self.trackers
+            .removed
+            .reader(self.trackers.last_change_tick())
+    }
+
+    pub fn query_ref<Q: ReadOnlyQueryData>(&self) -> QueryRef<'_, Q> {
+        QueryRef {
+            world: &self.inner,
+            inner: self.inner.query(),
+            sparse: &self.sparse,
+            ticks: self.trackers.ticks(),
+        }
+    }
+
+    /// 書き込みを含むクエリを作ります。借用が競合するクエリは構築時に panic します。
+    pub fn query_mut<Q: QueryData>(&mut self) -> QueryMut<'_, Q> {
+        self.try_query_mut().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_query_mut<Q: QueryData>(&mut self) -> Result<QueryMut<'_, Q>, QueryError> {
+        self.try_query_filtered_mut()
+    }
+
+    /// フィルタ `F` を型引数で指定して書き込みを含むクエリを作ります。
+    pub fn try_query_filtered_mut<Q: QueryData, F: QueryFilter>(
+        &mut self,
+    ) -> Result<QueryMut<'_, Q, F>, QueryError> {
+        // SAFETY: `&mut self` なので他の借用はありません。
+        unsafe { self.try_query_filtered_unchecked() }
+    }
+
+    /// 共有参照から書き込みを含むクエリを作ります。システムの引数を並列に取り出すときに
+    /// 使い、`&mut World` を作らないので他のシステムの借用と重なりません。
+    ///
+    /// # Safety
+    /// クエリを使う間、`Q` が書き込む疎集合のコンポーネントへの他の借用と、`Q` が読む疎集合の
+    /// コンポーネントへの書き込みがなく、構造の変更も起きない必要があります。
+    /// テーブルのコンポーネントは hecs が実行時に借用を検査します。
+    pub(crate) unsafe fn try_query_filtered_unchecked<Q: QueryData, F: QueryFilter>(
+        &self,
+    ) -> Result<QueryMut<'_, Q, F>, QueryError> {
+        check_access::<Q>()?;
+        Ok(QueryMut {
+            world: &self.inner,
+            inner: self.inner.query(),
+            sparse: &self.sparse,
+            ticks: self.trackers.ticks(),
+        })
+    }
+}
+
+/// バンドルの各コンポーネントに `HecsTicks` を添えたビルダーを作ります。
+///
+/// `existing` が既に持っているコンポーネントは追加ティックを引き継ぎ、変更として記録します。
+/// 疎集合のコンポーネントはビルダーに入れず、`sparse` に直接書き込みます。
+fn builder_from<B: Bundle>(
+    bundle: B,
+    entity: Entity,
+    tick: u64,
+    existing: Option<h::EntityRef<'_>>,
+    sparse: &mut SparseStorages,
+) -> h::EntityBuilder {
+    struct Builder<'a> {
+        builder: h::EntityBuilder,
+        entity: Entity,
+        tick: u64,
+        existing: Option<h::EntityRef<'a>>,
+        sparse: &'a mut SparseStorages,
+    }
+    impl ComponentWriter for Builder<'_> {
+        fn write<T: Component>(&mut self, component: T) {
+            if is_sparse::<T>() {
+                self.sparse
+                    .get_or_insert::<T>()
+                    .insert(self.entity, component, self.tick);
+                return;
+            }
+            let previous = self
+                .existing
+                .and_then(|entity| entity.get::<&HecsTicks<T>>().map(|ticks| ticks.get()));
+            let ticks = match previous {
+                Some(mut ticks) => {
+                    ticks.set_changed(self.tick);
+                    ticks
+                }
+                None => ComponentTicks::new(self.tick),
+            };
+            self.builder.add(component).add(HecsTicks::<T>::new(ticks));
+        }
+    }
+    let mut builder = Builder {
+        builder: h::EntityBuilder::new(),
+        entity,
+        tick,
+        existing,
+        sparse,
+    };
+    bundle.write(&mut builder);
+    builder.builder
+}
+
+pub struct QueryRef<'w, Q: ReadOnlyQueryData, F: QueryFilter = ()> {
+    world: &'w h::World,
+    inner: h::QueryBorrow<'w, F::Hecs<Q::Hecs>>,
+    sparse: &'w SparseStorages,
+    ticks: Ticks,
+}
+
+impl<'w, Q: ReadOnlyQueryData, F: QueryFilter> QueryRef<'w, Q, F> {
+    pub fn filter<G: QueryFilter>(self) -> QueryRef<'w, Q, (F, G)> {
+        QueryRef {
+            world: self.world,
+            inner: self.world.query(),
+            sparse: self.sparse,
+            ticks: self.ticks,
+        }
+    }
+
+    pub fn with<T: Component>(self) -> QueryRef<'w, Q, (F, With<T>)> {
+        self.filter()
+    }
+
+    pub fn without<T: Component>(self) -> QueryRef<'w, Q, (F, Without<T>)> {
+        self.filter()
+    }
+
+    pub fn iter<'a>(
+        &'a mut self,
+    ) -> impl Iterator<Item = (Entity, Q::Item<'a>)> + 'a + use<'a, 'w, Q, F> {
+        let ticks = self.ticks;
+        let sparse = self.sparse;
+        self.inner.iter().filter_map(move |(e, item)| {
+            let entity = Entity(e);
+            let item = F::unwrap_hecs::<Q::Hecs>(item, ticks)?;
+            if !Q::matches_sparse(entity, sparse) || !F::filter_sparse(entity, sparse, ticks) {
+                return None;
+            }
+            // SAFETY: 読み取り専用のクエリは疎集合を読むだけです。
+            let item = unsafe { Q::from_hecs(item, ticks, entity, sparse) };
+            Some((entity, item))
+        })
+    }
+}
+
+// 疎集合へは共有参照から書き込みます。`&mut World` から作るか、`SystemAccess` で競合がないことを
+// 検査したシステムの引数として作るので、書き込む型を他の誰かが同時に借用することはありません。
+pub struct QueryMut<'w, Q: QueryData, F: QueryFilter = ()> {
+    world: &'w h::World,
+    inner: h::QueryBorrow<'w, F::Hecs<Q::Hecs>>,
+    sparse: &'w SparseStorages,
+    ticks: Ticks,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> QueryMut<'w, Q, F> {
+    pub fn filter<G: QueryFilter>(self) -> QueryMut<'w, Q, (F, G)> {
+        QueryMut {
+            world: self.world,
+            inner: self.world.query(),
+            sparse: self.sparse,
+            ticks: self.ticks,
+        }
+    }
+
+    pub fn with<T: Component>(self) -> QueryMut<'w, Q, (F, With<T>)> {
+        self.filter()
+    }
+
+    pub fn without<T: Component>(self) -> QueryMut<'w, Q, (F, Without<T>)> {
+        self.filter()
+    }
+
+    pub fn iter(&mut self) -> impl Iterator<Item = (Entity, Q::Item<'_>)> + '_ + use<'_, 'w, Q, F> {
+        let ticks = self.ticks;
+        let sparse = self.sparse;
+        self.inner.iter().filter_map(move |(e, item)| {
+            let entity = Entity(e);
+            let item = F::unwrap_hecs::<Q::Hecs>(item, ticks)?;
+            if !Q::matches_sparse(entity, sparse) || !F::filter_sparse(entity, sparse, ticks) {
+                return None;
+            }
+            // SAFETY: 反復中は `'w` の間 `World` の構造が変わらないので、疎集合の追加/削除は
+            // 起きません。各エンティティは 1 度しか返らず、`check_access` で同じ型への重複した
+            // 可変借用は禁止されています。
+            Some((entity, unsafe { Q::from_hecs(item, ticks, entity, sparse) }))
+        })
+    }
+}
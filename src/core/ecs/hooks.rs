@@ -0,0 +1,264 @@
+//! コンポーネントのライフサイクルフックとオブザーバー。
+//!
+//! フックはコンポーネント型ごとに登録する関数で、`World` の構造変更の中で即座に呼ばれます。
+//!
+//! - on_add: エンティティが持っていなかったコンポーネントが追加されたとき（spawn を含む）
+//! - on_insert: コンポーネントが書き込まれたとき（追加と上書きの両方。on_add の後）
+//! - on_remove: コンポーネントが取り除かれるとき（despawn を含む）。取り除く前に呼ばれるので、
+//!   フックの中でまだ値を読めます
+//!
+//! フックの中で同じエンティティの同じコンポーネントを追加/削除すると再びフックが呼ばれます。
+//!
+//! フックは `World` の中から呼ばれるため `DiContainer` を受け取りません。リソースに触れる処理は
+//! `World::defer` で予約すると、次の `World::flush_observers` で `&mut DiContainer` 付きで実行されます。
+//! 通知をシステムで処理したいだけならオブザーバーを使います。
+//!
+//! オブザーバー（`World::observe`）はフックを使って通知を溜め、`World::flush_observers` で
+//! `DiContainer` の `Events<ComponentAdded<T>>` などへ送ります。`Schedule::run_stage` が
+//! システムの実行ごとに送るので、システムからは通常のイベントとして読めます。
+
+use super::{Bundle, Component, Entity, TypeVisitor, World};
+use crate::core::{DiContainer, Events};
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// コンポーネントのフック。対象のエンティティを受け取ります。
+///
+/// `DiContainer` は渡されないので、リソースを使う処理は `World::defer` で予約します。
+pub type ComponentHook = fn(&mut World, Entity);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum HookKind {
+    Add,
+    Insert,
+    Remove,
+}
+
+type Notification = Box<dyn FnOnce(&mut DiContainer) + Send + Sync>;
+
+/// `World` が持つフックと、まだ送っていないオブザーバーの通知。
+#[derive(Default)]
+pub(crate) struct ComponentHooks {
+    hooks: HashMap<(HookKind, TypeId), Vec<ComponentHook>>,
+    observed: HashSet<TypeId>,
+    // `World::first_install` で登録済みの内部フックの種類。
+    installed: HashSet<TypeId>,
+    pending: Vec<Notification>,
+}
+
+impl ComponentHooks {
+    fn get(&self, kind: HookKind, id: TypeId) -> Option<Vec<ComponentHook>> {
+        self.hooks.get(&(kind, id)).cloned()
+    }
+
+    fn has_any(&self, id: TypeId) -> bool {
+        [HookKind::Add, HookKind::Insert, HookKind::Remove]
+            .iter()
+            .any(|kind| self.hooks.contains_key(&(*kind, id)))
+    }
+}
+
+/// バンドルの挿入でフックを呼ぶコンポーネント。挿入の前に `World::insert_targets` で調べます。
+pub(crate) struct InsertTargets(Vec<(TypeId, bool)>);
+
+/// `T` がエンティティに追加されたことの通知。
+pub struct ComponentAdded<T> {
+    pub entity: Entity,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// `T` がエンティティに書き込まれた（追加または上書きされた）ことの通知。
+pub struct ComponentInserted<T> {
+    pub entity: Entity,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// `T` がエンティティから取り除かれた（despawn を含む）ことの通知。
+pub struct ComponentRemoved<T> {
+    pub entity: Entity,
+    _marker: PhantomData<fn() -> T>,
+}
+
+macro_rules! notification {
+    ($name:ident, $notify:ident) => {
+        impl<T> $name<T> {
+            pub fn new(entity: Entity) -> Self {
+                Self {
+                    entity,
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl<T> Clone for $name<T> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<T> Copy for $name<T> {}
+
+        impl<T> std::fmt::Debug for $name<T> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("entity", &self.entity)
+                    .finish()
+            }
+        }
+
+        impl<T> PartialEq for $name<T> {
+            fn eq(&self, other: &Self) -> bool {
+                self.entity == other.entity
+            }
+        }
+
+        fn $notify<T: Component>(world: &mut World, entity: Entity) {
+            world.defer(move |di| {
+                if let Some(events) = di.get_mut::<Events<$name<T>>>() {
+                    events.send($name::new(entity));
+                }
+            });
+        }
+    };
+}
+
+notification!(ComponentAdded, notify_added);
+notification!(ComponentInserted, notify_inserted);
+notification!(ComponentRemoved, notify_removed);
+
+impl World {
+    /// `T` が追加されたときに呼ぶフックを登録します。
+    pub fn on_add<T: Component>(&mut self, hook: ComponentHook) -> &mut Self {
+        self.register_hook::<T>(HookKind::Add, hook)
+    }
+
+    /// `T` が書き込まれたとき（追加と上書き）に呼ぶフックを登録します。
+    pub fn on_insert<T: Component>(&mut self, hook: ComponentHook) -> &mut Self {
+        self.register_hook::<T>(HookKind::Insert, hook)
+    }
+
+    /// `T` が取り除かれる直前（despawn を含む）に呼ぶフックを登録します。
+    pub fn on_remove<T: Component>(&mut self, hook: ComponentHook) -> &mut Self {
+        self.register_hook::<T>(HookKind::Remove, hook)
+    }
+
+    /// `T` の追加/書き込み/削除を `Events<ComponentAdded<T>>` / `Events<ComponentInserted<T>>` /
+    /// `Events<ComponentRemoved<T>>` へ通知します。`DiContainer` にないイベントは送られません。
+    ///
+    /// 同じ型を何度登録しても通知は 1 回です。
+    pub fn observe<T: Component>(&mut self) -> &mut Self {
+        if self.hooks_mut().observed.insert(TypeId::of::<T>()) {
+            self.on_add::<T>(notify_added::<T>)
+                .on_insert::<T>(notify_inserted::<T>)
+                .on_remove::<T>(notify_removed::<T>);
+        }
+        self
+    }
+
+    /// `DiContainer` を使う処理を予約します。フックからリソースに触れるときに使います。
+    ///
+    /// 予約した処理は次の `flush_observers` で予約した順に実行されます。`Schedule` は
+    /// システムの実行ごとに呼ぶので、システムの中で起きたフックの処理はそのシステムの直後に実行されます。
+    pub fn defer(&mut self, f: impl FnOnce(&mut DiContainer) + Send + Sync + 'static) {
+        self.hooks_mut().pending.push(Box::new(f));
+    }
+
+    /// エンジンが内部で使うフックを `K` ごとに 1 度だけ登録するために、初回だけ `true` を返します。
+    pub(crate) fn first_install<K: 'static>(&mut self) -> bool {
+        self.hooks_mut().installed.insert(TypeId::of::<K>())
+    }
+
+    /// 溜まっているオブザーバーの通知と `defer` で予約した処理を `di` に対して実行します。
+    pub fn flush_observers(&mut self, di: &mut DiContainer) {
+        for notification in std::mem::take(&mut self.hooks_mut().pending) {
+            notification(di);
+        }
+    }
+
+    fn register_hook<T: Component>(&mut self, kind: HookKind, hook: ComponentHook) -> &mut Self {
+        self.hooks_mut()
+            .hooks
+            .entry((kind, TypeId::of::<T>()))
+            .or_default()
+            .push(hook);
+        self
+    }
+
+    /// バンドルのうちフックを持つコンポーネントと、それが新規の追加かどうかを調べます。
+    pub(crate) fn insert_targets<B: Bundle>(&self, entity: Entity) -> InsertTargets {
+        struct Visit<'a> {
+            world: &'a World,
+            entity: Entity,
+            targets: Vec<(TypeId, bool)>,
+        }
+        impl TypeVisitor for Visit<'_> {
+            fn visit<T: Component>(&mut self) {
+                let id = TypeId::of::<T>();
+                if self.world.hooks().has_any(id) {
+                    let added = self.world.get::<T>(self.entity).is_none();
+                    self.targets.push((id, added));
+                }
+            }
+        }
+
+        if self.hooks().hooks.is_empty() {
+            return InsertTargets(Vec::new());
+        }
+        let mut visit = Visit {
+            world: self,
+            entity,
+            targets: Vec::new(),
+        };
+        B::visit_types(&mut visit);
+        InsertTargets(visit.targets)
+    }
+
+    /// 挿入の後に on_add（新規のものだけ）、続いて on_insert を呼びます。
+    pub(crate) fn run_insert_hooks(&mut self, entity: Entity, targets: InsertTargets) {
+        for &(id, added) in &targets.0 {
+            if added {
+                self.run_hooks(HookKind::Add, id, entity);
+            }
+        }
+        for &(id, _) in &targets.0 {
+            self.run_hooks(HookKind::Insert, id, entity);
+        }
+    }
+
+    /// 取り除く前に `types` の on_remove を呼びます。
+    pub(crate) fn run_remove_hooks(&mut self, entity: Entity, types: &[TypeId]) {
+        if self.hooks().hooks.is_empty() {
+            return;
+        }
+        for &id in types {
+            self.run_hooks(HookKind::Remove, id, entity);
+        }
+    }
+
+    fn run_hooks(&mut self, kind: HookKind, id: TypeId, entity: Entity) {
+        let Some(hooks) = self.hooks().get(kind, id) else {
+            return;
+        };
+        for hook in hooks {
+            // 先に呼んだフックが despawn した場合は残りを呼びません。
+            if !self.contains(entity) {
+                return;
+            }
+            hook(self, entity);
+        }
+    }
+}
+
+/// バンドルのコンポーネント型を並び順のまま返します。
+pub(crate) fn bundle_type_ids<B: Bundle>() -> Vec<TypeId> {
+    struct Collect(Vec<TypeId>);
+    impl TypeVisitor for Collect {
+        fn visit<T: Component>(&mut self) {
+            self.0.push(TypeId::of::<T>());
+        }
+    }
+    let mut collect = Collect(Vec::new());
+    B::visit_types(&mut collect);
+    collect.0
+}
@@ -0,0 +1,182 @@
+//! デバッグツールやテスト向けの `World` の統計と内部構造の問い合わせ。
+//!
+//! コンポーネント型の名前と大きさは `spawn` / `insert` で初めて書き込まれたときに記録します。
+//! メモリ量はコンポーネントとティックの大きさ×要素数から見積もる概算で、`Vec` の予備容量や
+//! バックエンド内部の管理領域は含みません。
+
+use super::{Bundle, Component, ComponentTicks, Entity, StorageType, TypeVisitor, World};
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+
+/// `World` に書き込まれたことのあるコンポーネント型の情報。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentInfo {
+    pub name: &'static str,
+    /// 1 つあたりの大きさ（バイト）。
+    pub size: usize,
+    pub storage: StorageType,
+}
+
+/// 1 つのアーキタイプの概要。疎集合のコンポーネントはアーキタイプに含まれません。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchetypeInfo {
+    /// コンポーネント型名（名前順）。
+    pub components: Vec<&'static str>,
+    pub entity_count: usize,
+    /// コンポーネントとティックの推定メモリ量（バイト）。
+    pub estimated_bytes: usize,
+}
+
+/// 1 種類の疎集合の概要。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseSetInfo {
+    pub component: &'static str,
+    pub entity_count: usize,
+    /// 値・ティック・エンティティと索引の推定メモリ量（バイト）。
+    pub estimated_bytes: usize,
+}
+
+/// `World::stats` の結果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldStats {
+    pub entity_count: usize,
+    /// エンティティを 1 つ以上持つアーキタイプの数。
+    pub archetype_count: usize,
+    /// 書き込まれたことのあるコンポーネント型の数。
+    pub component_type_count: usize,
+    pub estimated_bytes: usize,
+}
+
+/// 型 ID→コンポーネント情報。
+#[derive(Default)]
+pub(crate) struct ComponentRegistry {
+    infos: HashMap<TypeId, ComponentInfo>,
+    // 登録済みのバンドル型。同じバンドルを何度も走査しないためのものです。
+    bundles: HashSet<TypeId>,
+}
+
+impl ComponentRegistry {
+    pub(crate) fn register_bundle<B: Bundle>(&mut self) {
+        struct Register<'a>(&'a mut HashMap<TypeId, ComponentInfo>);
+        impl TypeVisitor for Register<'_> {
+            fn visit<T: Component>(&mut self) {
+                self.0.entry(TypeId::of::<T>()).or_insert(ComponentInfo {
+                    name: std::any::type_name::<T>(),
+                    size: size_of::<T>(),
+                    storage: T::STORAGE,
+                });
+            }
+        }
+        if self.bundles.insert(TypeId::of::<B>()) {
+            B::visit_types(&mut Register(&mut self.infos));
+        }
+    }
+
+    pub(crate) fn get(&self, id: TypeId) -> Option<&ComponentInfo> {
+        self.infos.get(&id)
+    }
+}
+
+/// テーブルの 1 要素あたりの推定バイト数。
+fn table_bytes(info: &ComponentInfo) -> usize {
+    info.size + size_of::<ComponentTicks>()
+}
+
+/// 疎集合の 1 要素あたりの推定バイト数。
+fn sparse_bytes(info: &ComponentInfo) -> usize {
+    info.size + size_of::<ComponentTicks>() + size_of::<Entity>() + size_of::<u32>()
+}
+
+impl World {
+    /// 書き込まれたことのあるコンポーネント型の情報。
+    pub fn component_info<T: Component>(&self) -> Option<ComponentInfo> {
+        self.components().get(TypeId::of::<T>()).copied()
+    }
+
+    /// `entity` が持つコンポーネント型名を名前順に返します。生存していなければ `None`。
+    pub fn component_names(&self, entity: Entity) -> Option<Vec<&'static str>> {
+        let mut types = self.table_types_of(entity)?;
+        types.extend(self.sparse().types_of(entity));
+        let mut names: Vec<&'static str> = types
+            .into_iter()
+            .filter_map(|id| self.components().get(id).map(|info| info.name))
+            .collect();
+        names.sort_unstable();
+        Some(names)
+    }
+
+    /// アーキタイプの一覧。空のアーキタイプも含みます。
+    pub fn archetypes(&self) -> Vec<ArchetypeInfo> {
+        self.table_layouts()
+            .into_iter()
+            .map(|(types, entity_count)| {
+                let infos: Vec<&ComponentInfo> = types
+                    .iter()
+                    .filter_map(|id| self.components().get(*id))
+                    .collect();
+                let mut components: Vec<&'static str> =
+                    infos.iter().map(|info| info.name).collect();
+                components.sort_unstable();
+                ArchetypeInfo {
+                    components,
+                    entity_count,
+                    estimated_bytes: infos.iter().map(|info| table_bytes(info)).sum::<usize>()
+                        * entity_count,
+                }
+            })
+            .collect()
+    }
+
+    /// 疎集合の一覧（型名順）。
+    pub fn sparse_sets(&self) -> Vec<SparseSetInfo> {
+        let mut sets: Vec<SparseSetInfo> = self
+            .sparse()
+            .lens()
+            .filter_map(|(id, entity_count)| {
+                let info = self.components().get(id)?;
+                Some(SparseSetInfo {
+                    component: info.name,
+                    entity_count,
+                    estimated_bytes: sparse_bytes(info) * entity_count,
+                })
+            })
+            .collect();
+        sets.sort_unstable_by_key(|set| set.component);
+        sets
+    }
+
+    /// コンポーネントの推定メモリ量（バイト）。
+    pub fn estimated_memory(&self) -> usize {
+        let tables: usize = self
+            .archetypes()
+            .iter()
+            .map(|archetype| archetype.estimated_bytes)
+            .sum();
+        let sparse: usize = self
+            .sparse_sets()
+            .iter()
+            .map(|set| set.estimated_bytes)
+            .sum();
+        tables + sparse
+    }
+
+    /// エンティティ数やアーキタイプ数などの統計をまとめて返します。
+    pub fn stats(&self) -> WorldStats {
+        WorldStats {
+            entity_count: self.entity_count(),
+            archetype_count: self
+                .table_layouts()
+                .iter()
+                .filter(|(_, entity_count)| *entity_count > 0)
+                .count(),
+            component_type_count: self.components().infos.len(),
+            estimated_bytes: self.estimated_memory(),
+        }
+    }
+
+    /// `spawn` / `insert` の前に呼び、バンドルのコンポーネント型を記録します。
+    pub(crate) fn register_components<B: Bundle>(&mut self) {
+        self.components_mut().register_bundle::<B>();
+    }
+}
@@ -1,82 +1,88 @@
-#[cfg(feature = "ecs-hecs")]
-mod hecs_impl {
-    // use super::*;
-    use hecs as h;
-    pub struct World(h::World);
-    #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-    pub struct Entity(h::Entity);
-    pub trait Component: Send + Sync + 'static {}
-    pub struct Ref<'a, T: ?Sized>(h::Ref<'a, T>);
-    pub struct RefMut<'a, T: ?Sized>(h::RefMut<'a, T>);
+//! ECS バックエンドの選択。
+//!
+//! `ecs-hecs`（デフォルト）と `ecs-custom` はどちらも同じ `World` / `Entity` / `Ref` /
+//! `RefMut` / `QueryRef` / `QueryMut` を公開します。両方が有効な場合は明示的に指定された
+//! `ecs-custom` を優先します。
 
-    impl<T: Send + Sync + 'static> Component for T {}
-    impl Default for World {
-        fn default() -> Self {
-            Self::new()
-        }
-    }
-
-    impl World {
-        pub fn new() -> Self {
-            Self(h::World::new())
-        }
-        pub fn spawn<T: Component>(&mut self, component: T) -> Entity {
-            Entity(self.0.spawn((component,)))
-        }
-
-        pub fn despawn(&mut self, entity: Entity) -> bool {
-            self.0.despawn(entity.0).is_ok()
-        }
-
-        pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> bool {
-            self.0.insert(entity.0, (component,)).is_ok()
-        }
-
-        pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
-            self.0.remove_one::<T>(entity.0).ok()
-        }
+#[cfg(not(any(feature = "ecs-hecs", feature = "ecs-custom")))]
+compile_error!("either the `ecs-hecs` or the `ecs-custom` feature must be enabled");
 
-        pub fn get<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
-            self.0.get::<&T>(entity.0).ok().map(Ref)
-        }
+/// `World` に格納できる型のマーカー。
+///
+/// タプルをバンドルとして扱うため、コンポーネントは `impl Component for MyType {}` か
+/// `component!` で明示的に宣言します。
+pub trait Component: Send + Sync + 'static {
+    /// 格納方法。既定はアーキタイプ（テーブル）です。頻繁に付け外しする型は
+    /// `StorageType::SparseSet` にするとアーキタイプの移動が起きません。
+    const STORAGE: StorageType = StorageType::Table;
 
-        pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<RefMut<'_, T>> {
-            self.0.get::<&mut T>(entity.0).ok().map(RefMut)
-        }
-
-        pub fn query_ref<T: Component>(&self) -> QueryRef<'_, T> {
-            QueryRef {
-                inner: self.0.query::<&T>(),
-            }
-        }
-
-        pub fn query_mut<T: Component>(&mut self) -> QueryMut<'_, T> {
-            QueryMut {
-                inner: self.0.query::<&mut T>(),
-            }
-        }
-    }
+    /// `false` の型は `World::get_mut` や `&mut T` のクエリで書き換えられず、値を変えるには
+    /// `World::insert` で置き換えます。フックで索引を保つ `Name` / `Tags` が使います。
+    const MUTABLE: bool = true;
+}
 
-    pub struct QueryRef<'w, T: Component> {
-        inner: h::QueryBorrow<'w, &'w T>,
+/// `T` を書き換えられなければ panic します。
+#[track_caller]
+pub(crate) fn assert_mutable<T: Component>() {
+    if !T::MUTABLE {
+        panic!(
+            "{} cannot be mutated in place; replace it with World::insert",
+            std::any::type_name::<T>()
+        );
     }
+}
 
-    impl<'w, T: Component> QueryRef<'w, T> {
-        pub fn iter<'a>(
-            &'a mut self,
-        ) -> impl Iterator<Item = (Entity, &'a T)> + 'a + use<'a, 'w, T> {
-            self.inner.iter().map(|(e, c)| (Entity(e), c))
-        }
-    }
-    pub struct QueryMut<'w, T: Component> {
-        inner: h::QueryBorrow<'w, &'w mut T>,
-    }
-    impl<'w, T: Component> QueryMut<'w, T> {
-        pub fn iter(&mut self) -> impl Iterator<Item = (Entity, &'_ mut T)> + '_ + use<'_, 'w, T> {
-            self.inner.iter().map(|(e, c)| (Entity(e), c))
-        }
-    }
+/// 型をまとめて `Component` として宣言します。
+///
+/// `: SparseSet` のように書くと、その型の `Component::STORAGE` を指定できます。
+///
+/// ```ignore
+/// component!(Position, Velocity, Selected: SparseSet);
+/// ```
+#[macro_export]
+macro_rules! component {
+    ($($ty:ty $(: $storage:ident)?),* $(,)?) => {
+        $(
+            impl $crate::core::ecs::Component for $ty {
+                $(const STORAGE: $crate::core::ecs::StorageType =
+                    $crate::core::ecs::StorageType::$storage;)?
+            }
+        )*
+    };
 }
+pub use crate::component;
 
-#[cfg(feature = "ecs-hecs")]
+mod bundle;
+pub use bundle::{Bundle, ComponentReader, ComponentWriter, TypeVisitor};
+mod change;
+pub(crate) use change::Ticks;
+pub use change::{Added, Changed, ComponentTicks, RemovedComponents};
+mod commands;
+pub use commands::Commands;
+mod entities;
+mod hooks;
+pub use hooks::{ComponentAdded, ComponentHook, ComponentInserted, ComponentRemoved};
+mod introspection;
+pub use introspection::{ArchetypeInfo, ComponentInfo, SparseSetInfo, WorldStats};
+mod name;
+pub use name::{Name, Tags};
+mod parallel;
+pub use parallel::DEFAULT_BATCH_SIZE;
+pub use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+mod query;
+pub(crate) use query::conflicting;
+pub use query::{Access, QueryData, QueryError, QueryFilter, ReadOnlyQueryData, With, Without};
+mod snapshot;
+pub use snapshot::{Snapshot, SnapshotRegistry};
+mod storage;
+pub use storage::StorageType;
+
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+mod hecs_impl;
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
 pub use hecs_impl::*;
+
+#[cfg(feature = "ecs-custom")]
+mod custom_impl;
+#[cfg(feature = "ecs-custom")]
+pub use custom_impl::*;
@@ -0,0 +1,199 @@
+//! エンティティの名前とタグ、およびその索引。
+//!
+//! `World` は `Name` / `Tags` の on_insert / on_remove フックで名前→`Entity` とタグ→`Entity` の
+//! 索引を保ち、`find_by_name` / `iter_tagged` はクエリを走査せずに引けます。
+//! 索引はコンポーネントの挿入と削除（despawn やシーンの読み込みを含む）で更新されます。
+//! `Name` / `Tags` は `Component::MUTABLE` が `false` で、`get_mut` や `&mut Name` のクエリでは
+//! 書き換えられないので、名前やタグを変えるときは `World::insert` で置き換えます。
+
+use super::{Component, Entity, World};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+
+/// エンティティの名前。同じ名前を複数のエンティティが持つこともできます。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Name(String);
+
+impl Component for Name {
+    const MUTABLE: bool = false;
+}
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+/// エンティティに付ける文字列のタグの集合。重複は取り除かれ、辞書順に並びます。
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Tags(Vec<String>);
+
+impl Component for Tags {
+    const MUTABLE: bool = false;
+}
+
+impl Tags {
+    pub fn new<I, S>(tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut tags: Vec<String> = tags.into_iter().map(Into::into).collect();
+        tags.sort();
+        tags.dedup();
+        Self(tags)
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.binary_search_by(|t| t.as_str().cmp(tag)).is_ok()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// 名前/タグ→エンティティの索引。エンティティは索引に入った順に並びます。
+#[derive(Default)]
+pub(crate) struct NameIndex {
+    by_name: HashMap<String, Vec<Entity>>,
+    by_tag: HashMap<String, Vec<Entity>>,
+    // 付け替えの際に古い索引を消すための逆引き。
+    names: HashMap<Entity, String>,
+    tags: HashMap<Entity, Vec<String>>,
+}
+
+impl NameIndex {
+    fn unindex_name(&mut self, entity: Entity) {
+        if let Some(name) = self.names.remove(&entity) {
+            detach(&mut self.by_name, &name, entity);
+        }
+    }
+
+    fn unindex_tags(&mut self, entity: Entity) {
+        for tag in self.tags.remove(&entity).unwrap_or_default() {
+            detach(&mut self.by_tag, &tag, entity);
+        }
+    }
+}
+
+fn detach(index: &mut HashMap<String, Vec<Entity>>, key: &str, entity: Entity) {
+    if let Some(entities) = index.get_mut(key) {
+        entities.retain(|e| *e != entity);
+        if entities.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+fn index_name(world: &mut World, entity: Entity) {
+    let Some(name) = world.get::<Name>(entity).map(|name| name.0.clone()) else {
+        return;
+    };
+    let index = world.names_mut();
+    index.unindex_name(entity);
+    index.by_name.entry(name.clone()).or_default().push(entity);
+    index.names.insert(entity, name);
+}
+
+fn unindex_name(world: &mut World, entity: Entity) {
+    world.names_mut().unindex_name(entity);
+}
+
+fn index_tags(world: &mut World, entity: Entity) {
+    let Some(tags) = world.get::<Tags>(entity).map(|tags| tags.0.clone()) else {
+        return;
+    };
+    let index = world.names_mut();
+    index.unindex_tags(entity);
+    for tag in &tags {
+        index.by_tag.entry(tag.clone()).or_default().push(entity);
+    }
+    index.tags.insert(entity, tags);
+}
+
+fn unindex_tags(world: &mut World, entity: Entity) {
+    world.names_mut().unindex_tags(entity);
+}
+
+impl World {
+    /// `Name` / `Tags` の索引を保つフックを登録します。`World::new` から呼びます。
+    pub(crate) fn register_name_hooks(&mut self) {
+        self.on_insert::<Name>(index_name)
+            .on_remove::<Name>(unindex_name)
+            .on_insert::<Tags>(index_tags)
+            .on_remove::<Tags>(unindex_tags);
+    }
+
+    /// `Name` / `Tags` の索引を現在のコンポーネントから作り直します。
+    /// フックを通さずにコンポーネントを戻すスナップショットの復元で使います。
+    pub(crate) fn rebuild_name_index(&mut self) {
+        *self.names_mut() = NameIndex::default();
+        let named: Vec<Entity> = self.query_ref::<&Name>().iter().map(|(e, _)| e).collect();
+        for entity in named {
+            index_name(self, entity);
+        }
+        let tagged: Vec<Entity> = self.query_ref::<&Tags>().iter().map(|(e, _)| e).collect();
+        for entity in tagged {
+            index_tags(self, entity);
+        }
+    }
+
+    /// `name` という名前のエンティティを 1 つ返します。複数あれば最初に名付けられたもの。
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.iter_named(name).next()
+    }
+
+    /// `name` という名前のエンティティを名付けられた順に返します。
+    pub fn iter_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Entity> + 'a {
+        self.names()
+            .by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    /// `tag` を持つエンティティをタグが付いた順に返します。
+    pub fn iter_tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = Entity> + 'a {
+        self.names().by_tag.get(tag).into_iter().flatten().copied()
+    }
+}
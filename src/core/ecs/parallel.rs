@@ -0,0 +1,77 @@
+//! クエリの並列反復。
+//!
+//! `par_iter` / `par_for_each` はまず直列の `iter` で一致するエンティティを集め（変更の記録も
+//! この時点で行われます）、それを `batch_size` 件以上のまとまりに分けて rayon のグローバルな
+//! スレッドプールで処理します。対象と順序は直列の `iter` と同じで、`collect` すれば同じ並びの
+//! 結果になります。
+//!
+//! ```ignore
+//! world
+//!     .query_mut::<(&mut Transform2D, &Velocity)>()
+//!     .par_for_each(256, |_, (transform, velocity)| {
+//!         transform.translate(velocity.0);
+//!     });
+//! ```
+
+use super::{Entity, QueryData, QueryFilter, QueryMut, QueryRef, ReadOnlyQueryData};
+use rayon::prelude::*;
+
+/// `par_iter` / `par_for_each` の既定のまとまりの大きさ。
+pub const DEFAULT_BATCH_SIZE: usize = 256;
+
+fn into_batches<I: Send>(
+    items: Vec<I>,
+    batch_size: usize,
+) -> impl IndexedParallelIterator<Item = I> {
+    items.into_par_iter().with_min_len(batch_size.max(1))
+}
+
+impl<Q: ReadOnlyQueryData, F: QueryFilter> QueryRef<'_, Q, F> {
+    /// 一致するエンティティを `batch_size` 件以上ずつに分けて並列に返します。
+    pub fn par_iter<'a>(
+        &'a mut self,
+        batch_size: usize,
+    ) -> impl IndexedParallelIterator<Item = (Entity, Q::Item<'a>)>
+    where
+        Q::Item<'a>: Send,
+    {
+        into_batches(self.iter().collect(), batch_size)
+    }
+
+    /// 一致するエンティティごとに `f` を並列に呼びます。
+    pub fn par_for_each<'a>(
+        &'a mut self,
+        batch_size: usize,
+        f: impl Fn(Entity, Q::Item<'a>) + Send + Sync,
+    ) where
+        Q::Item<'a>: Send,
+    {
+        self.par_iter(batch_size)
+            .for_each(|(entity, item)| f(entity, item));
+    }
+}
+
+impl<Q: QueryData, F: QueryFilter> QueryMut<'_, Q, F> {
+    /// 一致するエンティティを `batch_size` 件以上ずつに分けて並列に返します。
+    pub fn par_iter<'a>(
+        &'a mut self,
+        batch_size: usize,
+    ) -> impl IndexedParallelIterator<Item = (Entity, Q::Item<'a>)>
+    where
+        Q::Item<'a>: Send,
+    {
+        into_batches(self.iter().collect(), batch_size)
+    }
+
+    /// 一致するエンティティごとに `f` を並列に呼びます。
+    pub fn par_for_each<'a>(
+        &'a mut self,
+        batch_size: usize,
+        f: impl Fn(Entity, Q::Item<'a>) + Send + Sync,
+    ) where
+        Q::Item<'a>: Send,
+    {
+        self.par_iter(batch_size)
+            .for_each(|(entity, item)| f(entity, item));
+    }
+}
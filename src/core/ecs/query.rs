@@ -0,0 +1,776 @@
+//! `World::query_ref` / `World::query_mut` で使うクエリ型。
+//!
+//! クエリデータは `&T`、`&mut T`、`Option<Q>` とそれらのタプルで表し、
+//! `With<T>` / `Without<T>`（とそのタプル）でエンティティを絞り込みます。
+//!
+//! ```ignore
+//! let mut q = world
+//!     .query_mut::<(&mut Transform2D, &Sprite, Option<&Camera2D>)>()
+//!     .without::<Hidden>();
+//! for (entity, (transform, sprite, camera)) in q.iter() { /* ... */ }
+//! ```
+//!
+//! バックエンド固有のフック（`#[doc(hidden)]` の項目）はこのモジュールの外から
+//! 実装することを想定していません。
+//!
+//! `StorageType::SparseSet` のコンポーネントはバックエンドのアーキタイプには現れないため、
+//! アーキタイプの照合では条件なしとして扱い、エンティティごとに `matches_sparse` /
+//! `filter_sparse` で確かめてから疎集合から取り出します。
+
+use super::change::Ticks;
+use super::storage::{is_sparse, SparseStorages};
+use super::{Component, Entity};
+use std::any::TypeId;
+use std::marker::PhantomData;
+use thiserror::Error;
+
+#[cfg(feature = "ecs-custom")]
+use super::change::ComponentTicks;
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+use super::change::HecsTicks;
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+use hecs as h;
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+pub(crate) use hecs_storage::Stored;
+
+/// `T` を持つエンティティだけに絞り込むフィルタ。
+pub struct With<T>(PhantomData<fn() -> T>);
+
+/// `T` を持たないエンティティだけに絞り込むフィルタ。
+pub struct Without<T>(PhantomData<fn() -> T>);
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    #[error(
+        "query {query} accesses {component} mutably more than once or both mutably and immutably"
+    )]
+    Conflict {
+        query: &'static str,
+        component: &'static str,
+    },
+    #[error(
+        "query {query} borrows {component} mutably, but it can only be replaced with World::insert"
+    )]
+    Immutable {
+        query: &'static str,
+        component: &'static str,
+    },
+}
+
+/// クエリが読み書きするコンポーネント型の集合。
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+    filter_reads: Vec<(TypeId, &'static str)>,
+    conflict: Option<&'static str>,
+    immutable: Option<&'static str>,
+}
+
+impl Access {
+    pub fn read<T: Component>(&mut self) {
+        let id = TypeId::of::<T>();
+        if self.writes.iter().any(|(w, _)| *w == id) {
+            self.conflict.get_or_insert(std::any::type_name::<T>());
+        }
+        self.reads.push((id, std::any::type_name::<T>()));
+    }
+
+    pub fn write<T: Component>(&mut self) {
+        let id = TypeId::of::<T>();
+        if self
+            .reads
+            .iter()
+            .chain(self.writes.iter())
+            .any(|(other, _)| *other == id)
+        {
+            self.conflict.get_or_insert(std::any::type_name::<T>());
+        }
+        if !T::MUTABLE {
+            self.immutable.get_or_insert(std::any::type_name::<T>());
+        }
+        self.writes.push((id, std::any::type_name::<T>()));
+    }
+
+    /// フィルタが `T` とその変更ティックを読むことを記録します。
+    ///
+    /// `Query<&mut T, Changed<T>>` のように同じシステム内の書き込みとは競合しませんが、
+    /// 並列に実行する他のシステムの書き込みとは競合します。
+    pub fn read_filter<T: Component>(&mut self) {
+        self.filter_reads
+            .push((TypeId::of::<T>(), std::any::type_name::<T>()));
+    }
+
+    /// 同じクエリ内で競合した最初のコンポーネント型名を返します。
+    pub fn conflict(&self) -> Option<&'static str> {
+        self.conflict
+    }
+
+    /// 書き込もうとした `Component::MUTABLE` が `false` の最初の型名を返します。
+    pub fn immutable(&self) -> Option<&'static str> {
+        self.immutable
+    }
+
+    /// `other` と同時に借用できないコンポーネントがあれば、その型名を返します。
+    pub fn conflicts_with(&self, other: &Access) -> Option<&'static str> {
+        let reads = [&self.reads[..], &self.filter_reads[..]].concat();
+        let other_reads = [&other.reads[..], &other.filter_reads[..]].concat();
+        conflicting(&reads, &self.writes, &other_reads, &other.writes)
+    }
+}
+
+/// 一方の書き込みと他方の読み書きが重なる最初の型名。
+pub(crate) fn conflicting(
+    reads: &[(TypeId, &'static str)],
+    writes: &[(TypeId, &'static str)],
+    other_reads: &[(TypeId, &'static str)],
+    other_writes: &[(TypeId, &'static str)],
+) -> Option<&'static str> {
+    let overlaps = |a: &[(TypeId, &'static str)], b: &[(TypeId, &'static str)]| {
+        a.iter()
+            .find(|(id, _)| b.iter().any(|(other, _)| other == id))
+            .map(|(_, name)| *name)
+    };
+    overlaps(writes, other_reads)
+        .or_else(|| overlaps(writes, other_writes))
+        .or_else(|| overlaps(reads, other_writes))
+}
+
+/// クエリの借用が競合していないかを構築時に検査します。
+pub(crate) fn check_access<Q: QueryData>() -> Result<(), QueryError> {
+    let mut access = Access::default();
+    Q::access(&mut access);
+    let query = std::any::type_name::<Q>();
+    if let Some(component) = access.conflict() {
+        return Err(QueryError::Conflict { query, component });
+    }
+    match access.immutable() {
+        Some(component) => Err(QueryError::Immutable { query, component }),
+        None => Ok(()),
+    }
+}
+
+/// 自前バックエンドでアーキタイプの列を取り出すためのインターフェース。
+#[cfg(feature = "ecs-custom")]
+#[doc(hidden)]
+pub trait ColumnSource {
+    /// アーキタイプが持つコンポーネント型（ソート済み）。
+    fn types(&self) -> &[TypeId];
+    /// `T` の列の読み取り用の先頭ポインタ。`T` の列が存在しない場合は panic します。
+    fn column_ptr<T: Component>(&self) -> *const T;
+    /// `T` の列に対応するティック列の読み取り用の先頭ポインタ。
+    fn ticks_ptr<T: Component>(&self) -> *const ComponentTicks;
+    /// `T` の列の書き込み用の先頭ポインタ。
+    ///
+    /// # Safety
+    /// 返したポインタを使う間、他に `T` の列を読み書きする借用がない必要があります。
+    unsafe fn column_ptr_mut<T: Component>(&self) -> *mut T;
+    /// `T` のティック列の書き込み用の先頭ポインタ。
+    ///
+    /// # Safety
+    /// `column_ptr_mut` と同じです。
+    unsafe fn ticks_ptr_mut<T: Component>(&self) -> *mut ComponentTicks;
+}
+
+#[cfg(feature = "ecs-custom")]
+pub(crate) fn has_type<T: Component>(types: &[TypeId]) -> bool {
+    types.binary_search(&TypeId::of::<T>()).is_ok()
+}
+
+/// エンティティごとに取り出すデータ。
+pub trait QueryData {
+    type Item<'w>;
+
+    /// このクエリが読み書きするコンポーネントを `access` に記録します。
+    fn access(access: &mut Access);
+
+    /// 疎集合のコンポーネントを `entity` がすべて持っていれば `true`。
+    #[doc(hidden)]
+    fn matches_sparse(entity: Entity, sparse: &SparseStorages) -> bool;
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    #[doc(hidden)]
+    type Hecs: h::Query;
+
+    /// # Safety
+    /// `sparse` は有効で、`matches_sparse` を満たすエンティティに対して呼び、結果の借用が
+    /// 他の借用と競合しない必要があります。
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    #[doc(hidden)]
+    unsafe fn from_hecs<'q>(
+        item: <Self::Hecs as h::Query>::Item<'q>,
+        ticks: Ticks,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> Self::Item<'q>;
+
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    type State: Copy;
+
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    fn matches_archetype(types: &[TypeId]) -> bool;
+
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    fn init_state<S: ColumnSource>(source: &mut S, ticks: Ticks) -> Self::State;
+
+    /// # Safety
+    /// `state` は `matches_archetype` を満たすアーキタイプから作られ、`row` はその範囲内の
+    /// `entity` の行で、`entity` は `matches_sparse` を満たし、`sparse` は有効で、
+    /// 結果の借用が他の借用と競合しない必要があります。
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    unsafe fn fetch<'w>(
+        state: Self::State,
+        row: usize,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> Self::Item<'w>;
+}
+
+/// 書き込みを含まないクエリ。`World::query_ref` で使えます。
+pub trait ReadOnlyQueryData: QueryData {}
+
+/// エンティティを絞り込む条件。タプルはすべての条件の AND になります。
+pub trait QueryFilter {
+    /// 条件の判定で読むコンポーネントを `access` に記録します。
+    fn access(access: &mut Access);
+
+    /// 疎集合のコンポーネントについての条件を満たせば `true`。
+    #[doc(hidden)]
+    fn filter_sparse(entity: Entity, sparse: &SparseStorages, ticks: Ticks) -> bool;
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    #[doc(hidden)]
+    type Hecs<Q: h::Query>: h::Query;
+
+    /// 条件を満たさない行は `None` を返して読み飛ばします。
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    #[doc(hidden)]
+    fn unwrap_hecs<'q, Q: h::Query>(
+        item: <Self::Hecs<Q> as h::Query>::Item<'q>,
+        ticks: Ticks,
+    ) -> Option<<Q as h::Query>::Item<'q>>;
+
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    type State: Copy;
+
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    fn matches_archetype(types: &[TypeId]) -> bool;
+
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    fn init_state<S: ColumnSource>(source: &mut S, ticks: Ticks) -> Self::State;
+
+    /// # Safety
+    /// `state` は `matches_archetype` を満たすアーキタイプから作られ、`row` はその範囲内である必要があります。
+    #[cfg(feature = "ecs-custom")]
+    #[doc(hidden)]
+    unsafe fn filter_row(state: Self::State, row: usize) -> bool;
+}
+
+impl<T: Component> QueryData for &T {
+    type Item<'w> = &'w T;
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn matches_sparse(entity: Entity, sparse: &SparseStorages) -> bool {
+        !is_sparse::<T>() || sparse.contains::<T>(entity)
+    }
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    type Hecs = Stored<T, &'static T, true>;
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    unsafe fn from_hecs<'q>(
+        item: <Self::Hecs as h::Query>::Item<'q>,
+        _ticks: Ticks,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> Self::Item<'q> {
+        match item {
+            Some(item) => item,
+            None => sparse_ref(sparse, entity),
+        }
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    type State = *const T;
+
+    #[cfg(feature = "ecs-custom")]
+    fn matches_archetype(types: &[TypeId]) -> bool {
+        is_sparse::<T>() || has_type::<T>(types)
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    fn init_state<S: ColumnSource>(source: &mut S, _ticks: Ticks) -> *const T {
+        if is_sparse::<T>() {
+            std::ptr::null()
+        } else {
+            source.column_ptr::<T>()
+        }
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    unsafe fn fetch<'w>(
+        state: *const T,
+        row: usize,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> &'w T {
+        if is_sparse::<T>() {
+            sparse_ref(sparse, entity)
+        } else {
+            &*state.add(row)
+        }
+    }
+}
+
+impl<T: Component> ReadOnlyQueryData for &T {}
+
+impl<T: Component> QueryData for &mut T {
+    type Item<'w> = &'w mut T;
+
+    fn access(access: &mut Access) {
+        access.write::<T>();
+    }
+
+    fn matches_sparse(entity: Entity, sparse: &SparseStorages) -> bool {
+        !is_sparse::<T>() || sparse.contains::<T>(entity)
+    }
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    // ティックは共有で借用し、同じクエリの `Changed<T>` / `Added<T>` と両立させます。
+    type Hecs = Stored<T, (&'static mut T, &'static HecsTicks<T>), true>;
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    unsafe fn from_hecs<'q>(
+        item: <Self::Hecs as h::Query>::Item<'q>,
+        ticks: Ticks,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> Self::Item<'q> {
+        match item {
+            Some((value, component_ticks)) => {
+                component_ticks.set_changed(ticks.this_run);
+                value
+            }
+            None => sparse_mut(sparse, entity, ticks.this_run),
+        }
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    type State = (*mut T, *mut ComponentTicks, u64);
+
+    #[cfg(feature = "ecs-custom")]
+    fn matches_archetype(types: &[TypeId]) -> bool {
+        is_sparse::<T>() || has_type::<T>(types)
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    fn init_state<S: ColumnSource>(source: &mut S, ticks: Ticks) -> Self::State {
+        if is_sparse::<T>() {
+            return (std::ptr::null_mut(), std::ptr::null_mut(), ticks.this_run);
+        }
+        // SAFETY: ポインタを使うのは `fetch` だけで、その呼び出し側が `T` への他の借用が
+        // ないことを保証します。
+        unsafe {
+            (
+                source.column_ptr_mut::<T>(),
+                source.ticks_ptr_mut::<T>(),
+                ticks.this_run,
+            )
+        }
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    unsafe fn fetch<'w>(
+        state: Self::State,
+        row: usize,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> &'w mut T {
+        let (values, component_ticks, this_run) = state;
+        if is_sparse::<T>() {
+            return sparse_mut(sparse, entity, this_run);
+        }
+        (*component_ticks.add(row)).set_changed(this_run);
+        &mut *values.add(row)
+    }
+}
+
+impl<Q: QueryData> QueryData for Option<Q> {
+    type Item<'w> = Option<Q::Item<'w>>;
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+
+    fn matches_sparse(_entity: Entity, _sparse: &SparseStorages) -> bool {
+        true
+    }
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    type Hecs = Option<Q::Hecs>;
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    unsafe fn from_hecs<'q>(
+        item: <Self::Hecs as h::Query>::Item<'q>,
+        ticks: Ticks,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> Self::Item<'q> {
+        item.filter(|_| Q::matches_sparse(entity, &*sparse))
+            .map(|item| Q::from_hecs(item, ticks, entity, sparse))
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    type State = Option<Q::State>;
+
+    #[cfg(feature = "ecs-custom")]
+    fn matches_archetype(_types: &[TypeId]) -> bool {
+        true
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    fn init_state<S: ColumnSource>(source: &mut S, ticks: Ticks) -> Option<Q::State> {
+        if Q::matches_archetype(source.types()) {
+            Some(Q::init_state(source, ticks))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    unsafe fn fetch<'w>(
+        state: Option<Q::State>,
+        row: usize,
+        entity: Entity,
+        sparse: *const SparseStorages,
+    ) -> Option<Q::Item<'w>> {
+        state
+            .filter(|_| Q::matches_sparse(entity, &*sparse))
+            .map(|state| Q::fetch(state, row, entity, sparse))
+    }
+}
+
+impl<Q: ReadOnlyQueryData> ReadOnlyQueryData for Option<Q> {}
+
+impl<T: Component> QueryFilter for With<T> {
+    // アーキタイプと疎集合の索引だけを見るので、コンポーネントの値やティックは読みません。
+    fn access(_access: &mut Access) {}
+
+    fn filter_sparse(entity: Entity, sparse: &SparseStorages, _ticks: Ticks) -> bool {
+        !is_sparse::<T>() || sparse.contains::<T>(entity)
+    }
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    type Hecs<Q: h::Query> = h::With<Q, Stored<T, &'static T, true>>;
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    fn unwrap_hecs<'q, Q: h::Query>(
+        item: <Self::Hecs<Q> as h::Query>::Item<'q>,
+        _ticks: Ticks,
+    ) -> Option<<Q as h::Query>::Item<'q>> {
+        Some(item)
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    type State = ();
+
+    #[cfg(feature = "ecs-custom")]
+    fn matches_archetype(types: &[TypeId]) -> bool {
+        is_sparse::<T>() || has_type::<T>(types)
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    fn init_state<S: ColumnSource>(_source: &mut S, _ticks: Ticks) {}
+
+    #[cfg(feature = "ecs-custom")]
+    unsafe fn filter_row(_state: (), _row: usize) -> bool {
+        true
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    // アーキタイプと疎集合の索引だけを見るので、コンポーネントの値やティックは読みません。
+    fn access(_access: &mut Access) {}
+
+    fn filter_sparse(entity: Entity, sparse: &SparseStorages, _ticks: Ticks) -> bool {
+        !is_sparse::<T>() || !sparse.contains::<T>(entity)
+    }
+
+    // 疎集合の `T` はどのアーキタイプにも一致しない `Stored` になり、何も除外しません。
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    type Hecs<Q: h::Query> = h::Without<Q, Stored<T, &'static T, false>>;
+
+    #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+    fn unwrap_hecs<'q, Q: h::Query>(
+        item: <Self::Hecs<Q> as h::Query>::Item<'q>,
+        _ticks: Ticks,
+    ) -> Option<<Q as h::Query>::Item<'q>> {
+        Some(item)
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    type State = ();
+
+    #[cfg(feature = "ecs-custom")]
+    fn matches_archetype(types: &[TypeId]) -> bool {
+        is_sparse::<T>() || !has_type::<T>(types)
+    }
+
+    #[cfg(feature = "ecs-custom")]
+    fn init_state<S: ColumnSource>(_source: &mut S, _ticks: Ticks) {}
+
+    #[cfg(feature = "ecs-custom")]
+    unsafe fn filter_row(_state: (), _row: usize) -> bool {
+        true
+    }
+}
+
+macro_rules! tuple_query {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type Item<'w> = ($($name::Item<'w>,)*);
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+
+            fn matches_sparse(entity: Entity, sparse: &SparseStorages) -> bool {
+                true $(&& $name::matches_sparse(entity, sparse))*
+            }
+
+            #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+            type Hecs = ($($name::Hecs,)*);
+
+            #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+            unsafe fn from_hecs<'q>(
+                item: <Self::Hecs as h::Query>::Item<'q>,
+                ticks: Ticks,
+                entity: Entity,
+                sparse: *const SparseStorages,
+            ) -> Self::Item<'q> {
+                let ($($name,)*) = item;
+                ($($name::from_hecs($name, ticks, entity, sparse),)*)
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            type State = ($($name::State,)*);
+
+            #[cfg(feature = "ecs-custom")]
+            fn matches_archetype(types: &[TypeId]) -> bool {
+                true $(&& $name::matches_archetype(types))*
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            fn init_state<S: ColumnSource>(source: &mut S, ticks: Ticks) -> Self::State {
+                ($($name::init_state(source, ticks),)*)
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            unsafe fn fetch<'w>(
+                state: Self::State,
+                row: usize,
+                entity: Entity,
+                sparse: *const SparseStorages,
+            ) -> Self::Item<'w> {
+                let ($($name,)*) = state;
+                ($($name::fetch($name, row, entity, sparse),)*)
+            }
+        }
+
+        impl<$($name: ReadOnlyQueryData),*> ReadOnlyQueryData for ($($name,)*) {}
+
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+
+            fn filter_sparse(entity: Entity, sparse: &SparseStorages, ticks: Ticks) -> bool {
+                true $(&& $name::filter_sparse(entity, sparse, ticks))*
+            }
+
+            #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+            type Hecs<Q: h::Query> = tuple_query!(@hecs Q; $($name),*);
+
+            #[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+            fn unwrap_hecs<'q, Q: h::Query>(
+                item: <Self::Hecs<Q> as h::Query>::Item<'q>,
+                ticks: Ticks,
+            ) -> Option<<Q as h::Query>::Item<'q>> {
+                Some(tuple_query!(@unwrap item, ticks, Q; $($name),*))
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            type State = ($($name::State,)*);
+
+            #[cfg(feature = "ecs-custom")]
+            fn matches_archetype(types: &[TypeId]) -> bool {
+                true $(&& $name::matches_archetype(types))*
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            fn init_state<S: ColumnSource>(source: &mut S, ticks: Ticks) -> Self::State {
+                ($($name::init_state(source, ticks),)*)
+            }
+
+            #[cfg(feature = "ecs-custom")]
+            unsafe fn filter_row(state: Self::State, row: usize) -> bool {
+                let ($($name,)*) = state;
+                true $(&& $name::filter_row($name, row))*
+            }
+        }
+    };
+    // hecs ではフィルタを入れ子のクエリ型で表すため、先頭の要素を最も内側に包みます。
+    (@hecs $q:ty;) => { $q };
+    (@hecs $q:ty; $head:ident $(, $rest:ident)*) => {
+        tuple_query!(@hecs $head::Hecs<$q>; $($rest),*)
+    };
+    (@unwrap $item:expr, $ticks:expr, $q:ty;) => { $item };
+    (@unwrap $item:expr, $ticks:expr, $q:ty; $head:ident $(, $rest:ident)*) => {
+        $head::unwrap_hecs::<$q>(
+            tuple_query!(@unwrap $item, $ticks, $head::Hecs<$q>; $($rest),*),
+            $ticks,
+        )?
+    };
+}
+
+tuple_query!();
+tuple_query!(A);
+tuple_query!(A, B);
+tuple_query!(A, B, C);
+tuple_query!(A, B, C, D);
+tuple_query!(A, B, C, D, E);
+tuple_query!(A, B, C, D, E, F);
+tuple_query!(A, B, C, D, E, F, G);
+tuple_query!(A, B, C, D, E, F, G, H);
+
+/// 疎集合から `entity` の `T` を借用します。
+///
+/// # Safety
+/// `sparse` は有効で、`entity` は `T` を持っている必要があります。
+unsafe fn sparse_ref<'w, T: Component>(sparse: *const SparseStorages, entity: Entity) -> &'w T {
+    let value: *const T = (*sparse)
+        .get::<T>()
+        .and_then(|set| set.get(entity))
+        .expect("sparse component is missing from a matched entity");
+    &*value
+}
+
+/// 疎集合から `entity` の `T` を可変で借用し、変更として記録します。
+///
+/// # Safety
+/// `sparse` は有効で、`entity` は `T` を持ち、結果の借用が他の借用と競合しない必要があります。
+unsafe fn sparse_mut<'w, T: Component>(
+    sparse: *const SparseStorages,
+    entity: Entity,
+    this_run: u64,
+) -> &'w mut T {
+    let (value, ticks) = (*sparse)
+        .fetch_ptrs::<T>(entity)
+        .expect("sparse component is missing from a matched entity");
+    (*ticks).set_changed(this_run);
+    &mut *value
+}
+
+/// hecs のクエリで、格納方法に応じてアーキタイプの照合を切り替える型。
+#[cfg(all(feature = "ecs-hecs", not(feature = "ecs-custom")))]
+mod hecs_storage {
+    use super::is_sparse;
+    use crate::core::ecs::Component;
+    use hecs as h;
+    use std::any::TypeId;
+    use std::marker::PhantomData;
+
+    /// テーブルの `T` なら `Q` として照合し `Some` を返します。疎集合の `T` はアーキタイプに
+    /// 現れないので、`MATCH_SPARSE` が `true` ならすべてのアーキタイプに一致して `None` を返し、
+    /// `false` ならどのアーキタイプにも一致しません。
+    pub struct Stored<T, Q, const MATCH_SPARSE: bool>(PhantomData<fn() -> (T, Q)>);
+
+    impl<T: Component, Q: h::Query, const MATCH_SPARSE: bool> h::Query for Stored<T, Q, MATCH_SPARSE> {
+        type Item<'a> = Option<Q::Item<'a>>;
+        type Fetch = StoredFetch<T, Q::Fetch, MATCH_SPARSE>;
+
+        unsafe fn get<'a>(fetch: &Self::Fetch, n: usize) -> Self::Item<'a> {
+            fetch.inner.as_ref().map(|inner| Q::get(inner, n))
+        }
+    }
+
+    pub struct StoredFetch<T, F, const MATCH_SPARSE: bool> {
+        inner: Option<F>,
+        _marker: PhantomData<fn() -> T>,
+    }
+
+    impl<T, F: Clone, const MATCH_SPARSE: bool> Clone for StoredFetch<T, F, MATCH_SPARSE> {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    unsafe impl<T: Component, F: h::Fetch, const MATCH_SPARSE: bool> h::Fetch
+        for StoredFetch<T, F, MATCH_SPARSE>
+    {
+        type State = Option<F::State>;
+
+        fn dangling() -> Self {
+            Self {
+                inner: None,
+                _marker: PhantomData,
+            }
+        }
+
+        fn access(archetype: &h::Archetype) -> Option<h::Access> {
+            match (is_sparse::<T>(), MATCH_SPARSE) {
+                (false, _) => F::access(archetype),
+                (true, true) => Some(h::Access::Iterate),
+                (true, false) => None,
+            }
+        }
+
+        fn borrow(archetype: &h::Archetype, state: Self::State) {
+            if let Some(state) = state {
+                F::borrow(archetype, state);
+            }
+        }
+
+        fn prepare(archetype: &h::Archetype) -> Option<Self::State> {
+            match (is_sparse::<T>(), MATCH_SPARSE) {
+                (false, _) => F::prepare(archetype).map(Some),
+                (true, true) => Some(None),
+                (true, false) => None,
+            }
+        }
+
+        fn execute(archetype: &h::Archetype, state: Self::State) -> Self {
+            Self {
+                inner: state.map(|state| F::execute(archetype, state)),
+                _marker: PhantomData,
+            }
+        }
+
+        fn release(archetype: &h::Archetype, state: Self::State) {
+            if let Some(state) = state {
+                F::release(archetype, state);
+            }
+        }
+
+        fn for_each_borrow(f: impl FnMut(TypeId, bool)) {
+            if !is_sparse::<T>() {
+                F::for_each_borrow(f);
+            }
+        }
+    }
+}
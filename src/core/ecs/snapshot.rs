@@ -0,0 +1,168 @@
+//! `World` と選択した `DiContainer` リソースのスナップショット。
+//!
+//! `SnapshotRegistry` に登録したコンポーネント型とリソース型を `Clone` で写し取り、
+//! `Snapshot::restore` で写し取った時点の状態に戻します。エンティティの ID（世代を含む）も
+//! そのまま戻るので、スナップショットの前に取った `Entity` は復元後も同じものを指します。
+//!
+//! 復元はフック（on_remove / on_add / on_insert）、オブザーバー、`World::removed` の記録を通さずに
+//! 全エンティティを置き換えるので、システムからは復元がゲームプレイ中の追加/削除に見えません。
+//! コンポーネントの追加/変更のティックも写し取った時点の値に戻ります。`Name` / `Tags` の索引は
+//! 作り直されます。登録していないコンポーネントは失われます。
+
+use super::entities::AllocatorSnapshot;
+use super::{Component, ComponentTicks, Entity, Name, Tags, World};
+use crate::core::DiContainer;
+use std::any::TypeId;
+
+/// 写し取った 1 種類のコンポーネント。実体は `Vec<(Entity, T, ComponentTicks)>` です。
+trait ComponentColumn: Send + Sync {
+    fn restore(&self, world: &mut World);
+}
+
+impl<T: Component + Clone> ComponentColumn for Vec<(Entity, T, ComponentTicks)> {
+    fn restore(&self, world: &mut World) {
+        for (entity, component, ticks) in self {
+            world.insert_raw(*entity, component.clone(), *ticks);
+        }
+    }
+}
+
+/// 写し取った 1 つのリソース。
+trait ResourceValue: Send + Sync {
+    fn restore(&self, di: &mut DiContainer);
+}
+
+struct Resource<R>(R);
+
+impl<R: Clone + Send + Sync + 'static> ResourceValue for Resource<R> {
+    fn restore(&self, di: &mut DiContainer) {
+        di.insert(self.0.clone());
+    }
+}
+
+type CaptureComponents = fn(&World) -> Box<dyn ComponentColumn>;
+type CaptureResource = fn(&DiContainer) -> Option<Box<dyn ResourceValue>>;
+
+fn capture_components<T: Component + Clone>(world: &World) -> Box<dyn ComponentColumn> {
+    let column: Vec<(Entity, T, ComponentTicks)> = world
+        .query_ref::<&T>()
+        .iter()
+        .map(|(entity, component)| {
+            let ticks = world
+                .component_ticks::<T>(entity)
+                .expect("queried component has ticks");
+            (entity, component.clone(), ticks)
+        })
+        .collect();
+    Box::new(column)
+}
+
+fn capture_resource<R: Clone + Send + Sync + 'static>(
+    di: &DiContainer,
+) -> Option<Box<dyn ResourceValue>> {
+    di.get::<R>()
+        .map(|resource| Box::new(Resource(resource.clone())) as Box<dyn ResourceValue>)
+}
+
+/// スナップショットに含めるコンポーネント型とリソース型。
+///
+/// `Name` と `Tags` は最初から登録されています。
+pub struct SnapshotRegistry {
+    components: Vec<(TypeId, CaptureComponents)>,
+    resources: Vec<(TypeId, CaptureResource)>,
+}
+
+impl Default for SnapshotRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnapshotRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            components: Vec::new(),
+            resources: Vec::new(),
+        };
+        registry.register::<Name>().register::<Tags>();
+        registry
+    }
+
+    /// コンポーネント型を登録します。同じ型を何度登録しても 1 回だけ写し取ります。
+    pub fn register<T: Component + Clone>(&mut self) -> &mut Self {
+        let id = TypeId::of::<T>();
+        if !self
+            .components
+            .iter()
+            .any(|(registered, _)| *registered == id)
+        {
+            self.components.push((id, capture_components::<T>));
+        }
+        self
+    }
+
+    /// `DiContainer` のリソース型（`Time` など）を登録します。
+    pub fn register_resource<R: Clone + Send + Sync + 'static>(&mut self) -> &mut Self {
+        let id = TypeId::of::<R>();
+        if !self
+            .resources
+            .iter()
+            .any(|(registered, _)| *registered == id)
+        {
+            self.resources.push((id, capture_resource::<R>));
+        }
+        self
+    }
+
+    /// 現在の状態を写し取ります。`di` にない登録済みリソースは含まれません。
+    pub fn capture(&self, world: &World, di: &DiContainer) -> Snapshot {
+        Snapshot {
+            allocator: world.allocator().snapshot(),
+            entities: world.query_ref::<()>().iter().map(|(e, _)| e).collect(),
+            components: self
+                .components
+                .iter()
+                .map(|(_, capture)| capture(world))
+                .collect(),
+            resources: self
+                .resources
+                .iter()
+                .filter_map(|(_, capture)| capture(di))
+                .collect(),
+        }
+    }
+}
+
+/// `SnapshotRegistry::capture` で写し取った状態。何度でも復元できます。
+pub struct Snapshot {
+    allocator: AllocatorSnapshot,
+    entities: Vec<Entity>,
+    components: Vec<Box<dyn ComponentColumn>>,
+    resources: Vec<Box<dyn ResourceValue>>,
+}
+
+impl Snapshot {
+    /// 写し取ったエンティティの数。
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// `world` と `di` を写し取った時点の状態に戻します。
+    ///
+    /// 写し取った後に予約した ID は無効になるので、未適用の `Commands` は先に適用するか
+    /// 破棄してください。
+    pub fn restore(&self, world: &mut World, di: &mut DiContainer) {
+        world.clear_raw();
+        world.allocator().restore(&self.allocator);
+        for &entity in &self.entities {
+            world.spawn_reserved(entity, ());
+        }
+        for column in &self.components {
+            column.restore(world);
+        }
+        world.rebuild_name_index();
+        for resource in &self.resources {
+            resource.restore(di);
+        }
+    }
+}
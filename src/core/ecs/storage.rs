@@ -0,0 +1,263 @@
+//! コンポーネントの格納方法。
+//!
+//! 既定の `StorageType::Table` はアーキタイプ（テーブル）に格納し、反復が速い代わりに
+//! 追加/削除のたびにエンティティの行が別のアーキタイプへ移ります。`StorageType::SparseSet` は
+//! 型ごとの疎集合に格納し、追加/削除でアーキタイプが変わりません。頻繁に付け外しする
+//! マーカー（`Hovered`、`Selected`、状態異常など）に向いています。
+//!
+//! ```ignore
+//! struct Selected;
+//! impl Component for Selected {
+//!     const STORAGE: StorageType = StorageType::SparseSet;
+//! }
+//! ```
+//!
+//! クエリやフィルタはどちらの格納方法でも同じように書けます。疎集合のコンポーネントは
+//! エンティティごとに引くので、疎集合のコンポーネントだけのクエリは全エンティティを走査します。
+
+use super::{Component, ComponentTicks, Entity};
+use std::any::{Any, TypeId};
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+
+/// コンポーネントの格納方法。`Component::STORAGE` で型ごとに宣言します。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageType {
+    Table,
+    SparseSet,
+}
+
+pub(crate) fn is_sparse<T: Component>() -> bool {
+    T::STORAGE == StorageType::SparseSet
+}
+
+/// クエリが共有参照から書き込めるようにした列。
+///
+/// 同じ列を同時に読み書きしないことは、クエリの `check_access` とシステムの
+/// `SystemAccess` の検査が保証します。構造の変更（追加/削除）は `&mut` でしか行いません。
+pub(crate) struct ColumnCell<T>(UnsafeCell<Vec<T>>);
+
+// SAFETY: 要素は `Vec<T>` と同じくスレッドをまたいで移動し、共有中の書き込みは `ptr_mut` の
+// 呼び出し側が他の読み書きと重ならないことを保証します。
+unsafe impl<T: Send> Send for ColumnCell<T> {}
+unsafe impl<T: Send + Sync> Sync for ColumnCell<T> {}
+
+impl<T> ColumnCell<T> {
+    pub(crate) fn new() -> Self {
+        Self(UnsafeCell::new(Vec::new()))
+    }
+
+    pub(crate) fn get(&self) -> &Vec<T> {
+        // SAFETY: 共有参照を通した書き込みは `ptr_mut` だけで、その間この列は読まれません。
+        unsafe { &*self.0.get() }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut Vec<T> {
+        self.0.get_mut()
+    }
+
+    /// 書き込み用の先頭ポインタ。
+    ///
+    /// # Safety
+    /// 返したポインタを使う間、他にこの列を読み書きする借用がない必要があります。
+    pub(crate) unsafe fn ptr_mut(&self) -> *mut T {
+        (*self.0.get()).as_mut_ptr()
+    }
+}
+
+const EMPTY: u32 = u32::MAX;
+
+/// 1 つの型の疎集合。値は詰めて並べ、エンティティのインデックスから位置を引きます。
+pub(crate) struct SparseSet<T> {
+    values: ColumnCell<T>,
+    ticks: ColumnCell<ComponentTicks>,
+    entities: Vec<Entity>,
+    // エンティティのインデックス→`values` の位置。世代は `entities` と照合します。
+    sparse: Vec<u32>,
+}
+
+impl<T> SparseSet<T> {
+    fn new() -> Self {
+        Self {
+            values: ColumnCell::new(),
+            ticks: ColumnCell::new(),
+            entities: Vec::new(),
+            sparse: Vec::new(),
+        }
+    }
+
+    fn position(&self, entity: Entity) -> Option<usize> {
+        let dense = *self.sparse.get(entity.index() as usize)?;
+        let dense = dense as usize;
+        (dense < self.entities.len() && self.entities[dense] == entity).then_some(dense)
+    }
+
+    pub(crate) fn contains(&self, entity: Entity) -> bool {
+        self.position(entity).is_some()
+    }
+
+    pub(crate) fn get(&self, entity: Entity) -> Option<&T> {
+        self.position(entity).map(|dense| &self.values.get()[dense])
+    }
+
+    pub(crate) fn ticks(&self, entity: Entity) -> Option<ComponentTicks> {
+        self.position(entity).map(|dense| self.ticks.get()[dense])
+    }
+
+    /// 可変参照を返し、変更として記録します。
+    pub(crate) fn get_mut(&mut self, entity: Entity, tick: u64) -> Option<&mut T> {
+        let dense = self.position(entity)?;
+        self.ticks.get_mut()[dense].set_changed(tick);
+        Some(&mut self.values.get_mut()[dense])
+    }
+
+    /// 値を格納します。既にあれば上書きして変更として記録します。
+    pub(crate) fn insert(&mut self, entity: Entity, value: T, tick: u64) {
+        if let Some(dense) = self.position(entity) {
+            self.values.get_mut()[dense] = value;
+            self.ticks.get_mut()[dense].set_changed(tick);
+            return;
+        }
+        let index = entity.index() as usize;
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, EMPTY);
+        }
+        self.sparse[index] = self.entities.len() as u32;
+        self.values.get_mut().push(value);
+        self.ticks.get_mut().push(ComponentTicks::new(tick));
+        self.entities.push(entity);
+    }
+
+    /// 値を `ticks` のまま格納します。スナップショットの復元で使います。
+    pub(crate) fn insert_with_ticks(&mut self, entity: Entity, value: T, ticks: ComponentTicks) {
+        self.insert(entity, value, ticks.added());
+        let dense = self.position(entity).expect("value was just inserted");
+        self.ticks.get_mut()[dense] = ticks;
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) -> Option<T> {
+        let dense = self.position(entity)?;
+        self.sparse[entity.index() as usize] = EMPTY;
+        self.entities.swap_remove(dense);
+        self.ticks.get_mut().swap_remove(dense);
+        let value = self.values.get_mut().swap_remove(dense);
+        if let Some(moved) = self.entities.get(dense) {
+            self.sparse[moved.index() as usize] = dense as u32;
+        }
+        Some(value)
+    }
+
+    /// `fetch` 用に、値とティックへの書き込み用ポインタを返します。
+    ///
+    /// # Safety
+    /// 返したポインタを使う間、他にこの型の値とティックを読み書きする借用がない必要があります。
+    unsafe fn ptrs(&self, entity: Entity) -> Option<(*mut T, *mut ComponentTicks)> {
+        let dense = self.position(entity)?;
+        Some((
+            self.values.ptr_mut().add(dense),
+            self.ticks.ptr_mut().add(dense),
+        ))
+    }
+}
+
+/// 型消去された疎集合。実体は `SparseSet<T>` です。
+trait AnySparseSet: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn contains(&self, entity: Entity) -> bool;
+    fn len(&self) -> usize;
+    fn remove_drop(&mut self, entity: Entity) -> bool;
+}
+
+impl<T: Component> AnySparseSet for SparseSet<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn contains(&self, entity: Entity) -> bool {
+        SparseSet::contains(self, entity)
+    }
+    fn len(&self) -> usize {
+        self.entities.len()
+    }
+    fn remove_drop(&mut self, entity: Entity) -> bool {
+        self.remove(entity).is_some()
+    }
+}
+
+/// `World` が持つ疎集合の一覧。
+#[doc(hidden)]
+#[derive(Default)]
+pub struct SparseStorages {
+    sets: HashMap<TypeId, Box<dyn AnySparseSet>>,
+}
+
+impl SparseStorages {
+    pub(crate) fn get<T: Component>(&self) -> Option<&SparseSet<T>> {
+        self.sets
+            .get(&TypeId::of::<T>())
+            .and_then(|set| set.as_any().downcast_ref())
+    }
+
+    pub(crate) fn get_mut<T: Component>(&mut self) -> Option<&mut SparseSet<T>> {
+        self.sets
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|set| set.as_any_mut().downcast_mut())
+    }
+
+    pub(crate) fn get_or_insert<T: Component>(&mut self) -> &mut SparseSet<T> {
+        self.sets
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("sparse set type mismatch")
+    }
+
+    pub(crate) fn contains<T: Component>(&self, entity: Entity) -> bool {
+        self.get::<T>().is_some_and(|set| set.contains(entity))
+    }
+
+    /// `entity` が持つ疎集合のコンポーネント型。
+    pub(crate) fn types_of(&self, entity: Entity) -> Vec<TypeId> {
+        self.sets
+            .iter()
+            .filter(|(_, set)| set.contains(entity))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// 型ごとの要素数。
+    pub(crate) fn lens(&self) -> impl Iterator<Item = (TypeId, usize)> + '_ {
+        self.sets.iter().map(|(id, set)| (*id, set.len()))
+    }
+
+    /// すべての疎集合を空にします。
+    pub(crate) fn clear(&mut self) {
+        self.sets.clear();
+    }
+
+    /// `entity` のコンポーネントをすべて捨て、取り除いた型を返します。
+    pub(crate) fn remove_all(&mut self, entity: Entity) -> Vec<TypeId> {
+        self.sets
+            .iter_mut()
+            .filter_map(|(id, set)| set.remove_drop(entity).then_some(*id))
+            .collect()
+    }
+
+    /// クエリの `fetch` 用に、`entity` の `T` の値とティックへの書き込み用ポインタを返します。
+    ///
+    /// 共有参照から取り出すので、同じ `World` を読む他のクエリと並んで使えます。
+    ///
+    /// # Safety
+    /// 返したポインタを使う間、他に `T` を読み書きする借用がなく、疎集合の追加/削除が
+    /// 起きない必要があります。
+    pub(crate) unsafe fn fetch_ptrs<T: Component>(
+        &self,
+        entity: Entity,
+    ) -> Option<(*mut T, *mut ComponentTicks)> {
+        self.get::<T>()?.ptrs(entity)
+    }
+}
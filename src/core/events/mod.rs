@@ -29,6 +29,11 @@ impl<T> Events<T> {
         self.read_ref().len()
     }
 
+    /// 読めるイベントを消費せずに返します。
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.read_ref().iter()
+    }
+
     pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
         self.read_mut().drain(..)
     }
@@ -1,9 +1,19 @@
 pub mod app;
+pub mod condition;
+pub mod diagnostics;
+pub use diagnostics::ScheduleDiagnostics;
 pub mod plugin;
+pub mod runner;
+pub use runner::{AppExit, FixedStepOrder, FixedStepPolicy, HeadlessRunner, Runner};
 pub mod schedule;
+pub mod schedule_graph;
+pub mod state;
+pub mod system;
+pub mod system_param;
 pub use app::App;
 pub use plugin::Plugin;
 pub use schedule::{Schedule, Stage};
+pub use system::{IntoSystem, System};
 pub mod input;
 pub use input::Input;
 pub mod events;
@@ -11,7 +21,7 @@ pub use events::Events;
 pub mod dicontainer;
 pub use dicontainer::DiContainer;
 pub mod ecs;
-pub use ecs::{Component, Entity, World};
+pub use ecs::{Bundle, Component, Entity, World};
 pub mod time;
 pub use time::{Time, TimeFixed, TimeState};
 pub mod asset;
@@ -0,0 +1,153 @@
+//! `App::run` のメインループ。
+//!
+//! 1 フレームの中身（入力、`Update` と `FixedUpdate`、描画、`LateUpdate`）は `App::run_frame` が
+//! 実行し、`Runner` はそれを呼ぶループとフレームの時間を受け持ちます。ウィンドウを開くなら
+//! `WinitBackend`、ツールやテストなら `HeadlessRunner` を `App::set_runner` で設定します。
+//!
+//! ```ignore
+//! app.set_fixed_step_policy(FixedStepPolicy {
+//!     max_steps: Some(5),
+//!     ..FixedStepPolicy::default()
+//! })
+//! .set_runner(HeadlessRunner::new().frames(600).frame_time(Duration::from_millis(16)))
+//! .run();
+//! ```
+
+use crate::core::App;
+use std::time::{Duration, Instant};
+
+/// `App` を終了まで動かすメインループ。
+pub trait Runner: 'static {
+    fn run(&mut self, app: &mut App);
+}
+
+impl<F: FnMut(&mut App) + 'static> Runner for F {
+    fn run(&mut self, app: &mut App) {
+        self(app)
+    }
+}
+
+/// `FixedUpdate` を `Update` の前後どちらで実行するか。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FixedStepOrder {
+    /// `Update` の後に `FixedUpdate` を実行します。
+    #[default]
+    UpdateFirst,
+    /// `FixedUpdate` の後に `Update` を実行します。`Update` から物理の結果をそのフレームで読めます。
+    FixedFirst,
+}
+
+/// `App::run_frame` の固定ステップの進め方。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedStepPolicy {
+    /// 1 フレームで積み増す時間の上限（既定 0.25 秒）。止まっていた後に `FixedUpdate` が
+    /// まとめて走るのを防ぎます。
+    pub max_frame_time: Duration,
+    /// 1 フレームで実行する `FixedUpdate` の上限。`None` なら上限はありません。
+    /// 上限を超えた分の遅れは捨て、1 ステップに満たない端数だけを持ち越します。
+    pub max_steps: Option<u32>,
+    pub order: FixedStepOrder,
+}
+
+impl Default for FixedStepPolicy {
+    fn default() -> Self {
+        Self {
+            max_frame_time: Duration::from_millis(250),
+            max_steps: None,
+            order: FixedStepOrder::UpdateFirst,
+        }
+    }
+}
+
+/// システムからメインループの終了を要求するためのリソース。`App` が登録します。
+#[derive(Debug, Default)]
+pub struct AppExit {
+    requested: bool,
+}
+
+impl AppExit {
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested
+    }
+}
+
+/// ウィンドウを開かずにフレームを回すランナー。`App::run` の既定です。
+///
+/// `AppExit` が要求されるか、`frames` で指定したフレーム数を回ると止まります。どちらもなければ
+/// 止まらないので、既定では 1 フレームを最短 1/60 秒に保ち、CPU を使い切らないようにします。
+#[derive(Debug, Clone)]
+pub struct HeadlessRunner {
+    frames: Option<u64>,
+    frame_time: Option<Duration>,
+    frame_interval: Duration,
+}
+
+impl Default for HeadlessRunner {
+    fn default() -> Self {
+        Self {
+            frames: None,
+            frame_time: None,
+            frame_interval: Duration::from_secs(1) / 60,
+        }
+    }
+}
+
+impl HeadlessRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `frames` フレームで止めます。
+    pub fn frames(mut self, frames: u64) -> Self {
+        self.frames = Some(frames);
+        self
+    }
+
+    /// 実時間の代わりに毎フレーム `frame_time` だけ時間を進めます。結果を再現したいテストや
+    /// ツール向けです。設定するとフレームの間で待たず、できるだけ速く回します。
+    pub fn frame_time(mut self, frame_time: Duration) -> Self {
+        self.frame_time = Some(frame_time);
+        self
+    }
+
+    /// 1 フレームの最短の実時間（既定 1/60 秒）。早く終わったフレームは残りを待ちます。
+    /// `Duration::ZERO` なら待たずに次のフレームへ進みます。`frame_time` を設定した場合は
+    /// 使いません。
+    pub fn frame_interval(mut self, frame_interval: Duration) -> Self {
+        self.frame_interval = frame_interval;
+        self
+    }
+}
+
+impl Runner for HeadlessRunner {
+    fn run(&mut self, app: &mut App) {
+        let mut last_instant = Instant::now();
+        let mut next_frame = last_instant;
+        let mut frame = 0;
+        // 決まった時間で回す場合は実時間に合わせる必要がありません。
+        let frame_interval = if self.frame_time.is_some() {
+            Duration::ZERO
+        } else {
+            self.frame_interval
+        };
+        while !app.exit_requested() && self.frames.is_none_or(|frames| frame < frames) {
+            let now = Instant::now();
+            if now < next_frame {
+                std::thread::sleep(next_frame - now);
+            }
+            let now = Instant::now();
+            next_frame = now + frame_interval;
+            let frame_time = self.frame_time.unwrap_or_else(|| {
+                let elapsed = now - last_instant;
+                last_instant = now;
+                elapsed
+            });
+            app.run_frame(frame_time);
+            frame += 1;
+        }
+    }
+}
@@ -1,7 +1,27 @@
+pub use crate::core::condition::{every_seconds, not, resource_exists, Condition};
+pub use crate::core::diagnostics::ScheduleDiagnostics;
 pub use crate::core::ecs;
+use crate::core::plugin::Plugin;
+pub use crate::core::schedule_graph::{ScheduleGraph, StageInfo, StateScheduleInfo, SystemInfo};
+pub use crate::core::state::{in_state, OnEnter, OnExit, OnUpdate, State, States};
+use crate::core::state::{StateDriver, StateSchedules};
+pub use crate::core::system::{
+    BoxedSystemFn, ConfiguredSystem, IntoSystem, System, SystemLabel, SystemOrder,
+};
+pub use crate::core::system_param::{
+    EventReader, EventWriter, Query, Res, ResMut, SystemAccess, SystemError, SystemParam,
+};
 pub use crate::core::{DiContainer, Events, Time, TimeState};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use thiserror::Error;
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+/// 実行の位相。組み込みの 7 つに加えて、`Schedule::add_stage_after` /
+/// `add_stage_before` で `Stage::Custom` を既存のステージの前後に追加できます。
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Stage {
     Startup,
     ProcessInput,
@@ -10,17 +30,610 @@ pub enum Stage {
     PreRender,
     Render,
     LateUpdate,
+    /// 名前で区別するユーザー定義のステージ。
+    Custom(&'static str),
 }
 
-type System = fn(&mut DiContainer, &mut ecs::World);
+/// 組み込みのステージはその名前、`Stage::Custom` は付けた名前で表示します。
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Custom(name) => f.write_str(name),
+            stage => fmt::Debug::fmt(stage, f),
+        }
+    }
+}
+
+/// 登録したシステムを後から無効にしたり取り除いたりするためのハンドル。
+/// `Schedule::register_system` / `App::register_system` が返します。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemHandle(u64);
+
+impl SystemHandle {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// 有効/無効の切り替えや削除の対象。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SystemTarget<'a> {
+    Handle(SystemHandle),
+    /// プラグインの型名。そのプラグインが登録したシステムすべてが対象です。
+    Plugin(&'a str),
+}
+
+impl SystemTarget<'_> {
+    fn matches(&self, entry: &SystemEntry) -> bool {
+        match *self {
+            SystemTarget::Handle(handle) => entry.handle == handle,
+            SystemTarget::Plugin(plugin) => entry.plugin == Some(plugin),
+        }
+    }
+}
+
+/// 登録されたシステムと、変更検出の基準になる前回実行時のワールドのティック。
+struct SystemEntry {
+    system: Box<dyn System>,
+    handle: SystemHandle,
+    priority: usize,
+    plugin: Option<&'static str>,
+    enabled: bool,
+    last_run: u64,
+    initialized: bool,
+}
+
+/// ステージ内の実行順を決められなかった理由。`stage` はステージまたは状態のスケジュールの名前です。
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    #[error("systems in stage {stage} form an ordering cycle: {}", .systems.join(" -> "))]
+    Cycle { stage: String, systems: Vec<String> },
+    #[error("system {system} in stage {stage} is ordered against label {label}, which no system in the stage has")]
+    UnknownLabel {
+        stage: String,
+        system: String,
+        label: SystemLabel,
+    },
+    #[error("systems {first} and {second} in stage {stage} both access {name} without an explicit order")]
+    Ambiguous {
+        stage: String,
+        first: String,
+        second: String,
+        name: &'static str,
+    },
+}
+
+/// ステージ内のシステムの実行方法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Executor {
+    /// 借用を宣言したシステムのうち、競合せず順序の制約もないものを rayon の
+    /// スレッドプールで並列に実行します。
+    #[default]
+    MultiThreaded,
+    /// 解決済みの順に 1 つずつ実行します。デバッグで実行順を固定したいときに使います。
+    SingleThreaded,
+}
+
+/// 計測中であれば、システムの実行時間を `ScheduleDiagnostics` に記録します。
+fn record_system(
+    di: &mut DiContainer,
+    stage_name: Option<&str>,
+    entry: &SystemEntry,
+    duration: Duration,
+) {
+    if let Some(stage) = stage_name {
+        if let Some(diagnostics) = di.get_mut::<ScheduleDiagnostics>() {
+            diagnostics.record_system(stage, entry.handle, &entry.system.name(), duration);
+        }
+    }
+}
+
+/// `order`（トポロジカル順）と制約の辺から、各システムより後に実行するシステムを求めます。
+fn reachability(order: &[usize], successors: &[Vec<usize>]) -> Vec<Vec<bool>> {
+    let count = order.len();
+    let mut reachable = vec![vec![false; count]; count];
+    for &index in order.iter().rev() {
+        for &next in &successors[index] {
+            let reached = reachable[next].clone();
+            let from = &mut reachable[index];
+            from[next] = true;
+            for (slot, reached) in from.iter_mut().zip(reached) {
+                *slot |= reached;
+            }
+        }
+    }
+    reachable
+}
+
+/// ステージと状態のスケジュールに共通する設定。
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ScheduleSettings {
+    pub(crate) deny_ambiguities: bool,
+    pub(crate) executor: Executor,
+}
+
+/// 1 つのステージのシステムと、解決済みの実行順。
+#[derive(Default)]
+pub(crate) struct StageSystems {
+    systems: Vec<SystemEntry>,
+    order: Vec<usize>,
+    // successors[a] に b があれば a を b より先に実行する（`before` / `after` の制約）
+    successors: Vec<Vec<usize>>,
+    // reachable[a][b]: a から b に制約の辺をたどって到達できる（a は b より先に実行する）
+    reachable: Vec<Vec<bool>>,
+    dirty: bool,
+    label_conditions: Vec<(SystemLabel, Box<dyn Condition>)>,
+}
+
+impl StageSystems {
+    pub(crate) fn add(
+        &mut self,
+        priority: usize,
+        system: Box<dyn System>,
+        plugin: Option<&'static str>,
+    ) -> SystemHandle {
+        // Clamp the incoming priority to a sane upper bound so that priorities
+        // stay within the documented range.
+        let capped = if priority > MAX_PRIORITY {
+            log::warn!(
+                "add_system: priority {} > MAX_PRIORITY ({}); clamping to {}",
+                priority,
+                MAX_PRIORITY,
+                MAX_PRIORITY
+            );
+            MAX_PRIORITY
+        } else {
+            priority
+        };
+        let handle = SystemHandle::next();
+        self.systems.push(SystemEntry {
+            system,
+            handle,
+            priority: capped,
+            plugin,
+            enabled: true,
+            last_run: 0,
+            initialized: false,
+        });
+        self.dirty = true;
+        handle
+    }
+
+    /// `target` のシステムを有効/無効にし、見つかった数を返します。
+    pub(crate) fn set_enabled(&mut self, target: SystemTarget, enabled: bool) -> usize {
+        let mut found = 0;
+        for entry in self
+            .systems
+            .iter_mut()
+            .filter(|entry| target.matches(entry))
+        {
+            entry.enabled = enabled;
+            found += 1;
+        }
+        found
+    }
+
+    /// `target` のシステムを取り除き、取り除いた数を返します。実行順は次の実行で解決し直します。
+    pub(crate) fn remove(&mut self, target: SystemTarget) -> usize {
+        let count = self.systems.len();
+        self.systems.retain(|entry| !target.matches(entry));
+        let removed = count - self.systems.len();
+        if removed > 0 {
+            self.dirty = true;
+        }
+        removed
+    }
+
+    pub(crate) fn is_enabled(&self, handle: SystemHandle) -> Option<bool> {
+        self.systems
+            .iter()
+            .find(|entry| entry.handle == handle)
+            .map(|entry| entry.enabled)
+    }
+
+    /// 解決済みの順にシステムを実行します。`Schedule::run_stage` を参照してください。
+    pub(crate) fn run(
+        &mut self,
+        stage: &dyn fmt::Display,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    ) {
+        if self.dirty {
+            if let Err(err) = self.build(stage, settings.deny_ambiguities) {
+                panic!("{err}");
+            }
+        }
+
+        // 計測は `ScheduleDiagnostics` があるときだけ行い、空のステージは記録しません。
+        let stage_name = di
+            .get::<ScheduleDiagnostics>()
+            .filter(|_| !self.systems.is_empty())
+            .map(|_| stage.to_string());
+        let stage_name = stage_name.as_deref();
+        let started = Instant::now();
+
+        if di.get::<ecs::Commands>().is_none() {
+            di.insert(ecs::Commands::new(world));
+        }
+        world.flush_observers(di);
+
+        let StageSystems {
+            systems,
+            order,
+            reachable,
+            label_conditions,
+            ..
+        } = self;
+        let mut label_results = vec![None; label_conditions.len()];
+        // 並列に実行するために集めている、互いに競合しないシステム
+        let mut batch = Vec::new();
+        for &index in order.iter() {
+            let entry = &mut systems[index];
+            if !entry.enabled {
+                continue;
+            }
+            // 条件は先行するシステムの結果を見て判定するので、制約で先に実行するシステムが
+            // バッチに残っていれば先に実行します。そのバッチにはどのみち加われません。
+            if batch.iter().any(|&other: &usize| reachable[other][index]) {
+                Self::run_batch(systems, &mut batch, stage_name, di, world);
+            }
+            let entry = &mut systems[index];
+            let labels = entry.system.order().map_or(&[][..], |order| order.labels());
+            let group_runs = label_conditions
+                .iter_mut()
+                .zip(label_results.iter_mut())
+                .all(|((label, condition), result)| {
+                    !labels.contains(label) || *result.get_or_insert_with(|| condition.evaluate(di))
+                });
+            if !group_runs || !entry.system.should_run(di) {
+                continue;
+            }
+
+            let parallel =
+                settings.executor == Executor::MultiThreaded && entry.system.access().is_some();
+            if !parallel {
+                Self::run_batch(systems, &mut batch, stage_name, di, world);
+                Self::run_system(&mut systems[index], stage_name, di, world);
+                continue;
+            }
+            let access = systems[index].system.access();
+            let joins = batch.iter().all(|&other: &usize| {
+                !reachable[other][index]
+                    && systems[other]
+                        .system
+                        .access()
+                        .zip(access)
+                        .is_some_and(|(other, access)| other.conflicts_with(access).is_none())
+            });
+            if !joins {
+                Self::run_batch(systems, &mut batch, stage_name, di, world);
+            }
+            batch.push(index);
+        }
+        Self::run_batch(systems, &mut batch, stage_name, di, world);
+
+        if let Some(commands) = di.get_mut::<ecs::Commands>() {
+            commands.apply(world);
+        }
+        world.flush_observers(di);
+
+        if let Some(stage) = stage_name {
+            if let Some(diagnostics) = di.get_mut::<ScheduleDiagnostics>() {
+                diagnostics.record_stage(stage, started.elapsed());
+            }
+        }
+    }
+
+    fn run_system(
+        entry: &mut SystemEntry,
+        stage_name: Option<&str>,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    ) {
+        if !entry.initialized {
+            entry.system.initialize(di, world);
+            entry.initialized = true;
+        }
+        let tick = world.change_tick();
+        world.set_last_change_tick(entry.last_run);
+        let started = Instant::now();
+        entry.system.run(di, world);
+        record_system(di, stage_name, entry, started.elapsed());
+        entry.last_run = tick;
+        world.increment_change_tick();
+        world.flush_observers(di);
+    }
+
+    /// `batch` のシステムを並列に実行し、`batch` を空にします。
+    ///
+    /// 初期化、変更検出のティックの設定、引数の取り出しはこのスレッドで順に行い、
+    /// 取り出した引数でシステムの本体だけを並列に実行します。
+    fn run_batch(
+        systems: &mut [SystemEntry],
+        batch: &mut Vec<usize>,
+        stage_name: Option<&str>,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    ) {
+        if batch.len() <= 1 {
+            if let Some(index) = batch.pop() {
+                Self::run_system(&mut systems[index], stage_name, di, world);
+            }
+            return;
+        }
+
+        // 引数を取り出す前にすべて初期化し、以降は `PreparedRun` が残っている間
+        // `&mut DiContainer` / `&mut World` を作りません。
+        for &index in batch.iter() {
+            let entry = &mut systems[index];
+            if !entry.initialized {
+                entry.system.initialize(di, world);
+                entry.initialized = true;
+            }
+        }
+        // 各システムのティックは、順に 1 つずつ実行した場合と同じになるよう先に決めます。
+        let first_tick = world.change_tick();
+
+        let di_ptr: *mut DiContainer = di;
+        let world_ptr: *mut ecs::World = world;
+        let mut entries: Vec<_> = systems.iter_mut().map(Some).collect();
+        let mut runs = Vec::with_capacity(batch.len());
+        // `prepare` を持たないシステムは借用を宣言していても並列に走らせず、後で単独で実行します。
+        let mut exclusive = Vec::new();
+        for (offset, &index) in batch.iter().enumerate() {
+            let entry = entries[index]
+                .take()
+                .expect("a system appears in the batch only once");
+            let this_run = first_tick + offset as u64;
+            let ticks = ecs::Ticks {
+                last_run: entry.last_run,
+                this_run,
+            };
+            // SAFETY: `world_ptr` は有効で、ティックはアトミックなので、先に取り出した引数が
+            // `World` を共有参照で借用していても設定できます。取り出し済みの引数はティックを
+            // 取り出した時点で写し取っています。
+            unsafe { (*world_ptr).set_system_ticks(ticks) };
+            // SAFETY: バッチ内のシステムは `SystemAccess::conflicts_with` で互いに競合しないことを
+            // 検査済みです。`PreparedRun` は下の `rayon::scope` が終わるまでに消費されます。
+            match unsafe { entry.system.prepare(di_ptr, world_ptr) } {
+                Some(run) => {
+                    runs.push((index, run));
+                    entry.last_run = this_run;
+                }
+                None => exclusive.push(index),
+            }
+        }
+        let (indices, runs): (Vec<_>, Vec<_>) = runs.into_iter().unzip();
+        let mut durations = vec![Duration::ZERO; runs.len()];
+        rayon::scope(|scope| {
+            for (run, duration) in runs.into_iter().zip(durations.iter_mut()) {
+                scope.spawn(move |_| {
+                    let started = Instant::now();
+                    run.run();
+                    *duration = started.elapsed();
+                });
+            }
+        });
+        drop(entries);
+        // SAFETY: `rayon::scope` はすべての `PreparedRun` を実行し終えてから戻るので、
+        // 取り出した引数はもう残っていません。
+        let (di, world) = unsafe { (&mut *di_ptr, &mut *world_ptr) };
+        world.set_system_ticks(ecs::Ticks {
+            last_run: world.last_change_tick(),
+            this_run: first_tick + batch.len() as u64,
+        });
+        for (index, duration) in indices.into_iter().zip(durations) {
+            record_system(di, stage_name, &systems[index], duration);
+        }
+        world.flush_observers(di);
+        for index in exclusive {
+            Self::run_system(&mut systems[index], stage_name, di, world);
+        }
+        batch.clear();
+    }
+
+    /// 解決済みの実行順に並べたシステムの情報。先に `build` しておく必要があります。
+    fn info(&self, stage: Stage) -> StageInfo {
+        let (systems, constraints) = self.systems_info();
+        StageInfo {
+            stage,
+            systems,
+            constraints,
+        }
+    }
+
+    /// 解決済みの実行順に並べたシステムと、その並びでの `before` / `after` の制約。
+    pub(crate) fn systems_info(&self) -> (Vec<SystemInfo>, Vec<(usize, usize)>) {
+        let mut position = vec![0; self.systems.len()];
+        for (rank, &index) in self.order.iter().enumerate() {
+            position[index] = rank;
+        }
+        let systems = self
+            .order
+            .iter()
+            .map(|&index| {
+                let entry = &self.systems[index];
+                SystemInfo {
+                    name: entry.system.name().into_owned(),
+                    priority: entry.priority,
+                    plugin: entry.plugin,
+                    enabled: entry.enabled,
+                    labels: entry
+                        .system
+                        .order()
+                        .map_or_else(Vec::new, |order| order.labels().to_vec()),
+                }
+            })
+            .collect();
+        let constraints = self
+            .successors
+            .iter()
+            .enumerate()
+            .flat_map(|(from, tos)| tos.iter().map(move |&to| (from, to)))
+            .map(|(from, to)| (position[from], position[to]))
+            .collect();
+        (systems, constraints)
+    }
+
+    /// `before` / `after` をトポロジカルソートで解決します。
+    ///
+    /// 制約で順序が決まらないシステム同士は、優先度、登録順の順に並べます。
+    pub(crate) fn build(
+        &mut self,
+        stage: &dyn fmt::Display,
+        deny_ambiguities: bool,
+    ) -> Result<(), ScheduleError> {
+        let count = self.systems.len();
+        let mut labeled: HashMap<&SystemLabel, Vec<usize>> = HashMap::new();
+        for (index, entry) in self.systems.iter().enumerate() {
+            for label in entry.system.order().map_or(&[][..], |o| o.labels()) {
+                labeled.entry(label).or_default().push(index);
+            }
+        }
+
+        // successors[a] に b があれば a を b より先に実行します。
+        let mut successors = vec![Vec::new(); count];
+        for (index, entry) in self.systems.iter().enumerate() {
+            let Some(order) = entry.system.order() else {
+                continue;
+            };
+            let constraints = order
+                .before()
+                .iter()
+                .map(|label| (label, true))
+                .chain(order.after().iter().map(|label| (label, false)));
+            for (label, before) in constraints {
+                let Some(others) = labeled.get(label) else {
+                    return Err(ScheduleError::UnknownLabel {
+                        stage: stage.to_string(),
+                        system: entry.system.name().into_owned(),
+                        label: label.clone(),
+                    });
+                };
+                for &other in others.iter().filter(|&&other| other != index) {
+                    let (from, to) = if before {
+                        (index, other)
+                    } else {
+                        (other, index)
+                    };
+                    if !successors[from].contains(&to) {
+                        successors[from].push(to);
+                    }
+                }
+            }
+        }
+
+        let mut in_degree = vec![0usize; count];
+        for &to in successors.iter().flatten() {
+            in_degree[to] += 1;
+        }
+        let mut ready: BinaryHeap<_> = (0..count)
+            .filter(|&index| in_degree[index] == 0)
+            .map(|index| Reverse((self.systems[index].priority, index)))
+            .collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(Reverse((_, index))) = ready.pop() {
+            order.push(index);
+            for &next in &successors[index] {
+                in_degree[next] -= 1;
+                if in_degree[next] == 0 {
+                    ready.push(Reverse((self.systems[next].priority, next)));
+                }
+            }
+        }
+        if order.len() < count {
+            return Err(ScheduleError::Cycle {
+                stage: stage.to_string(),
+                systems: self.find_cycle(&successors, &in_degree),
+            });
+        }
+
+        let reachable = reachability(&order, &successors);
+        if deny_ambiguities {
+            self.check_ambiguities(stage, &order, &reachable)?;
+        }
+        self.order = order;
+        self.successors = successors;
+        self.reachable = reachable;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// ソートで残ったシステムから 1 つの閉路を取り出します。
+    ///
+    /// 残ったシステムは必ず残ったシステムを前に持つので、前をたどると閉路に戻ります。
+    fn find_cycle(&self, successors: &[Vec<usize>], in_degree: &[usize]) -> Vec<String> {
+        let mut predecessors = vec![Vec::new(); successors.len()];
+        for (from, tos) in successors.iter().enumerate() {
+            for &to in tos {
+                predecessors[to].push(from);
+            }
+        }
+        let mut path = Vec::new();
+        let mut current = (0..in_degree.len())
+            .find(|&index| in_degree[index] > 0)
+            .expect("a cycle leaves systems unsorted");
+        while !path.contains(&current) {
+            path.push(current);
+            current = *predecessors[current]
+                .iter()
+                .find(|&&from| in_degree[from] > 0)
+                .expect("an unsorted system has an unsorted predecessor");
+        }
+        let start = path.iter().position(|&index| index == current).unwrap();
+        let mut cycle: Vec<String> = path[start..]
+            .iter()
+            .rev()
+            .map(|&index| self.systems[index].system.name().into_owned())
+            .collect();
+        cycle.push(cycle[0].clone());
+        cycle
+    }
+
+    /// 借用が競合するのに `before` / `after` で順序が決まっていない組を報告します。
+    ///
+    /// 借用を宣言していない（`System::access` が `None` の）システムは対象外です。
+    fn check_ambiguities(
+        &self,
+        stage: &dyn fmt::Display,
+        order: &[usize],
+        reachable: &[Vec<bool>],
+    ) -> Result<(), ScheduleError> {
+        for (position, &first) in order.iter().enumerate() {
+            let Some(first_access) = self.systems[first].system.access() else {
+                continue;
+            };
+            for &second in &order[position + 1..] {
+                let Some(second_access) = self.systems[second].system.access() else {
+                    continue;
+                };
+                if reachable[first][second] || reachable[second][first] {
+                    continue;
+                }
+                if let Some(name) = first_access.conflicts_with(second_access) {
+                    return Err(ScheduleError::Ambiguous {
+                        stage: stage.to_string(),
+                        first: self.systems[first].system.name().into_owned(),
+                        second: self.systems[second].system.name().into_owned(),
+                        name,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
 /// Maximum allowed priority index. Values above this will be clamped to this value.
 ///
 /// Use a named constant to avoid magic numbers sprinkled around the codebase.
 pub const MAX_PRIORITY: usize = 8;
 /// Convenience priority levels to avoid sprinkling raw numbers in callers.
 ///
-/// These map to internal bucket indices; callers can pass `Priority` or a
-/// `usize` directly (both are accepted by `add_system`).
+/// These map to priority indices; callers can pass `Priority` or a
+/// `usize` directly (both are accepted by `add_system`). Priorities only
+/// break ties between systems not ordered by `before` / `after`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Priority {
     Highest,
@@ -41,95 +654,341 @@ impl From<Priority> for usize {
         }
     }
 }
+/// 登録されたステージ。`anchor` は実行を受け持つ組み込みのステージで、
+/// 組み込みのステージでは自身、追加したステージでは前後に挿入した先のものです。
+struct StageSlot {
+    stage: Stage,
+    anchor: Stage,
+    systems: StageSystems,
+}
+
 pub struct Schedule {
-    // Each stage holds priority buckets (Vec of Vec<System>). Lower index = higher priority (runs earlier).
-    startup: Vec<Vec<System>>,
-    update: Vec<Vec<System>>,
-    late_update: Vec<Vec<System>>,
-    render: Vec<Vec<System>>,
-    fixed_update: Vec<Vec<System>>,
-    process_input: Vec<Vec<System>>,
-    pre_render: Vec<Vec<System>>,
+    // Stages in run order; each holds its systems and the resolved run order
+    // (see `StageSystems::build`).
+    stages: Vec<StageSlot>,
+    // `App::add_state` で登録した状態ごとの `OnEnter` / `OnExit` / `OnUpdate` のスケジュール
+    states: Vec<Box<dyn StateDriver>>,
+    settings: ScheduleSettings,
 }
 
 impl Schedule {
     pub fn new() -> Self {
+        let stages = [
+            Stage::Startup,
+            Stage::ProcessInput,
+            Stage::Update,
+            Stage::FixedUpdate,
+            Stage::PreRender,
+            Stage::Render,
+            Stage::LateUpdate,
+        ]
+        .into_iter()
+        .map(|stage| StageSlot {
+            stage,
+            anchor: stage,
+            systems: StageSystems::default(),
+        })
+        .collect();
         Self {
-            // start each stage with a single default priority bucket
-            startup: vec![vec![]],
-            update: vec![vec![]],
-            late_update: vec![vec![]],
-            render: vec![vec![]],
-            fixed_update: vec![vec![]],
-            process_input: vec![vec![]],
-            pre_render: vec![vec![]],
+            stages,
+            states: Vec::new(),
+            settings: ScheduleSettings::default(),
+        }
+    }
+
+    fn position(&self, stage: Stage) -> Option<usize> {
+        self.stages.iter().position(|slot| slot.stage == stage)
+    }
+
+    fn stage_mut(&mut self, stage: Stage) -> &mut StageSystems {
+        match self.position(stage) {
+            Some(index) => &mut self.stages[index].systems,
+            None => panic!(
+                "stage {stage:?} is not registered; add it with add_stage_after or add_stage_before"
+            ),
+        }
+    }
+
+    /// `stage` を `existing` の直後に追加します。`stage` は `existing` と同じ `App` の位相
+    /// （例えば `FixedUpdate` の後なら `App::fixed_update`）で実行されます。
+    ///
+    /// `existing` が登録されていない場合や `stage` が登録済みの場合は panic します。
+    pub fn add_stage_after(&mut self, existing: Stage, stage: Stage) -> &mut Self {
+        self.insert_stage(existing, stage, 1)
+    }
+
+    /// `stage` を `existing` の直前に追加します。`add_stage_after` を参照してください。
+    pub fn add_stage_before(&mut self, existing: Stage, stage: Stage) -> &mut Self {
+        self.insert_stage(existing, stage, 0)
+    }
+
+    fn insert_stage(&mut self, existing: Stage, stage: Stage, offset: usize) -> &mut Self {
+        if self.position(stage).is_some() {
+            panic!("stage {stage:?} is already registered");
         }
+        let Some(index) = self.position(existing) else {
+            panic!("stage {existing:?} is not registered");
+        };
+        let anchor = self.stages[index].anchor;
+        self.stages.insert(
+            index + offset,
+            StageSlot {
+                stage,
+                anchor,
+                systems: StageSystems::default(),
+            },
+        );
+        self
     }
+
+    /// 登録されているステージを実行順に返します。
+    pub fn stages(&self) -> impl Iterator<Item = Stage> + '_ {
+        self.stages.iter().map(|slot| slot.stage)
+    }
+
     // 注意: スケジュール API は優先度優先です。システム登録時に明示的な
     // 優先度を渡してください。`add_system` は優先度を受け取り、使用を簡潔
     // にして自己説明的にします。
 
-    /// 指定したステージにシステムを追加します（優先度バケット付き）。
-    /// `priority` の値が小さいほど先に実行されます。バケットは必要に応じて作成されます。
+    /// 指定したステージにシステムを追加します（優先度付き）。
+    /// `before` / `after` の制約がないシステム同士は、`priority` の値が小さいほど先に実行されます。
     ///
-    /// 補足: 内部バケットの無制限拡張を防ぐため、`priority` は `MAX_PRIORITY` にクランプ
-    /// されます。`MAX_PRIORITY` を超える値が与えられた場合はログで警告し、クランプします。
-    pub fn add_system<I: Into<usize>>(
+    /// 補足: `priority` は `MAX_PRIORITY` にクランプされます。`MAX_PRIORITY` を超える値が
+    /// 与えられた場合はログで警告し、クランプします。
+    pub fn add_system<I: Into<usize>, M>(
         &mut self,
         stage: Stage,
         priority: I,
-        system: System,
+        system: impl IntoSystem<M>,
     ) -> &mut Self {
-        let v = match stage {
-            Stage::Startup => &mut self.startup,
-            Stage::Update => &mut self.update,
-            Stage::LateUpdate => &mut self.late_update,
-            Stage::Render => &mut self.render,
-            Stage::FixedUpdate => &mut self.fixed_update,
-            Stage::ProcessInput => &mut self.process_input,
-            Stage::PreRender => &mut self.pre_render,
-        };
         // Accept either a `usize` or a `Priority` (which implements Into<usize>).
-        let priority = priority.into();
+        self.add_boxed_system(stage, priority.into(), Box::new(system.into_system()), None);
+        self
+    }
 
-        // Clamp the incoming priority to a sane upper bound to prevent a
-        // caller from accidentally creating a huge number of empty buckets.
-        let capped = if priority > MAX_PRIORITY {
-            log::warn!(
-                "Schedule::add_system: priority {} > MAX_PRIORITY ({}); clamping to {}",
-                priority,
-                MAX_PRIORITY,
-                MAX_PRIORITY
-            );
-            MAX_PRIORITY
-        } else {
-            priority
+    /// `add_system` と同じですが、後から `disable_system` / `remove_system` などで
+    /// 操作するためのハンドルを返します。
+    pub fn register_system<I: Into<usize>, M>(
+        &mut self,
+        stage: Stage,
+        priority: I,
+        system: impl IntoSystem<M>,
+    ) -> SystemHandle {
+        self.add_boxed_system(stage, priority.into(), Box::new(system.into_system()), None)
+    }
+
+    /// `plugin` は診断や有効/無効の切り替えに使う、システムを登録したプラグインの型名です。
+    pub(crate) fn add_boxed_system(
+        &mut self,
+        stage: Stage,
+        priority: usize,
+        system: Box<dyn System>,
+        plugin: Option<&'static str>,
+    ) -> SystemHandle {
+        self.stage_mut(stage).add(priority, system, plugin)
+    }
+
+    /// 全ステージと状態のスケジュールのシステム。
+    pub(crate) fn stage_systems_mut(&mut self) -> impl Iterator<Item = &mut StageSystems> {
+        self.stages.iter_mut().map(|slot| &mut slot.systems).chain(
+            self.states
+                .iter_mut()
+                .flat_map(|states| states.stage_systems_mut()),
+        )
+    }
+
+    /// `S` の状態のスケジュールを（まだなければ）用意します。
+    pub(crate) fn add_state<S: States>(&mut self) {
+        if self.state_schedules::<S>().is_none() {
+            self.states.push(Box::new(StateSchedules::<S>::new()));
+        }
+    }
+
+    pub(crate) fn state_schedules<S: States>(&mut self) -> Option<&mut StateSchedules<S>> {
+        self.states
+            .iter_mut()
+            .find_map(|states| states.as_any_mut().downcast_mut::<StateSchedules<S>>())
+    }
+
+    /// 各状態の最初の `OnEnter` を実行します。`StateDriver::enter_initial` を参照してください。
+    pub(crate) fn enter_initial_states(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        let settings = self.settings;
+        for states in &mut self.states {
+            states.enter_initial(settings, di, world);
+        }
+    }
+
+    /// 各状態の現在の `OnUpdate` を実行します。
+    pub(crate) fn run_state_updates(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        let settings = self.settings;
+        for states in &mut self.states {
+            states.run_update(settings, di, world);
+        }
+    }
+
+    /// 各状態で要求された切り替えを適用します。
+    pub(crate) fn apply_state_transitions(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        let settings = self.settings;
+        for states in &mut self.states {
+            states.apply_transition(settings, di, world);
+        }
+    }
+
+    /// システムを有効に戻します。見つからなければ `false` を返します。
+    pub fn enable_system(&mut self, handle: SystemHandle) -> bool {
+        self.set_enabled(SystemTarget::Handle(handle), true) > 0
+    }
+
+    /// システムを無効にします。無効なシステムは有効に戻すまで実行されず、実行条件も評価しません。
+    /// `before` / `after` の制約には引き続き加わります。見つからなければ `false` を返します。
+    pub fn disable_system(&mut self, handle: SystemHandle) -> bool {
+        self.set_enabled(SystemTarget::Handle(handle), false) > 0
+    }
+
+    /// システムを取り除きます。見つからなければ `false` を返します。
+    ///
+    /// 実行順は次の実行で解決し直すので、取り除いたシステムのラベルに `before` / `after` で
+    /// 依存しているシステムが残っていると、そのときに panic します。
+    pub fn remove_system(&mut self, handle: SystemHandle) -> bool {
+        self.stage_systems_mut()
+            .map(|systems| systems.remove(SystemTarget::Handle(handle)))
+            .sum::<usize>()
+            > 0
+    }
+
+    /// システムが有効かどうか。取り除かれていれば `None` を返します。
+    pub fn is_system_enabled(&self, handle: SystemHandle) -> Option<bool> {
+        self.stages
+            .iter()
+            .map(|slot| &slot.systems)
+            .chain(self.states.iter().flat_map(|states| states.stage_systems()))
+            .find_map(|systems| systems.is_enabled(handle))
+    }
+
+    /// プラグイン `P` が登録したシステムをまとめて有効/無効にし、その数を返します。
+    pub fn set_plugin_enabled<P: Plugin>(&mut self, enabled: bool) -> usize {
+        self.set_enabled(SystemTarget::Plugin(std::any::type_name::<P>()), enabled)
+    }
+
+    /// プラグイン `P` が登録したシステムをまとめて取り除き、その数を返します。
+    pub fn remove_plugin_systems<P: Plugin>(&mut self) -> usize {
+        let target = SystemTarget::Plugin(std::any::type_name::<P>());
+        self.stage_systems_mut()
+            .map(|systems| systems.remove(target))
+            .sum()
+    }
+
+    fn set_enabled(&mut self, target: SystemTarget, enabled: bool) -> usize {
+        self.stage_systems_mut()
+            .map(|systems| systems.set_enabled(target, enabled))
+            .sum()
+    }
+
+    /// `DiContainer` の `ScheduleCommands` に溜まった要求を（状態のスケジュールも含めて）
+    /// 適用します。`App` は各位相の終わりに適用します。
+    pub fn apply_commands(&mut self, di: &mut DiContainer) {
+        let Some(commands) = di.get_mut::<ScheduleCommands>() else {
+            return;
         };
+        for command in commands.take() {
+            for systems in self.stage_systems_mut() {
+                command.apply(systems);
+            }
+        }
+    }
+
+    /// `stage` で `label` の付いたシステムを、`condition` が成り立つときだけ実行します。
+    ///
+    /// 条件はステージの実行ごとに、そのラベルの最初のシステムの直前で 1 回だけ評価します。
+    pub fn add_label_condition(
+        &mut self,
+        stage: Stage,
+        label: impl Into<SystemLabel>,
+        condition: impl Condition,
+    ) -> &mut Self {
+        self.stage_mut(stage)
+            .label_conditions
+            .push((label.into(), Box::new(condition)));
+        self
+    }
 
-        // Ensure enough buckets
-        while v.len() <= capped {
-            v.push(vec![]);
+    /// 借用が競合するのに `before` / `after` で順序が決まっていないシステムの組を
+    /// `ScheduleError::Ambiguous` として報告するかどうか。既定は `false` です。
+    ///
+    /// 対象は `Res<T>` / `Query<Q>` などで借用を宣言したシステムだけです。状態のスケジュールも、
+    /// 次の実行で解決し直して検査します。
+    pub fn set_deny_ambiguities(&mut self, deny: bool) -> &mut Self {
+        self.settings.deny_ambiguities = deny;
+        for systems in self.stage_systems_mut() {
+            systems.dirty = true;
         }
-        v[capped].push(system);
         self
     }
-    pub fn run_stage(&mut self, stage: Stage, di: &mut DiContainer, world: &mut ecs::World) {
-        let buckets = match stage {
-            Stage::Startup => &mut self.startup,
-            Stage::Update => &mut self.update,
-            Stage::LateUpdate => &mut self.late_update,
-            Stage::Render => &mut self.render,
-            Stage::FixedUpdate => &mut self.fixed_update,
-            Stage::ProcessInput => &mut self.process_input,
-            Stage::PreRender => &mut self.pre_render,
-        };
 
-        // Iterate buckets in order; within each bucket preserve insertion order.
-        for bucket in buckets.iter_mut() {
-            for system in bucket.iter().copied() {
-                system(di, world);
+    /// システムの実行方法を切り替えます。既定は `Executor::MultiThreaded` です。
+    pub fn set_executor(&mut self, executor: Executor) -> &mut Self {
+        self.settings.executor = executor;
+        self
+    }
+
+    /// 全ステージと状態のスケジュールの実行順を解決します。
+    ///
+    /// `run_stage` も最初の実行の前に解決しますが、その場合は失敗すると panic します。
+    pub fn build(&mut self) -> Result<(), ScheduleError> {
+        let deny = self.settings.deny_ambiguities;
+        for slot in &mut self.stages {
+            if slot.systems.dirty {
+                slot.systems.build(&slot.stage, deny)?;
             }
         }
+        for states in &mut self.states {
+            states.build(deny)?;
+        }
+        Ok(())
+    }
+
+    /// 全ステージと状態のスケジュールの実行順を解決し、スケジュールごとのシステムの並びを返します。
+    pub fn graph(&mut self) -> Result<ScheduleGraph, ScheduleError> {
+        self.build()?;
+        Ok(ScheduleGraph {
+            stages: self
+                .stages
+                .iter()
+                .map(|slot| slot.systems.info(slot.stage))
+                .collect(),
+            state_schedules: self
+                .states
+                .iter()
+                .flat_map(|states| states.info())
+                .collect(),
+        })
+    }
+
+    /// ステージのシステムを解決済みの順に実行します。
+    ///
+    /// システムが追加されてから最初の実行では、`before` / `after` と優先度から実行順を
+    /// 解決します。順序を決められない場合はその理由で panic します。
+    /// 実行条件（`run_if` と `add_label_condition`）が成り立たないシステムは飛ばします。
+    /// 飛ばしたシステムの変更検出の基準は、次に実行されるまで進みません。
+    /// `Executor::MultiThreaded` では、借用を宣言したシステムのうち互いに競合せず
+    /// `before` / `after` の制約もない連続したものを並列に実行します。
+    /// 初めて実行するシステムは、実行の前に `System::initialize` を呼びます。
+    /// 各システムの実行前に、そのシステムが前回実行されたティックを `Added` / `Changed` /
+    /// `World::removed` の基準として設定し、実行後にワールドの変更ティックを進めます。
+    /// ステージの最後に `DiContainer` の `ecs::Commands` を適用します（なければ作成します）。
+    /// オブザーバーの通知はステージの開始時、各システムの後、コマンドの適用後に送ります。
+    pub fn run_stage(&mut self, stage: Stage, di: &mut DiContainer, world: &mut ecs::World) {
+        let settings = self.settings;
+        self.stage_mut(stage).run(&stage, settings, di, world);
+    }
+
+    /// 組み込みのステージ `anchor` と、その前後に追加したステージを順に実行します。
+    /// `App` の各位相はこれでステージを実行します。
+    pub fn run_anchored(&mut self, anchor: Stage, di: &mut DiContainer, world: &mut ecs::World) {
+        let settings = self.settings;
+        for slot in self.stages.iter_mut().filter(|slot| slot.anchor == anchor) {
+            slot.systems.run(&slot.stage, settings, di, world);
+        }
     }
 }
 
@@ -139,6 +998,77 @@ impl Default for Schedule {
     }
 }
 
+/// `ScheduleCommands` に溜める要求。
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ScheduleCommand {
+    SetEnabled(SystemTarget<'static>, bool),
+    Remove(SystemTarget<'static>),
+}
+
+impl ScheduleCommand {
+    pub(crate) fn apply(&self, systems: &mut StageSystems) {
+        match *self {
+            ScheduleCommand::SetEnabled(target, enabled) => {
+                systems.set_enabled(target, enabled);
+            }
+            ScheduleCommand::Remove(target) => {
+                systems.remove(target);
+            }
+        }
+    }
+}
+
+/// システムの中からシステムの有効/無効の切り替えや削除を要求するためのリソース。
+///
+/// `App` が `DiContainer` に登録し、各位相（`update_logic` など）の終わりに適用します。
+/// 実行中のステージには影響しません。
+#[derive(Debug, Default)]
+pub struct ScheduleCommands {
+    pending: Vec<ScheduleCommand>,
+}
+
+impl ScheduleCommands {
+    pub fn enable_system(&mut self, handle: SystemHandle) {
+        self.set_enabled(SystemTarget::Handle(handle), true);
+    }
+
+    pub fn disable_system(&mut self, handle: SystemHandle) {
+        self.set_enabled(SystemTarget::Handle(handle), false);
+    }
+
+    pub fn remove_system(&mut self, handle: SystemHandle) {
+        self.pending
+            .push(ScheduleCommand::Remove(SystemTarget::Handle(handle)));
+    }
+
+    /// プラグイン `P` が登録したシステムをまとめて有効にします。
+    pub fn enable_plugin<P: Plugin>(&mut self) {
+        self.set_enabled(SystemTarget::Plugin(std::any::type_name::<P>()), true);
+    }
+
+    /// プラグイン `P` が登録したシステムをまとめて無効にします。
+    pub fn disable_plugin<P: Plugin>(&mut self) {
+        self.set_enabled(SystemTarget::Plugin(std::any::type_name::<P>()), false);
+    }
+
+    /// プラグイン `P` が登録したシステムをまとめて取り除きます。
+    pub fn remove_plugin<P: Plugin>(&mut self) {
+        self.pending
+            .push(ScheduleCommand::Remove(SystemTarget::Plugin(
+                std::any::type_name::<P>(),
+            )));
+    }
+
+    fn set_enabled(&mut self, target: SystemTarget<'static>, enabled: bool) {
+        self.pending
+            .push(ScheduleCommand::SetEnabled(target, enabled));
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<ScheduleCommand> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
 // Generic flush system for Events<T>.
 // Register with: app.add_system(Stage::LateUpdate, Priority::Normal, crate::core::schedule::flush_events::<YourEvent>);
 pub fn flush_events<T: 'static + Send + Sync>(di: &mut DiContainer, _world: &mut ecs::World) {
@@ -0,0 +1,198 @@
+//! 解決済みのスケジュールの実行順を調べるための情報と、その書き出し。
+//!
+//! `Schedule::graph` / `App::schedule_graph` で取得し、`to_text` で一覧を、`to_dot` で
+//! Graphviz の DOT を書き出します。テストでは `assert_order` で実行順を確かめられます。
+//! 状態のスケジュール（`OnEnter` / `OnExit` / `OnUpdate`）も `state_schedules` に含め、
+//! ステージの後に書き出します。
+
+use crate::core::schedule::Stage;
+use crate::core::system::SystemLabel;
+use std::fmt::{self, Write};
+
+/// 登録されたシステム 1 つの情報。
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemInfo {
+    pub name: String,
+    pub priority: usize,
+    /// システムを登録したプラグインの型名。`App::add_plugin` の外で登録した場合は `None`。
+    pub plugin: Option<&'static str>,
+    /// `false` なら `Schedule::disable_system` などで無効にされています。
+    pub enabled: bool,
+    pub labels: Vec<SystemLabel>,
+}
+
+impl SystemInfo {
+    /// `name` がこのシステムの名前か、パスを除いた名前と一致すれば `true`。
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name
+            || self
+                .name
+                .strip_suffix(name)
+                .is_some_and(|path| path.ends_with("::"))
+    }
+}
+
+/// 1 つのステージのシステムを解決済みの実行順に並べたもの。
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageInfo {
+    pub stage: Stage,
+    pub systems: Vec<SystemInfo>,
+    /// `before` / `after` の制約。`(a, b)` は `systems[a]` を `systems[b]` より先に実行します。
+    pub constraints: Vec<(usize, usize)>,
+}
+
+/// 状態のスケジュール 1 つのシステムを解決済みの実行順に並べたもの。
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateScheduleInfo {
+    /// `OnEnter(Menu)` のようなスケジュールの名前。
+    pub schedule: String,
+    pub systems: Vec<SystemInfo>,
+    /// `before` / `after` の制約。`(a, b)` は `systems[a]` を `systems[b]` より先に実行します。
+    pub constraints: Vec<(usize, usize)>,
+}
+
+/// 全ステージの解決済みの実行順。ステージも実行順に並びます。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleGraph {
+    pub stages: Vec<StageInfo>,
+    /// 状態のスケジュール。状態の型ごとに `OnEnter`、`OnExit`、`OnUpdate` の順で、同じ種類の中は
+    /// 名前順に並びます。
+    pub state_schedules: Vec<StateScheduleInfo>,
+}
+
+impl ScheduleGraph {
+    pub fn stage(&self, stage: Stage) -> Option<&StageInfo> {
+        self.stages.iter().find(|info| info.stage == stage)
+    }
+
+    /// `OnEnter(Menu)` のような名前の状態のスケジュール。
+    pub fn state_schedule(&self, schedule: &str) -> Option<&StateScheduleInfo> {
+        self.state_schedules
+            .iter()
+            .find(|info| info.schedule == schedule)
+    }
+
+    /// ステージと状態のスケジュールを書き出す順に、名前、システム、制約の組で返します。
+    fn sections(&self) -> impl Iterator<Item = (String, &[SystemInfo], &[(usize, usize)])> {
+        let stages = self.stages.iter().map(|info| {
+            let name = info.stage.to_string();
+            (name, &info.systems[..], &info.constraints[..])
+        });
+        let states = self.state_schedules.iter().map(|info| {
+            let name = info.schedule.clone();
+            (name, &info.systems[..], &info.constraints[..])
+        });
+        stages.chain(states)
+    }
+
+    /// ステージごとにシステムを実行順に並べた一覧。
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// Graphviz の DOT。ステージごとにまとめ、実行順を点線、制約を実線で結びます。
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph schedule {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (stage_index, (name, systems, constraints)) in self.sections().enumerate() {
+            let node = |index: usize| format!("s{stage_index}_{index}");
+            let _ = writeln!(dot, "    subgraph cluster_{stage_index} {{");
+            let _ = writeln!(dot, "        label={};", quote(&name));
+            for (index, system) in systems.iter().enumerate() {
+                let mut label = format!("{}\npriority {}", system.name, system.priority);
+                if let Some(plugin) = system.plugin {
+                    let _ = write!(label, "\n{plugin}");
+                }
+                let _ = writeln!(dot, "        {} [label={}];", node(index), quote(&label));
+            }
+            let _ = writeln!(dot, "    }}");
+            for index in 1..systems.len() {
+                let _ = writeln!(
+                    dot,
+                    "    {} -> {} [style=dotted];",
+                    node(index - 1),
+                    node(index)
+                );
+            }
+            for &(from, to) in constraints {
+                let _ = writeln!(dot, "    {} -> {};", node(from), node(to));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// `stage` で `names` のシステムがこの順に実行されることを確かめます。
+    ///
+    /// 名前はパスを除いたもの（`sys_a`）でも完全なもの（`my_crate::sys_a`）でも構いません。
+    /// 見つからない場合や順序が違う場合は、ステージの一覧を添えて panic します。
+    #[track_caller]
+    pub fn assert_order(&self, stage: Stage, names: &[&str]) {
+        let Some(info) = self.stage(stage) else {
+            panic!("stage {stage:?} is not in the schedule\n{self}");
+        };
+        self.assert_systems_order(&format!("stage {stage:?}"), &info.systems, names);
+    }
+
+    /// 状態のスケジュール（`OnEnter(Menu)` など）で `names` のシステムがこの順に実行されることを
+    /// 確かめます。名前の扱いと panic は `assert_order` と同じです。
+    #[track_caller]
+    pub fn assert_state_order(&self, schedule: &str, names: &[&str]) {
+        let Some(info) = self.state_schedule(schedule) else {
+            panic!("state schedule {schedule} is not in the schedule\n{self}");
+        };
+        self.assert_systems_order(&format!("state schedule {schedule}"), &info.systems, names);
+    }
+
+    #[track_caller]
+    fn assert_systems_order(&self, section: &str, systems: &[SystemInfo], names: &[&str]) {
+        let mut previous: Option<(usize, &str)> = None;
+        for &name in names {
+            let Some(position) = systems.iter().position(|system| system.matches(name)) else {
+                panic!("system {name} is not in {section}\n{self}");
+            };
+            if let Some((before, before_name)) = previous {
+                if position <= before {
+                    panic!("expected {before_name} to run before {name} in {section}\n{self}");
+                }
+            }
+            previous = Some((position, name));
+        }
+    }
+}
+
+impl fmt::Display for ScheduleGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, systems, _) in self.sections() {
+            writeln!(f, "{name}:")?;
+            for (index, system) in systems.iter().enumerate() {
+                write!(
+                    f,
+                    "  {}. {} (priority {}",
+                    index + 1,
+                    system.name,
+                    system.priority
+                )?;
+                if let Some(plugin) = system.plugin {
+                    write!(f, ", plugin {plugin}")?;
+                }
+                if !system.labels.is_empty() {
+                    let labels: Vec<_> = system.labels.iter().map(SystemLabel::as_str).collect();
+                    write!(f, ", labels {}", labels.join(", "))?;
+                }
+                if !system.enabled {
+                    write!(f, ", disabled")?;
+                }
+                writeln!(f, ")")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
@@ -0,0 +1,368 @@
+//! アプリケーションの状態（メニュー、ロード画面、ゲームプレイなど）の切り替え。
+//!
+//! `App::add_state` で `State<S>` リソースを登録し、`OnEnter(S)` / `OnExit(S)` /
+//! `OnUpdate(S)` のスケジュールにシステムを追加します。システムから `State::set` で
+//! 要求した切り替えは、フレームの終わり（`LateUpdate` の後）に適用されます。
+//!
+//! ```ignore
+//! #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+//! enum GameState { Menu, Playing }
+//!
+//! app.add_state(GameState::Menu)
+//!     .add_state_system(OnEnter(GameState::Playing), Priority::Normal, spawn_level)
+//!     .add_state_system(OnUpdate(GameState::Playing), Priority::Normal, movement)
+//!     .add_state_system(OnExit(GameState::Playing), Priority::Normal, despawn_level);
+//! ```
+
+use crate::core::condition::Condition;
+use crate::core::schedule::{
+    ScheduleError, ScheduleSettings, StageSystems, StateScheduleInfo, SystemHandle,
+};
+use crate::core::system::System;
+use crate::core::{ecs, DiContainer};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+
+/// 状態として使える型。`Debug + Clone + Eq + Hash` な enum などが自動で実装します。
+pub trait States: Debug + Clone + Eq + Hash + Send + Sync + 'static {}
+
+impl<T: Debug + Clone + Eq + Hash + Send + Sync + 'static> States for T {}
+
+/// 現在の状態と、次のフレームの間に切り替える状態。
+#[derive(Debug, Clone)]
+pub struct State<S: States> {
+    current: S,
+    next: Option<S>,
+}
+
+impl<S: States> State<S> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            next: None,
+        }
+    }
+
+    pub fn get(&self) -> &S {
+        &self.current
+    }
+
+    /// `next` への切り替えを要求します。フレーム内で複数回呼んだ場合は最後の要求が使われ、
+    /// 現在と同じ状態への切り替えは無視されます。
+    pub fn set(&mut self, next: S) {
+        self.next = Some(next);
+    }
+
+    /// 要求されていて、まだ適用されていない切り替え先。
+    pub fn pending(&self) -> Option<&S> {
+        self.next.as_ref()
+    }
+}
+
+/// 状態に入ったときに 1 回実行するスケジュール。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnEnter<S>(pub S);
+
+/// 状態から出るときに 1 回実行するスケジュール。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnExit<S>(pub S);
+
+/// 状態にいる間、毎フレーム `Stage::Update` の後に実行するスケジュール。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OnUpdate<S>(pub S);
+
+/// `OnEnter(Menu)` のように表示します。
+impl<S: Debug> Display for OnEnter<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<S: Debug> Display for OnExit<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<S: Debug> Display for OnUpdate<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// 状態ごとのスケジュールを指す型（`OnEnter` / `OnExit` / `OnUpdate`）。
+pub trait StateSchedule: Debug {
+    type State: States;
+
+    #[doc(hidden)]
+    fn into_parts(self) -> (StateHook, Self::State);
+}
+
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateHook {
+    Enter,
+    Exit,
+    Update,
+}
+
+impl<S: States> StateSchedule for OnEnter<S> {
+    type State = S;
+
+    fn into_parts(self) -> (StateHook, S) {
+        (StateHook::Enter, self.0)
+    }
+}
+
+impl<S: States> StateSchedule for OnExit<S> {
+    type State = S;
+
+    fn into_parts(self) -> (StateHook, S) {
+        (StateHook::Exit, self.0)
+    }
+}
+
+impl<S: States> StateSchedule for OnUpdate<S> {
+    type State = S;
+
+    fn into_parts(self) -> (StateHook, S) {
+        (StateHook::Update, self.0)
+    }
+}
+
+/// `S` の各状態のスケジュール。
+pub(crate) struct StateSchedules<S: States> {
+    on_enter: HashMap<S, StageSystems>,
+    on_exit: HashMap<S, StageSystems>,
+    on_update: HashMap<S, StageSystems>,
+    entered: bool,
+}
+
+impl<S: States> StateSchedules<S> {
+    pub(crate) fn new() -> Self {
+        Self {
+            on_enter: HashMap::new(),
+            on_exit: HashMap::new(),
+            on_update: HashMap::new(),
+            entered: false,
+        }
+    }
+
+    pub(crate) fn add_system(
+        &mut self,
+        schedule: impl StateSchedule<State = S>,
+        priority: usize,
+        system: Box<dyn System>,
+        plugin: Option<&'static str>,
+    ) -> SystemHandle {
+        let (hook, state) = schedule.into_parts();
+        let schedules = match hook {
+            StateHook::Enter => &mut self.on_enter,
+            StateHook::Exit => &mut self.on_exit,
+            StateHook::Update => &mut self.on_update,
+        };
+        schedules
+            .entry(state)
+            .or_default()
+            .add(priority, system, plugin)
+    }
+
+    fn run(
+        systems: &mut HashMap<S, StageSystems>,
+        schedule: &dyn Display,
+        state: &S,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    ) {
+        if let Some(systems) = systems.get_mut(state) {
+            systems.run(schedule, settings, di, world);
+        }
+    }
+
+    /// 1 種類のスケジュールの情報を名前順に返します。
+    fn schedules_info(
+        systems: &HashMap<S, StageSystems>,
+        name: impl Fn(&S) -> String,
+    ) -> Vec<StateScheduleInfo> {
+        let mut infos: Vec<_> = systems
+            .iter()
+            .map(|(state, systems)| {
+                let (systems, constraints) = systems.systems_info();
+                StateScheduleInfo {
+                    schedule: name(state),
+                    systems,
+                    constraints,
+                }
+            })
+            .collect();
+        infos.sort_by(|a, b| a.schedule.cmp(&b.schedule));
+        infos
+    }
+}
+
+/// `Schedule` が状態の型を区別せずに扱うためのトレイト。
+pub(crate) trait StateDriver: Send {
+    /// 最初の状態の `OnEnter` を 1 回だけ実行します。`startup` を呼ばずにフレームを回した場合も、
+    /// 最初の `apply_transition` / `run_update` がこれを先に呼びます。
+    fn enter_initial(
+        &mut self,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    );
+
+    /// 要求された切り替えを `OnExit`、状態の更新、`OnEnter` の順に適用します。
+    fn apply_transition(
+        &mut self,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    );
+
+    /// 現在の状態の `OnUpdate` を実行します。
+    fn run_update(
+        &mut self,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    );
+
+    fn build(&mut self, deny_ambiguities: bool) -> Result<(), ScheduleError>;
+
+    /// 解決済みの実行順。`OnEnter`、`OnExit`、`OnUpdate` の順に並べます。先に `build` しておく
+    /// 必要があります。
+    fn info(&self) -> Vec<StateScheduleInfo>;
+
+    /// すべての状態のスケジュールのシステム。
+    fn stage_systems(&self) -> Box<dyn Iterator<Item = &StageSystems> + '_>;
+
+    fn stage_systems_mut(&mut self) -> Box<dyn Iterator<Item = &mut StageSystems> + '_>;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<S: States> StateDriver for StateSchedules<S> {
+    fn enter_initial(
+        &mut self,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    ) {
+        if self.entered {
+            return;
+        }
+        self.entered = true;
+        let Some(current) = di.get::<State<S>>().map(|state| state.current.clone()) else {
+            return;
+        };
+        let schedule = OnEnter(current.clone());
+        Self::run(&mut self.on_enter, &schedule, &current, settings, di, world);
+    }
+
+    fn apply_transition(
+        &mut self,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    ) {
+        self.enter_initial(settings, di, world);
+        let Some(state) = di.get_mut::<State<S>>() else {
+            return;
+        };
+        let Some(next) = state.next.take() else {
+            return;
+        };
+        if next == state.current {
+            return;
+        }
+        let previous = state.current.clone();
+
+        // `OnExit` のシステムからはまだ前の状態が見えます。
+        let schedule = OnExit(previous.clone());
+        Self::run(&mut self.on_exit, &schedule, &previous, settings, di, world);
+        if let Some(state) = di.get_mut::<State<S>>() {
+            state.current = next.clone();
+        }
+        let schedule = OnEnter(next.clone());
+        Self::run(&mut self.on_enter, &schedule, &next, settings, di, world);
+    }
+
+    fn run_update(
+        &mut self,
+        settings: ScheduleSettings,
+        di: &mut DiContainer,
+        world: &mut ecs::World,
+    ) {
+        self.enter_initial(settings, di, world);
+        let Some(current) = di.get::<State<S>>().map(|state| state.current.clone()) else {
+            return;
+        };
+        let schedule = OnUpdate(current.clone());
+        Self::run(
+            &mut self.on_update,
+            &schedule,
+            &current,
+            settings,
+            di,
+            world,
+        );
+    }
+
+    fn build(&mut self, deny_ambiguities: bool) -> Result<(), ScheduleError> {
+        for (state, systems) in &mut self.on_enter {
+            systems.build(&OnEnter(state.clone()), deny_ambiguities)?;
+        }
+        for (state, systems) in &mut self.on_exit {
+            systems.build(&OnExit(state.clone()), deny_ambiguities)?;
+        }
+        for (state, systems) in &mut self.on_update {
+            systems.build(&OnUpdate(state.clone()), deny_ambiguities)?;
+        }
+        Ok(())
+    }
+
+    fn info(&self) -> Vec<StateScheduleInfo> {
+        let mut infos =
+            Self::schedules_info(&self.on_enter, |state| OnEnter(state.clone()).to_string());
+        infos.extend(Self::schedules_info(&self.on_exit, |state| {
+            OnExit(state.clone()).to_string()
+        }));
+        infos.extend(Self::schedules_info(&self.on_update, |state| {
+            OnUpdate(state.clone()).to_string()
+        }));
+        infos
+    }
+
+    fn stage_systems(&self) -> Box<dyn Iterator<Item = &StageSystems> + '_> {
+        Box::new(
+            self.on_enter
+                .values()
+                .chain(self.on_exit.values())
+                .chain(self.on_update.values()),
+        )
+    }
+
+    fn stage_systems_mut(&mut self) -> Box<dyn Iterator<Item = &mut StageSystems> + '_> {
+        Box::new(
+            self.on_enter
+                .values_mut()
+                .chain(self.on_exit.values_mut())
+                .chain(self.on_update.values_mut()),
+        )
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// 現在の状態が `state` のときに実行します。
+pub fn in_state<S: States>(state: S) -> impl Condition {
+    move |di: &DiContainer| {
+        di.get::<State<S>>()
+            .is_some_and(|current| current.current == state)
+    }
+}
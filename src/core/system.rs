@@ -0,0 +1,355 @@
+//! スケジュールに登録するシステム。
+//!
+//! `fn(&mut DiContainer, &mut ecs::World)` の関数に加えて、設定や状態を捕捉した `FnMut`
+//! クロージャ（`Box<dyn FnMut ...>` を含む）や、`System` を実装した型を登録できます。
+//!
+//! ```ignore
+//! let mut frames = 0;
+//! app.add_system(Stage::Update, Priority::Normal, move |_di: &mut DiContainer, _world: &mut ecs::World| {
+//!     frames += 1;
+//! });
+//! ```
+//!
+//! 同じステージ内の順序は `label` / `before` / `after` で指定できます。
+//!
+//! ```ignore
+//! app.add_system(Stage::Update, Priority::Normal, movement.label("movement"));
+//! app.add_system(Stage::Update, Priority::Normal, collision.after("movement"));
+//! ```
+//!
+//! `run_if` で実行条件を付けられます（`core::condition` を参照）。
+
+use crate::core::condition::Condition;
+use crate::core::system_param::{SystemAccess, SystemError};
+use crate::core::{ecs, DiContainer};
+use std::borrow::Cow;
+use std::fmt;
+
+/// 実行時に選んだクロージャなどを登録するための箱詰めのシステム関数。
+pub type BoxedSystemFn = Box<dyn FnMut(&mut DiContainer, &mut ecs::World) + Send>;
+
+/// スケジュールが実行する処理の単位。
+///
+/// `initialize` は最初の `run` の直前に 1 回だけ呼ばれます。
+pub trait System: Send + 'static {
+    /// 診断やエラーメッセージに使う名前。
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(std::any::type_name::<Self>())
+    }
+
+    /// 読み書きするリソースとコンポーネント。`None` は `DiContainer` と `World` の全体を
+    /// 借用するものとして扱います。
+    fn access(&self) -> Option<&SystemAccess> {
+        None
+    }
+
+    /// ラベルと、同じステージ内で前後に実行したいラベル。`None` は指定なしです。
+    fn order(&self) -> Option<&SystemOrder> {
+        None
+    }
+
+    /// 登録時に呼ばれ、実行に必要なものが揃っているかを調べます。
+    fn validate(&self, _di: &DiContainer) -> Result<(), SystemError> {
+        Ok(())
+    }
+
+    /// 実行の直前に呼ばれ、`false` を返すとこのフレームの実行を飛ばします。
+    fn should_run(&mut self, _di: &DiContainer) -> bool {
+        true
+    }
+
+    /// 最初の実行の前に 1 回だけ呼ばれます。リソースの用意などに使います。
+    fn initialize(&mut self, _di: &mut DiContainer, _world: &mut ecs::World) {}
+
+    fn run(&mut self, di: &mut DiContainer, world: &mut ecs::World);
+
+    /// 引数を取り出し、別のスレッドで実行できる処理を返します。`access` を宣言した
+    /// システムだけが実装します。`None` を返したシステムは、同じバッチの並列処理が
+    /// 終わった後に単独で `run` されます。
+    ///
+    /// # Safety
+    /// `di` と `world` は有効で、返した処理の実行中に他のスレッドが `access` と競合する
+    /// 借用をしていない必要があります。
+    #[doc(hidden)]
+    unsafe fn prepare(
+        &mut self,
+        _di: *mut DiContainer,
+        _world: *mut ecs::World,
+    ) -> Option<PreparedRun<'_>> {
+        None
+    }
+}
+
+/// 引数を取り出し済みで、別のスレッドで実行できるシステムの処理。
+#[doc(hidden)]
+pub struct PreparedRun<'a>(Box<dyn FnOnce() + 'a>);
+
+// SAFETY: 取り出した借用が同時に実行する他のシステムと競合しないことは、
+// スケジュールが `SystemAccess` で検査してから実行します。
+unsafe impl Send for PreparedRun<'_> {}
+
+impl<'a> PreparedRun<'a> {
+    /// # Safety
+    /// `run` が別のスレッドで呼ばれても問題ない処理である必要があります。
+    pub unsafe fn new(run: impl FnOnce() + 'a) -> Self {
+        Self(Box::new(run))
+    }
+
+    pub fn run(self) {
+        (self.0)()
+    }
+}
+
+impl<S: System + ?Sized> System for Box<S> {
+    fn name(&self) -> Cow<'static, str> {
+        (**self).name()
+    }
+
+    fn access(&self) -> Option<&SystemAccess> {
+        (**self).access()
+    }
+
+    fn order(&self) -> Option<&SystemOrder> {
+        (**self).order()
+    }
+
+    fn validate(&self, di: &DiContainer) -> Result<(), SystemError> {
+        (**self).validate(di)
+    }
+
+    fn should_run(&mut self, di: &DiContainer) -> bool {
+        (**self).should_run(di)
+    }
+
+    fn initialize(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        (**self).initialize(di, world);
+    }
+
+    fn run(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        (**self).run(di, world);
+    }
+
+    unsafe fn prepare(
+        &mut self,
+        di: *mut DiContainer,
+        world: *mut ecs::World,
+    ) -> Option<PreparedRun<'_>> {
+        (**self).prepare(di, world)
+    }
+}
+
+/// `System` に変換できる型。`Marker` は実装が重ならないようにするための型です。
+pub trait IntoSystem<Marker> {
+    type System: System;
+
+    fn into_system(self) -> Self::System;
+
+    /// システムにラベルを付けます。同じラベルを複数のシステムに付けることもできます。
+    fn label(self, label: impl Into<SystemLabel>) -> ConfiguredSystem<Self::System>
+    where
+        Self: Sized,
+    {
+        ConfiguredSystem::new(self.into_system()).label(label)
+    }
+
+    /// `label` の付いたシステムより先に実行します。
+    fn before(self, label: impl Into<SystemLabel>) -> ConfiguredSystem<Self::System>
+    where
+        Self: Sized,
+    {
+        ConfiguredSystem::new(self.into_system()).before(label)
+    }
+
+    /// `label` の付いたシステムより後に実行します。
+    fn after(self, label: impl Into<SystemLabel>) -> ConfiguredSystem<Self::System>
+    where
+        Self: Sized,
+    {
+        ConfiguredSystem::new(self.into_system()).after(label)
+    }
+
+    /// `condition` が成り立つときだけ実行します。
+    fn run_if(self, condition: impl Condition) -> ConfiguredSystem<Self::System>
+    where
+        Self: Sized,
+    {
+        ConfiguredSystem::new(self.into_system()).run_if(condition)
+    }
+}
+
+#[doc(hidden)]
+pub struct IsSystem;
+
+impl<S: System> IntoSystem<IsSystem> for S {
+    type System = S;
+
+    fn into_system(self) -> S {
+        self
+    }
+}
+
+#[doc(hidden)]
+pub struct IsFunctionSystem;
+
+impl<F> IntoSystem<IsFunctionSystem> for F
+where
+    F: FnMut(&mut DiContainer, &mut ecs::World) + Send + 'static,
+{
+    type System = FunctionSystem<F>;
+
+    fn into_system(self) -> FunctionSystem<F> {
+        FunctionSystem {
+            function: self,
+            name: std::any::type_name::<F>(),
+        }
+    }
+}
+
+/// `fn` やクロージャをそのまま実行するシステム。
+pub struct FunctionSystem<F> {
+    function: F,
+    name: &'static str,
+}
+
+impl<F> System for FunctionSystem<F>
+where
+    F: FnMut(&mut DiContainer, &mut ecs::World) + Send + 'static,
+{
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(self.name)
+    }
+
+    fn run(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        (self.function)(di, world);
+    }
+}
+
+/// システムの実行順を指定するための名前。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SystemLabel(Cow<'static, str>);
+
+impl SystemLabel {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&'static str> for SystemLabel {
+    fn from(label: &'static str) -> Self {
+        Self(Cow::Borrowed(label))
+    }
+}
+
+impl From<String> for SystemLabel {
+    fn from(label: String) -> Self {
+        Self(Cow::Owned(label))
+    }
+}
+
+impl fmt::Display for SystemLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// システムのラベルと、同じステージ内で前後に実行したいラベル。
+#[derive(Debug, Default, Clone)]
+pub struct SystemOrder {
+    labels: Vec<SystemLabel>,
+    before: Vec<SystemLabel>,
+    after: Vec<SystemLabel>,
+}
+
+impl SystemOrder {
+    pub fn labels(&self) -> &[SystemLabel] {
+        &self.labels
+    }
+
+    pub fn before(&self) -> &[SystemLabel] {
+        &self.before
+    }
+
+    pub fn after(&self) -> &[SystemLabel] {
+        &self.after
+    }
+}
+
+/// ラベル・順序・実行条件を付けたシステム。`IntoSystem::label` や `run_if` で作ります。
+pub struct ConfiguredSystem<S> {
+    system: S,
+    order: SystemOrder,
+    conditions: Vec<Box<dyn Condition>>,
+}
+
+impl<S: System> ConfiguredSystem<S> {
+    fn new(system: S) -> Self {
+        let order = system.order().cloned().unwrap_or_default();
+        Self {
+            system,
+            order,
+            conditions: Vec::new(),
+        }
+    }
+
+    pub fn label(mut self, label: impl Into<SystemLabel>) -> Self {
+        self.order.labels.push(label.into());
+        self
+    }
+
+    pub fn before(mut self, label: impl Into<SystemLabel>) -> Self {
+        self.order.before.push(label.into());
+        self
+    }
+
+    pub fn after(mut self, label: impl Into<SystemLabel>) -> Self {
+        self.order.after.push(label.into());
+        self
+    }
+
+    /// 実行条件を追加します。すべての条件が成り立つときだけ実行します。
+    pub fn run_if(mut self, condition: impl Condition) -> Self {
+        self.conditions.push(Box::new(condition));
+        self
+    }
+}
+
+impl<S: System> System for ConfiguredSystem<S> {
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+
+    fn access(&self) -> Option<&SystemAccess> {
+        self.system.access()
+    }
+
+    fn order(&self) -> Option<&SystemOrder> {
+        Some(&self.order)
+    }
+
+    fn should_run(&mut self, di: &DiContainer) -> bool {
+        self.conditions
+            .iter_mut()
+            .all(|condition| condition.evaluate(di))
+            && self.system.should_run(di)
+    }
+
+    fn validate(&self, di: &DiContainer) -> Result<(), SystemError> {
+        self.system.validate(di)
+    }
+
+    fn initialize(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        self.system.initialize(di, world);
+    }
+
+    fn run(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+        self.system.run(di, world);
+    }
+
+    unsafe fn prepare(
+        &mut self,
+        di: *mut DiContainer,
+        world: *mut ecs::World,
+    ) -> Option<PreparedRun<'_>> {
+        self.system.prepare(di, world)
+    }
+}
@@ -0,0 +1,447 @@
+//! 引数の型で必要なものを宣言するシステム。
+//!
+//! `Res<T>` / `ResMut<T>` は `DiContainer` のリソースを、`Query<Q, F>` は `World` のクエリを、
+//! `EventReader<T>` / `EventWriter<T>` は `Events<T>` を借用します。引数はすべて同時に
+//! 借用できるので、複数のリソースを `Vec` に写して受け渡す必要はありません。
+//!
+//! ```ignore
+//! fn movement(time: Res<Time>, mut query: Query<(&mut Transform2D, &Velocity)>) {
+//!     for (_, (transform, velocity)) in query.iter() { /* ... */ }
+//! }
+//! app.add_system(Stage::Update, Priority::Normal, movement);
+//! ```
+//!
+//! `App::add_system` は登録時に、足りないリソースと引数同士の借用の競合を型名付きで報告します。
+
+use crate::core::events::Events;
+use crate::core::system::{IntoSystem, PreparedRun, System};
+use crate::core::{ecs, DiContainer};
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum SystemError {
+    #[error("system {system} requires resource {resource}, which is not in the DiContainer")]
+    MissingResource {
+        system: String,
+        resource: &'static str,
+    },
+    #[error("system {system} borrows {name} mutably more than once or both mutably and immutably")]
+    Conflict { system: String, name: &'static str },
+    #[error("system {system} borrows {component} mutably, but it can only be replaced with World::insert")]
+    Immutable {
+        system: String,
+        component: &'static str,
+    },
+}
+
+/// システムが読み書きするリソースとコンポーネント。
+#[derive(Debug, Default, Clone)]
+pub struct SystemAccess {
+    resource_reads: Vec<(TypeId, &'static str)>,
+    resource_writes: Vec<(TypeId, &'static str)>,
+    required: Vec<(TypeId, &'static str)>,
+    components: ecs::Access,
+    conflict: Option<&'static str>,
+}
+
+impl SystemAccess {
+    pub fn read_resource<T: 'static>(&mut self) {
+        let id = TypeId::of::<T>();
+        if self.resource_writes.iter().any(|(w, _)| *w == id) {
+            self.conflict.get_or_insert(std::any::type_name::<T>());
+        }
+        self.resource_reads.push((id, std::any::type_name::<T>()));
+    }
+
+    pub fn write_resource<T: 'static>(&mut self) {
+        let id = TypeId::of::<T>();
+        if self
+            .resource_reads
+            .iter()
+            .chain(self.resource_writes.iter())
+            .any(|(other, _)| *other == id)
+        {
+            self.conflict.get_or_insert(std::any::type_name::<T>());
+        }
+        self.resource_writes.push((id, std::any::type_name::<T>()));
+    }
+
+    /// 実行に `T` のリソースが必要であることを記録します。
+    pub fn require_resource<T: 'static>(&mut self) {
+        self.required
+            .push((TypeId::of::<T>(), std::any::type_name::<T>()));
+    }
+
+    /// クエリのコンポーネントへのアクセス。引数をまたいだ競合もここで検出されます。
+    pub fn components_mut(&mut self) -> &mut ecs::Access {
+        &mut self.components
+    }
+
+    pub fn components(&self) -> &ecs::Access {
+        &self.components
+    }
+
+    /// 競合した最初のリソースまたはコンポーネントの型名。
+    pub fn conflict(&self) -> Option<&'static str> {
+        self.conflict.or(self.components.conflict())
+    }
+
+    /// `other` と同時に実行できない借用があれば、そのリソースまたはコンポーネントの型名を返します。
+    pub fn conflicts_with(&self, other: &SystemAccess) -> Option<&'static str> {
+        ecs::conflicting(
+            &self.resource_reads,
+            &self.resource_writes,
+            &other.resource_reads,
+            &other.resource_writes,
+        )
+        .or_else(|| self.components.conflicts_with(&other.components))
+    }
+
+    /// 実行に必要なリソースの型名のうち、`di` にないもの。
+    pub(crate) fn missing_resources<'a>(
+        &'a self,
+        di: &'a DiContainer,
+    ) -> impl Iterator<Item = &'static str> + 'a {
+        self.required
+            .iter()
+            .filter(|(id, _)| !di.contains_type(*id))
+            .map(|(_, name)| *name)
+    }
+}
+
+/// システムの引数として `DiContainer` / `World` から取り出せる型。
+pub trait SystemParam {
+    type Item<'w>;
+
+    /// この引数が借用するものを `access` に記録します。
+    fn access(access: &mut SystemAccess);
+
+    /// # Safety
+    /// `di` と `world` は有効で、`access` に記録した借用が他の引数の借用と競合しない
+    /// 必要があります。
+    #[doc(hidden)]
+    unsafe fn fetch<'w>(
+        di: *mut DiContainer,
+        world: *mut ecs::World,
+        system: &str,
+    ) -> Self::Item<'w>;
+}
+
+fn missing_resource<T>(system: &str) -> ! {
+    panic!(
+        "system {system} requires resource {}, which was removed from the DiContainer",
+        std::any::type_name::<T>()
+    )
+}
+
+/// `di` から `T` への可変ポインタを取り出します。ない場合はシステム名と型名付きで panic します。
+///
+/// 一時的な `&mut DiContainer` が覆うのはコンテナの表だけで、他の引数が借用している
+/// リソースはそれぞれ別の `Box` に入っているため重なりません。
+///
+/// # Safety
+/// `di` は有効で、この呼び出しの間に他のスレッドが `di` を使っていない必要があります。
+unsafe fn resource_ptr<T: Send + Sync + 'static>(di: *mut DiContainer, system: &str) -> *mut T {
+    match (*di).get_mut::<T>() {
+        Some(resource) => resource,
+        None => missing_resource::<T>(system),
+    }
+}
+
+/// `di` から `T` への共有ポインタを取り出します。同じリソースを読む他の引数と並んで使えるよう、
+/// `&mut DiContainer` を作りません。
+///
+/// # Safety
+/// `di` は有効である必要があります。
+unsafe fn resource_ref<T: Send + Sync + 'static>(di: *const DiContainer, system: &str) -> *const T {
+    match (*di).get::<T>() {
+        Some(resource) => resource,
+        None => missing_resource::<T>(system),
+    }
+}
+
+/// `DiContainer` のリソースへの共有参照。
+pub struct Res<'w, T>(&'w T);
+
+impl<T> Deref for Res<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for Res<'_, T> {
+    type Item<'w> = Res<'w, T>;
+
+    fn access(access: &mut SystemAccess) {
+        access.read_resource::<T>();
+        access.require_resource::<T>();
+    }
+
+    unsafe fn fetch<'w>(di: *mut DiContainer, _world: *mut ecs::World, system: &str) -> Res<'w, T> {
+        Res(&*resource_ref::<T>(di, system))
+    }
+}
+
+/// `DiContainer` のリソースへの可変参照。
+pub struct ResMut<'w, T>(&'w mut T);
+
+impl<T> Deref for ResMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T> DerefMut for ResMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for ResMut<'_, T> {
+    type Item<'w> = ResMut<'w, T>;
+
+    fn access(access: &mut SystemAccess) {
+        access.write_resource::<T>();
+        access.require_resource::<T>();
+    }
+
+    unsafe fn fetch<'w>(
+        di: *mut DiContainer,
+        _world: *mut ecs::World,
+        system: &str,
+    ) -> ResMut<'w, T> {
+        ResMut(&mut *resource_ptr::<T>(di, system))
+    }
+}
+
+/// `World` へのクエリ。`ecs::QueryMut` と同じように使えます。
+pub struct Query<'w, Q: ecs::QueryData, F: ecs::QueryFilter = ()>(ecs::QueryMut<'w, Q, F>);
+
+impl<'w, Q: ecs::QueryData, F: ecs::QueryFilter> Deref for Query<'w, Q, F> {
+    type Target = ecs::QueryMut<'w, Q, F>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Q: ecs::QueryData, F: ecs::QueryFilter> DerefMut for Query<'_, Q, F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<Q: ecs::QueryData + 'static, F: ecs::QueryFilter + 'static> SystemParam for Query<'_, Q, F> {
+    type Item<'w> = Query<'w, Q, F>;
+
+    fn access(access: &mut SystemAccess) {
+        Q::access(access.components_mut());
+        F::access(access.components_mut());
+    }
+
+    unsafe fn fetch<'w>(
+        _di: *mut DiContainer,
+        world: *mut ecs::World,
+        _system: &str,
+    ) -> Query<'w, Q, F> {
+        // 借用の競合は登録時に検査済みです。並列に走る他のシステムのクエリと `&mut World` が
+        // 重ならないよう、共有参照から作ります。
+        Query(
+            (*world)
+                .try_query_filtered_unchecked::<Q, F>()
+                .unwrap_or_else(|err| panic!("{err}")),
+        )
+    }
+}
+
+/// `Events<T>` から今フレームに読めるイベントを読み出します。
+pub struct EventReader<'w, T>(&'w mut Events<T>);
+
+impl<T> EventReader<'_, T> {
+    /// 読めるイベントを消費せずに返します。
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.iter()
+    }
+
+    /// 読めるイベントを取り出します。取り出したイベントは他のシステムからは読めません。
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.0.drain()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventReader<'_, T> {
+    type Item<'w> = EventReader<'w, T>;
+
+    fn access(access: &mut SystemAccess) {
+        access.write_resource::<Events<T>>();
+        access.require_resource::<Events<T>>();
+    }
+
+    unsafe fn fetch<'w>(
+        di: *mut DiContainer,
+        _world: *mut ecs::World,
+        system: &str,
+    ) -> EventReader<'w, T> {
+        EventReader(&mut *resource_ptr::<Events<T>>(di, system))
+    }
+}
+
+/// `Events<T>` にイベントを送ります。
+pub struct EventWriter<'w, T>(&'w mut Events<T>);
+
+impl<T> EventWriter<'_, T> {
+    pub fn send(&mut self, event: T) {
+        self.0.send(event);
+    }
+
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, events: I) {
+        self.0.extend(events);
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventWriter<'_, T> {
+    type Item<'w> = EventWriter<'w, T>;
+
+    fn access(access: &mut SystemAccess) {
+        access.write_resource::<Events<T>>();
+        access.require_resource::<Events<T>>();
+    }
+
+    unsafe fn fetch<'w>(
+        di: *mut DiContainer,
+        _world: *mut ecs::World,
+        system: &str,
+    ) -> EventWriter<'w, T> {
+        EventWriter(&mut *resource_ptr::<Events<T>>(di, system))
+    }
+}
+
+/// 引数の型から作ったシステム。
+pub struct ParamSystem<F, Params> {
+    function: F,
+    name: &'static str,
+    access: SystemAccess,
+    _marker: PhantomData<fn() -> Params>,
+}
+
+impl<F, Params> ParamSystem<F, Params> {
+    /// 登録時の検査。競合と足りないリソースを報告します。
+    fn check(&self, di: &DiContainer) -> Result<(), SystemError> {
+        if let Some(name) = self.access.conflict() {
+            return Err(SystemError::Conflict {
+                system: self.name.to_string(),
+                name,
+            });
+        }
+        if let Some(component) = self.access.components().immutable() {
+            return Err(SystemError::Immutable {
+                system: self.name.to_string(),
+                component,
+            });
+        }
+        match self.access.missing_resources(di).next() {
+            Some(resource) => Err(SystemError::MissingResource {
+                system: self.name.to_string(),
+                resource,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct IsParamSystem;
+
+macro_rules! param_system {
+    ($($param:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<Func, $($param: SystemParam + 'static),*> System for ParamSystem<Func, ($($param,)*)>
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func: FnMut($($param),*) + FnMut($($param::Item<'_>),*),
+        {
+            fn name(&self) -> Cow<'static, str> {
+                Cow::Borrowed(self.name)
+            }
+
+            fn access(&self) -> Option<&SystemAccess> {
+                Some(&self.access)
+            }
+
+            fn validate(&self, di: &DiContainer) -> Result<(), SystemError> {
+                self.check(di)
+            }
+
+            fn initialize(&mut self, di: &mut DiContainer, _world: &mut ecs::World) {
+                if let Err(err) = self.check(di) {
+                    panic!("{err}");
+                }
+            }
+
+            fn run(&mut self, di: &mut DiContainer, world: &mut ecs::World) {
+                // SAFETY: 引数同士の借用が競合しないことは `initialize` で検査済みで、
+                // 借用はこの呼び出しの間だけ有効です。
+                if let Some(run) = unsafe { self.prepare(di, world) } {
+                    run.run();
+                }
+            }
+
+            unsafe fn prepare(
+                &mut self,
+                di: *mut DiContainer,
+                world: *mut ecs::World,
+            ) -> Option<PreparedRun<'_>> {
+                // 高階のクロージャ境界から引数の型を推論させるための関数です。
+                #[allow(clippy::too_many_arguments)]
+                fn call<$($param),*>(mut f: impl FnMut($($param),*), $($param: $param),*) {
+                    f($($param),*)
+                }
+                let ($($param,)*) = ($($param::fetch(di, world, self.name),)*);
+                let function = &mut self.function;
+                Some(PreparedRun::new(move || call(function, $($param),*)))
+            }
+        }
+
+        impl<Func, $($param: SystemParam + 'static),*> IntoSystem<(IsParamSystem, fn($($param),*))>
+            for Func
+        where
+            Func: Send + 'static,
+            for<'a> &'a mut Func: FnMut($($param),*) + FnMut($($param::Item<'_>),*),
+        {
+            type System = ParamSystem<Func, ($($param,)*)>;
+
+            fn into_system(self) -> Self::System {
+                let mut access = SystemAccess::default();
+                $($param::access(&mut access);)*
+                ParamSystem {
+                    function: self,
+                    name: std::any::type_name::<Func>(),
+                    access,
+                    _marker: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+param_system!(P0);
+param_system!(P0, P1);
+param_system!(P0, P1, P2);
+param_system!(P0, P1, P2, P3);
+param_system!(P0, P1, P2, P3, P4);
+param_system!(P0, P1, P2, P3, P4, P5);
+param_system!(P0, P1, P2, P3, P4, P5, P6);
+param_system!(P0, P1, P2, P3, P4, P5, P6, P7);
@@ -13,6 +13,12 @@ impl Time {
     pub fn elapsed_seconds(&self) -> f32 {
         self.elapsed_seconds.as_secs_f32()
     }
+
+    /// `delta` だけ時間を進めます。
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta_seconds = delta;
+        self.elapsed_seconds += delta;
+    }
 }
 
 pub struct TimeState {
@@ -35,8 +41,7 @@ impl TimeState {
         let now = Instant::now();
         let delta = now - self.last_instant;
         self.last_instant = now;
-        self.time.elapsed_seconds += delta;
-        self.time.delta_seconds = delta;
+        self.time.advance(delta);
         self.time
     }
 }
@@ -47,11 +52,26 @@ impl Default for TimeState {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct TimeFixed {
     pub delta_seconds: f32,
+    alpha: f32,
 }
 impl TimeFixed {
     pub fn new(dt: f32) -> Self {
-        Self { delta_seconds: dt }
+        Self {
+            delta_seconds: dt,
+            alpha: 0.0,
+        }
+    }
+
+    /// 直前の `FixedUpdate` から次の `FixedUpdate` までの進み具合（0 以上 1 未満）。
+    /// `App::render` で設定され、描画で前後の物理の状態を補間するのに使います。
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub(crate) fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
     }
 }
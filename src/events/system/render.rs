@@ -1,9 +1,9 @@
-use crate::components::{Sprite, Transform2D};
+use crate::components::{GlobalTransform2D, Sprite};
 
 pub enum RenderCommand {
     DrawSprite {
         sprite: Sprite,
-        transform: Transform2D,
+        transform: GlobalTransform2D,
     },
 }
 
@@ -3,6 +3,8 @@ pub mod platform;
 pub use platform::WinitBackend;
 pub mod events;
 pub mod plugin;
-pub use plugin::InputPlugin;
+pub use plugin::{InputPlugin, TransformPlugin};
 pub mod components;
-pub use components::{Camera2D, Sprite, Transform2D};
+pub mod scene;
+pub use components::{Camera2D, Children, GlobalTransform2D, Parent, Sprite, Transform2D};
+pub use scene::Scene;
@@ -1,8 +1,9 @@
 use crate::core::events::Events;
 use crate::core::input::{EngineKey, EngineMouseButton};
+use crate::core::runner::Runner;
 use crate::core::App;
 use crate::events::{CursorMovedEvent, KeyboardInputEvent, MouseInputEvent};
-use std::time::{Duration, Instant};
+use std::time::Instant;
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::platform::run_return::EventLoopExtRunReturn;
 use winit::window::WindowBuilder;
@@ -16,7 +17,6 @@ pub enum WinitError {
 pub struct WinitBackend {
     event_loop: EventLoop<()>,
     window: winit::window::Window,
-    accumulator: Duration,
     last_instant: Instant,
 }
 
@@ -35,50 +35,23 @@ impl WinitBackend {
         Ok(WinitBackend {
             event_loop,
             window,
-            accumulator: Duration::ZERO,
             last_instant: Instant::now(),
         })
     }
 
+    /// イベントを処理し、`App::run_frame` で 1 フレームを実行します。
     pub fn poll_once(&mut self, app: &mut App) -> PollResult {
-        let fixed_dt =
-            if let Some(tf) = app.get_di_container().get::<crate::core::time::TimeFixed>() {
-                Duration::from_secs_f32(tf.delta_seconds)
-            } else {
-                Duration::from_secs_f32(1.0 / 60.0)
-            };
-
         let mut should_exit = false;
-        let mut accumulator = self.accumulator;
         let mut last_instant = self.last_instant;
 
         self.event_loop.run_return(|event, _, control_flow| {
             *control_flow = ControlFlow::Poll; // wait for next events by default
             match event {
                 winit::event::Event::MainEventsCleared => {
-                    app.process_input();
-
                     let now = Instant::now();
-                    let mut frame_time = now - last_instant;
+                    let frame_time = now - last_instant;
                     last_instant = now;
-
-                    if frame_time > Duration::from_secs_f32(0.25) {
-                        frame_time = Duration::from_secs_f32(0.25); // safety clamp
-                    }
-                    accumulator += frame_time;
-                    app.tick_timer();
-                    //可変Update
-                    app.update_logic();
-                    //固定Update
-                    while accumulator >= fixed_dt {
-                        app.fixed_update();
-                        accumulator -= fixed_dt;
-                    }
-                    //Render
-                    let alpha = accumulator.as_secs_f32() / fixed_dt.as_secs_f32();
-                    app.render(alpha);
-
-                    app.late_update();
+                    app.run_frame(frame_time);
 
                     // ウィンドウの再描画要求
                     self.window.request_redraw();
@@ -135,7 +108,6 @@ impl WinitBackend {
         });
 
         // Update self with the final state
-        self.accumulator = accumulator;
         self.last_instant = last_instant;
 
         if should_exit {
@@ -145,3 +117,10 @@ impl WinitBackend {
         }
     }
 }
+
+impl Runner for WinitBackend {
+    /// ウィンドウが閉じられるか `AppExit` が要求されるまでフレームを回します。
+    fn run(&mut self, app: &mut App) {
+        while !app.exit_requested() && self.poll_once(app) == PollResult::Continue {}
+    }
+}
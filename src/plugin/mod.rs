@@ -1,2 +1,2 @@
 pub mod system;
-pub use system::InputPlugin;
+pub use system::{InputPlugin, TransformPlugin};
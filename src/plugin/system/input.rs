@@ -1,9 +1,7 @@
 use crate::core::app::App;
-use crate::core::events::Events;
 use crate::core::input::Input;
 use crate::core::plugin::Plugin;
-use crate::core::schedule::Stage;
-use crate::core::DiContainer;
+use crate::core::schedule::{EventReader, ResMut, Stage};
 use crate::events::{CursorMovedEvent, KeyboardInputEvent, MouseInputEvent};
 
 pub struct InputPlugin;
@@ -22,60 +20,37 @@ impl Default for InputPlugin {
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
-        fn input_system(di: &mut DiContainer, _world: &mut crate::core::ecs::World) {
-            let input = di.get_mut::<Input>().unwrap();
+        fn input_system(
+            mut input: ResMut<Input>,
+            mut keyboard_events: EventReader<KeyboardInputEvent>,
+            mut mouse_events: EventReader<MouseInputEvent>,
+            mut cursor_events: EventReader<CursorMovedEvent>,
+        ) {
             input.clear_frame();
-            let _ = input;
 
-            // Keyboard events
-            if let Some(keyboard_events) = di.get_mut::<Events<KeyboardInputEvent>>() {
-                let drained: Vec<_> = keyboard_events.drain().collect();
-                let _ = keyboard_events;
-                if let Some(input) = di.get_mut::<Input>() {
-                    for event in drained {
-                        if event.state == crate::core::input::EngineElementState::Pressed {
-                            input.press_key(event.key);
-                        } else {
-                            input.release_key(event.key);
-                        }
-                    }
+            for event in keyboard_events.drain() {
+                if event.state == crate::core::input::EngineElementState::Pressed {
+                    input.press_key(event.key);
+                } else {
+                    input.release_key(event.key);
                 }
             }
 
-            // Mouse button events
-            if let Some(mouse_events) = di.get_mut::<Events<MouseInputEvent>>() {
-                let drained: Vec<_> = mouse_events.drain().collect();
-                let _ = mouse_events;
-                if let Some(input) = di.get_mut::<Input>() {
-                    for event in drained {
-                        if event.state == crate::core::input::EngineElementState::Pressed {
-                            input.press_mouse_button(event.button);
-                        } else {
-                            input.release_mouse_button(event.button);
-                        }
-                    }
+            for event in mouse_events.drain() {
+                if event.state == crate::core::input::EngineElementState::Pressed {
+                    input.press_mouse_button(event.button);
+                } else {
+                    input.release_mouse_button(event.button);
                 }
             }
 
-            // Cursor move events
-            if let Some(cursor_events) = di.get_mut::<Events<CursorMovedEvent>>() {
-                let drained: Vec<_> = cursor_events.drain().collect();
-                let _ = cursor_events;
-                if let Some(input) = di.get_mut::<Input>() {
-                    for event in drained {
-                        input.set_mouse_position(event.x, event.y);
-                    }
-                }
+            for event in cursor_events.drain() {
+                input.set_mouse_position(event.x, event.y);
             }
 
             // 他のイベント処理は同様のパターンで追加
         }
         app.get_di_container().insert(Input::new());
-        app.add_system(
-            Stage::ProcessInput,
-            crate::core::schedule::Priority::High,
-            input_system,
-        );
         app.add_event(
             crate::core::events::Events::<KeyboardInputEvent>::new(),
             Stage::LateUpdate,
@@ -91,5 +66,11 @@ impl Plugin for InputPlugin {
             Stage::LateUpdate,
             crate::core::schedule::Priority::Normal,
         );
+        // 登録時の検査で `Input` とイベントが揃っている必要があるので、システムは最後に登録します。
+        app.add_system(
+            Stage::ProcessInput,
+            crate::core::schedule::Priority::High,
+            input_system,
+        );
     }
 }
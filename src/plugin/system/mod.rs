@@ -2,3 +2,5 @@ pub mod input;
 pub use input::InputPlugin;
 pub mod render;
 pub use render::NullRenderer;
+pub mod transform;
+pub use transform::TransformPlugin;
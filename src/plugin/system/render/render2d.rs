@@ -1,9 +1,12 @@
+use crate::components::GlobalTransform2D;
 use crate::components::Sprite;
-use crate::components::Transform2D;
 use crate::core::app::App;
 use crate::core::plugin::Plugin;
 use crate::core::schedule::{Priority, Stage};
 use crate::events::system::{RenderCommand, RenderQueue};
+use crate::plugin::system::TransformPlugin;
+
+/// スプライトを `GlobalTransform2D`（ワールド空間）で描画するプラグイン。`TransformPlugin` も追加します。
 pub struct Render2D {}
 
 impl Render2D {
@@ -20,6 +23,7 @@ impl Default for Render2D {
 
 impl Plugin for Render2D {
     fn build(&self, app: &mut App) {
+        app.add_plugin(&TransformPlugin::new());
         app.add_event(RenderQueue::new(), Stage::PreRender, Priority::Normal);
         app.add_system(Stage::LateUpdate, Priority::Low, collect_and_send_system);
         app.add_system(Stage::Render, Priority::Normal, render_system);
@@ -36,8 +40,8 @@ fn render_system(di: &mut crate::core::DiContainer, _world: &mut crate::core::ec
                     println!(
                         "Rendering sprite {:?} at position ({}, {})",
                         sprite,
-                        transform.get_position().x,
-                        transform.get_position().y
+                        transform.translation().x,
+                        transform.translation().y
                     );
                 }
             }
@@ -57,7 +61,7 @@ fn collect_and_send_system(
 fn collect_sprite(_world: &mut crate::core::ecs::World, _cmds: &mut Vec<RenderCommand>) {
     // スプライト収集ロジックをここに実装します。
     let world = _world;
-    let mut targets = world.query_ref::<(Transform2D, Sprite)>();
+    let mut targets = world.query_ref::<(&GlobalTransform2D, &Sprite)>();
     for (_e, (transform, sprite)) in targets.iter() {
         // エンティティごとに Transform と Sprite を使って描画コマンドを生成します。
         let cmd = RenderCommand::DrawSprite {
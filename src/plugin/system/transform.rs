@@ -0,0 +1,77 @@
+use crate::components::{Children, GlobalTransform2D, Parent, Transform2D};
+use crate::core::app::App;
+use crate::core::ecs::{self, Entity};
+use crate::core::plugin::Plugin;
+use crate::core::schedule::{Priority, Stage};
+use crate::core::DiContainer;
+
+/// 親子関係に沿って `GlobalTransform2D` を計算するプラグイン。
+///
+/// 伝播システムは `Stage::PreRender` の最優先で実行されるため、`Update` / `FixedUpdate` での
+/// 変更はその後のステージ（描画コマンドの収集など）から見えます。`Render2D` は自動で追加します。
+pub struct TransformPlugin;
+
+impl TransformPlugin {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for TransformPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Plugin for TransformPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(Stage::PreRender, Priority::Highest, propagate_transforms);
+    }
+}
+
+/// `Transform2D` を持つエンティティの `GlobalTransform2D` を更新します（なければ追加）。
+///
+/// `Transform2D` を持たないエンティティは単位変換として扱い、その子には親の変換をそのまま渡します。
+/// `Transform2D` を持たない根（親のいない `Children` の持ち主）の子も、根を単位変換として計算します。
+pub fn propagate_transforms(_di: &mut DiContainer, world: &mut ecs::World) {
+    let is_root =
+        |parent: Option<&Parent>| parent.is_none_or(|parent| !world.contains(parent.get()));
+    // 親がいない、または親が despawn 済みのエンティティを根とします。
+    let mut stack: Vec<(Entity, GlobalTransform2D)> = world
+        .query_ref::<(&Transform2D, Option<&Parent>)>()
+        .iter()
+        .filter(|(_, (_, parent))| is_root(*parent))
+        .map(|(entity, (transform, _))| (entity, GlobalTransform2D::from(*transform)))
+        .collect();
+    stack.extend(
+        world
+            .query_ref::<(&Children, Option<&Parent>)>()
+            .without::<Transform2D>()
+            .iter()
+            .filter(|(_, (_, parent))| is_root(*parent))
+            .map(|(entity, _)| (entity, GlobalTransform2D::identity())),
+    );
+
+    let mut updates = Vec::new();
+    while let Some((entity, global)) = stack.pop() {
+        if let Some(children) = world.get::<Children>(entity) {
+            for child in children.iter() {
+                let child_global = match world.get::<Transform2D>(child) {
+                    Some(local) => global.mul_transform(&local),
+                    None => global,
+                };
+                stack.push((child, child_global));
+            }
+        }
+        if world.get::<Transform2D>(entity).is_some() {
+            updates.push((entity, global));
+        }
+    }
+
+    for (entity, global) in updates {
+        // 値が変わらなければ書き込まず、`Changed<GlobalTransform2D>` を余計に発火させません。
+        if world.get::<GlobalTransform2D>(entity).as_deref() != Some(&global) {
+            world.insert(entity, global);
+        }
+    }
+}
@@ -0,0 +1,8 @@
+mod prefab;
+pub use prefab::{Prefab, PrefabError, PrefabLibrary, PrefabOverrides};
+#[allow(clippy::module_inception)]
+mod scene;
+pub use scene::{
+    CameraData, Scene, SceneEntity, SceneEntityId, SceneError, SceneFormat, SpriteData,
+    TransformData,
+};
@@ -0,0 +1,219 @@
+//! プレハブ（再利用できるエンティティのひな形）。
+//!
+//! プレハブはシーンと同じ形式（TOML / RON）のファイルで、`[paths] prefab_dir` 以下に置きます。
+//! 親を持たないエンティティがちょうど 1 つ（ルート）必要で、子孫はルートと一緒に生成されます。
+//! インスタンスごとの上書き（`PrefabOverrides`）はルートにだけ適用されます。
+
+use super::{Scene, SceneEntity, SceneEntityId, SceneError, TransformData};
+use crate::core::asset::TextureManager;
+use crate::core::config::PrefabConfig;
+use crate::core::ecs::{Entity, World};
+use glam::Vec2;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PrefabError {
+    #[error("prefab_dir is not configured")]
+    MissingPrefabDir,
+    #[error("failed to load prefab: {path}")]
+    Load {
+        path: PathBuf,
+        source: Box<SceneError>,
+    },
+    #[error("prefab must have exactly one root entity, found {count}: {path}")]
+    RootCount { path: PathBuf, count: usize },
+    #[error("failed to instantiate prefab: {name}")]
+    Instantiate {
+        name: String,
+        source: Box<SceneError>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefab {
+    scene: Scene,
+    root: SceneEntityId,
+}
+
+impl Prefab {
+    /// ルートがちょうど 1 つのシーンからプレハブを作ります。そうでなければルートの数を返します。
+    pub fn from_scene(scene: Scene) -> Result<Self, usize> {
+        let roots: Vec<_> = scene
+            .entities
+            .iter()
+            .filter(|entity| entity.parent.is_none())
+            .map(|entity| entity.id)
+            .collect();
+        match roots.as_slice() {
+            [root] => Ok(Self { root: *root, scene }),
+            _ => Err(roots.len()),
+        }
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// ルートに `overrides` を適用して生成し、ルートの `Entity` を返します。
+    pub fn instantiate(
+        &self,
+        world: &mut World,
+        textures: &mut TextureManager,
+        overrides: &PrefabOverrides,
+    ) -> Result<Entity, SceneError> {
+        let mut scene = self.scene.clone();
+        if let Some(root) = scene.entities.iter_mut().find(|e| e.id == self.root) {
+            overrides.apply(root);
+        }
+        let map = scene.spawn_into(world, textures)?;
+        Ok(map[&self.root])
+    }
+}
+
+impl World {
+    /// プレハブを 1 つ生成し、ルートの `Entity` を返します。`Prefab::instantiate` と同じです。
+    pub fn spawn_prefab(
+        &mut self,
+        prefab: &Prefab,
+        textures: &mut TextureManager,
+        overrides: &PrefabOverrides,
+    ) -> Result<Entity, SceneError> {
+        prefab.instantiate(self, textures, overrides)
+    }
+}
+
+/// インスタンスごとにルートの既定値を上書きする値。`None` の項目はプレハブの値のままです。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrefabOverrides {
+    pub position: Option<Vec2>,
+    pub rotation: Option<f32>,
+    pub scale: Option<Vec2>,
+    pub tint: Option<[f32; 4]>,
+    pub visible: Option<bool>,
+}
+
+impl PrefabOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    pub fn scale(mut self, scale: Vec2) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    pub fn tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = Some(visible);
+        self
+    }
+
+    /// 変換の上書きはルートが `Transform2D` を持たなければ追加し、スプライトの上書きは
+    /// `Sprite` を持つ場合だけ適用します。
+    fn apply(&self, root: &mut SceneEntity) {
+        if self.position.is_some() || self.rotation.is_some() || self.scale.is_some() {
+            let transform = root.transform.get_or_insert_with(TransformData::default);
+            if let Some(position) = self.position {
+                transform.position = position.to_array();
+            }
+            if let Some(rotation) = self.rotation {
+                transform.rotation = rotation;
+            }
+            if let Some(scale) = self.scale {
+                transform.scale = scale.to_array();
+            }
+        }
+        if let Some(sprite) = root.sprite.as_mut() {
+            if let Some(tint) = self.tint {
+                sprite.tint = tint;
+            }
+            if let Some(visible) = self.visible {
+                sprite.visible = visible;
+            }
+        }
+    }
+}
+
+/// `prefab_dir` 以下のプレハブファイルを読み込んでキャッシュします。`DiContainer` に置いて使います。
+pub struct PrefabLibrary {
+    prefabs: HashMap<String, Prefab>,
+    config: PrefabConfig,
+}
+
+impl PrefabLibrary {
+    pub fn new(config: PrefabConfig) -> Self {
+        Self {
+            prefabs: HashMap::new(),
+            config,
+        }
+    }
+
+    /// `prefab_dir` からの相対パスでプレハブを読み込みます。読み込み済みならキャッシュを返します。
+    pub fn load(&mut self, path: &str) -> Result<&Prefab, PrefabError> {
+        if !self.prefabs.contains_key(path) {
+            let prefab = self.read(path)?;
+            self.prefabs.insert(path.to_string(), prefab);
+        }
+        Ok(&self.prefabs[path])
+    }
+
+    /// ファイルを読み直してキャッシュを置き換えます。既に生成したインスタンスは変わりません。
+    pub fn reload(&mut self, path: &str) -> Result<&Prefab, PrefabError> {
+        let prefab = self.read(path)?;
+        self.prefabs.insert(path.to_string(), prefab);
+        Ok(&self.prefabs[path])
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Prefab> {
+        self.prefabs.get(path)
+    }
+
+    /// 必要なら読み込んでから、プレハブを 1 つ生成します。
+    pub fn instantiate(
+        &mut self,
+        path: &str,
+        world: &mut World,
+        textures: &mut TextureManager,
+        overrides: &PrefabOverrides,
+    ) -> Result<Entity, PrefabError> {
+        self.load(path)?
+            .instantiate(world, textures, overrides)
+            .map_err(|source| PrefabError::Instantiate {
+                name: path.to_string(),
+                source: Box::new(source),
+            })
+    }
+
+    fn read(&self, path: &str) -> Result<Prefab, PrefabError> {
+        let prefab_dir = self
+            .config
+            .prefab_dir
+            .as_deref()
+            .ok_or(PrefabError::MissingPrefabDir)?;
+        let full_path = Path::new(prefab_dir).join(path);
+        let scene = Scene::load(&full_path).map_err(|source| PrefabError::Load {
+            path: full_path.clone(),
+            source: Box::new(source),
+        })?;
+        Prefab::from_scene(scene).map_err(|count| PrefabError::RootCount {
+            path: full_path,
+            count,
+        })
+    }
+}
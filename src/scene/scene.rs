@@ -0,0 +1,404 @@
+//! シーンの保存と読み込み。
+//!
+//! `Scene` は `World` のうちシリアライズできるコンポーネント（`Transform2D`、`Sprite`、
+//! `Camera2D`、`Name`、`Tags`）と親子関係を、シーン内だけで有効な ID で表したものです。`Sprite` の
+//! テクスチャは `TextureHandle` ではなく `TextureManager::load` に渡したパスで保存し、
+//! 読み込み時に `TextureManager` で解決します。親の参照は読み込み時に新しい `Entity` へ
+//! 付け替えます。
+
+use crate::components::{Camera2D, Children, Parent, Sprite, Transform2D};
+use crate::core::asset::{TextureError, TextureHandle, TextureManager};
+use crate::core::ecs::{Entity, Name, Tags, World};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// シーン内でエンティティを指す ID。`World` の `Entity` とは無関係です。
+pub type SceneEntityId = u64;
+
+#[derive(Debug, Error)]
+pub enum SceneError {
+    #[error("failed to read scene file: {path}")]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to write scene file: {path}")]
+    WriteFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("unsupported scene file extension (expected .toml or .ron): {path}")]
+    UnsupportedFormat { path: PathBuf },
+    #[error("failed to parse TOML scene")]
+    ParseToml(#[source] toml::de::Error),
+    #[error("failed to serialize TOML scene")]
+    SerializeToml(#[source] toml::ser::Error),
+    #[error("failed to parse RON scene")]
+    ParseRon(#[source] ron::error::SpannedError),
+    #[error("failed to serialize RON scene")]
+    SerializeRon(#[source] ron::Error),
+    #[error("sprite texture {handle:?} was not loaded through the TextureManager")]
+    UnknownTexture { handle: TextureHandle },
+    #[error("failed to load scene texture: {path}")]
+    Texture { path: String, source: TextureError },
+    #[error("scene entity id {id} is used more than once")]
+    DuplicateId { id: SceneEntityId },
+    #[error("scene entity {id} refers to unknown parent {parent}")]
+    UnknownParent {
+        id: SceneEntityId,
+        parent: SceneEntityId,
+    },
+    #[error("scene entity {id} is its own ancestor")]
+    ParentCycle { id: SceneEntityId },
+}
+
+/// シーンファイルの形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneFormat {
+    Toml,
+    Ron,
+}
+
+impl SceneFormat {
+    /// 拡張子（`.toml` / `.ron`）から形式を判定します。
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "ron" => Some(Self::Ron),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Scene {
+    #[serde(default)]
+    pub entities: Vec<SceneEntity>,
+}
+
+// TOML ではテーブルより前に値を書く必要があるため、値のフィールドを先に並べます。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SceneEntity {
+    pub id: SceneEntityId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<SceneEntityId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<SpriteData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<CameraData>,
+}
+
+impl SceneEntity {
+    pub fn new(id: SceneEntityId) -> Self {
+        Self {
+            id,
+            parent: None,
+            name: None,
+            tags: Vec::new(),
+            transform: None,
+            sprite: None,
+            camera: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TransformData {
+    #[serde(default)]
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 2],
+}
+
+impl Default for TransformData {
+    fn default() -> Self {
+        Transform2D::identity().into()
+    }
+}
+
+impl From<Transform2D> for TransformData {
+    fn from(transform: Transform2D) -> Self {
+        Self {
+            position: transform.get_position().to_array(),
+            rotation: transform.get_rotation(),
+            scale: transform.get_scale().to_array(),
+        }
+    }
+}
+
+impl From<TransformData> for Transform2D {
+    fn from(data: TransformData) -> Self {
+        let mut transform = Transform2D::identity();
+        transform.set_position(data.position.into());
+        transform.set_rotation(data.rotation);
+        transform.set_scale(data.scale.into());
+        transform
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpriteData {
+    /// `TextureManager::load` に渡すパス。`None` は無効なハンドルを表します。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<String>,
+    #[serde(default = "default_tint")]
+    pub tint: [f32; 4],
+    #[serde(default = "default_pivot")]
+    pub pivot: [f32; 2],
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CameraData {
+    pub viewport: [f32; 2],
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    #[serde(default)]
+    pub transform: TransformData,
+}
+
+impl From<&Camera2D> for CameraData {
+    fn from(camera: &Camera2D) -> Self {
+        let (width, height) = camera.get_viewport();
+        Self {
+            viewport: [width, height],
+            zoom: camera.get_zoom(),
+            transform: camera.get_transform().into(),
+        }
+    }
+}
+
+impl From<CameraData> for Camera2D {
+    fn from(data: CameraData) -> Self {
+        let mut camera = Camera2D::new(data.viewport[0], data.viewport[1]);
+        camera.set_zoom(data.zoom);
+        camera.set_transform(data.transform.into());
+        camera
+    }
+}
+
+fn default_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+fn default_tint() -> [f32; 4] {
+    [1.0, 1.0, 1.0, 1.0]
+}
+
+fn default_pivot() -> [f32; 2] {
+    [0.5, 0.5]
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+impl Scene {
+    /// `world` のうちシーンに保存できるコンポーネントか親子関係を持つエンティティを書き出します。
+    ///
+    /// 保存されないエンティティへの親の参照は取り除きます。
+    pub fn from_world(world: &World, textures: &TextureManager) -> Result<Self, SceneError> {
+        let mut query = world.query_ref::<(
+            Option<&Transform2D>,
+            Option<&Sprite>,
+            Option<&Camera2D>,
+            Option<&Parent>,
+            Option<&Children>,
+            Option<&Name>,
+            Option<&Tags>,
+        )>();
+        let mut ids = HashMap::new();
+        let mut entities = Vec::new();
+        let mut parents = Vec::new();
+        for (entity, (transform, sprite, camera, parent, children, name, tags)) in query.iter() {
+            if transform.is_none()
+                && sprite.is_none()
+                && camera.is_none()
+                && parent.is_none()
+                && children.is_none()
+                && name.is_none()
+                && tags.is_none()
+            {
+                continue;
+            }
+            let id = entities.len() as SceneEntityId;
+            ids.insert(entity, id);
+            let sprite = sprite
+                .map(|sprite| sprite_data(sprite, textures))
+                .transpose()?;
+            entities.push(SceneEntity {
+                id,
+                parent: None,
+                name: name.map(|name| name.to_string()),
+                tags: tags
+                    .map(|tags| tags.iter().map(str::to_string).collect())
+                    .unwrap_or_default(),
+                transform: transform.map(|transform| (*transform).into()),
+                sprite,
+                camera: camera.map(CameraData::from),
+            });
+            parents.push(parent.map(Parent::get));
+        }
+        for (entity, parent) in entities.iter_mut().zip(parents) {
+            entity.parent = parent.and_then(|parent| ids.get(&parent).copied());
+        }
+        Ok(Self { entities })
+    }
+
+    /// シーンのエンティティを `world` に生成し、シーン内の ID から生成した `Entity` への対応を返します。
+    ///
+    /// ID の重複や未知の親、テクスチャの読み込み失敗は、何も生成する前にエラーとして返します。
+    pub fn spawn_into(
+        &self,
+        world: &mut World,
+        textures: &mut TextureManager,
+    ) -> Result<HashMap<SceneEntityId, Entity>, SceneError> {
+        self.validate()?;
+        let mut handles = Vec::with_capacity(self.entities.len());
+        for entity in &self.entities {
+            let handle = match entity.sprite.as_ref().and_then(|s| s.texture.as_deref()) {
+                Some(path) => textures.load(path).map_err(|source| SceneError::Texture {
+                    path: path.to_string(),
+                    source,
+                })?,
+                None => TextureHandle::invalid(),
+            };
+            handles.push(handle);
+        }
+
+        let mut map = HashMap::with_capacity(self.entities.len());
+        for (data, handle) in self.entities.iter().zip(handles) {
+            let entity = world.spawn(());
+            if let Some(name) = &data.name {
+                world.insert(entity, Name::new(name.as_str()));
+            }
+            if !data.tags.is_empty() {
+                world.insert(entity, Tags::new(&data.tags));
+            }
+            if let Some(transform) = data.transform {
+                world.insert(entity, Transform2D::from(transform));
+            }
+            if let Some(sprite) = &data.sprite {
+                let mut component = Sprite::new(handle);
+                component.set_tint(sprite.tint);
+                component.set_pivot(sprite.pivot.into());
+                component.set_visible(sprite.visible);
+                world.insert(entity, component);
+            }
+            if let Some(camera) = data.camera {
+                world.insert(entity, Camera2D::from(camera));
+            }
+            map.insert(data.id, entity);
+        }
+        for data in &self.entities {
+            if let Some(parent) = data.parent {
+                world.set_parent(map[&data.id], map[&parent]);
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn encode(&self, format: SceneFormat) -> Result<String, SceneError> {
+        match format {
+            SceneFormat::Toml => toml::to_string(self).map_err(SceneError::SerializeToml),
+            SceneFormat::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(SceneError::SerializeRon),
+        }
+    }
+
+    pub fn decode(text: &str, format: SceneFormat) -> Result<Self, SceneError> {
+        match format {
+            SceneFormat::Toml => toml::from_str(text).map_err(SceneError::ParseToml),
+            SceneFormat::Ron => ron::from_str(text).map_err(SceneError::ParseRon),
+        }
+    }
+
+    /// 拡張子で形式を選んでファイルに書き出します。
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let path = path.as_ref();
+        let text = self.encode(format_of(path)?)?;
+        std::fs::write(path, text).map_err(|source| SceneError::WriteFile {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// 拡張子で形式を選んでファイルから読み込みます。
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let format = format_of(path)?;
+        let text = std::fs::read_to_string(path).map_err(|source| SceneError::ReadFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::decode(&text, format)
+    }
+
+    fn validate(&self) -> Result<(), SceneError> {
+        let mut parents = HashMap::with_capacity(self.entities.len());
+        for entity in &self.entities {
+            if parents.insert(entity.id, entity.parent).is_some() {
+                return Err(SceneError::DuplicateId { id: entity.id });
+            }
+        }
+        for entity in &self.entities {
+            let mut current = entity.parent;
+            // 祖先をたどって、シーンの大きさを超えたら循環しています。
+            for _ in 0..=self.entities.len() {
+                let Some(parent) = current else {
+                    break;
+                };
+                current = *parents.get(&parent).ok_or(SceneError::UnknownParent {
+                    id: entity.id,
+                    parent,
+                })?;
+                if parent == entity.id {
+                    return Err(SceneError::ParentCycle { id: entity.id });
+                }
+            }
+            if current.is_some() {
+                return Err(SceneError::ParentCycle { id: entity.id });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sprite_data(sprite: &Sprite, textures: &TextureManager) -> Result<SpriteData, SceneError> {
+    let handle = sprite.handle();
+    let texture = if handle.is_valid() {
+        let path = textures
+            .path(handle)
+            .ok_or(SceneError::UnknownTexture { handle: *handle })?;
+        Some(path.to_string())
+    } else {
+        None
+    };
+    Ok(SpriteData {
+        texture,
+        tint: sprite.get_tint(),
+        pivot: sprite.get_pivot().to_array(),
+        visible: sprite.is_visible(),
+    })
+}
+
+fn format_of(path: &Path) -> Result<SceneFormat, SceneError> {
+    SceneFormat::from_path(path).ok_or_else(|| SceneError::UnsupportedFormat {
+        path: path.to_path_buf(),
+    })
+}
@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+
+use rust_engine::core::schedule::{Priority, Res, ResMut, Stage};
+use rust_engine::core::{
+    App, AppExit, FixedStepOrder, FixedStepPolicy, HeadlessRunner, Time, TimeFixed,
+};
+
+#[derive(Default)]
+struct Log {
+    phases: Vec<&'static str>,
+    alphas: Vec<f32>,
+}
+
+fn startup(mut log: ResMut<Log>) {
+    log.phases.push("startup");
+}
+
+fn update(mut log: ResMut<Log>) {
+    log.phases.push("update");
+}
+
+fn fixed_update(mut log: ResMut<Log>) {
+    log.phases.push("fixed");
+}
+
+fn render(time: Res<TimeFixed>, mut log: ResMut<Log>) {
+    log.alphas.push(time.alpha());
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    // 2 進数で割り切れる時間にして、ステップ数と alpha を正確に比べます。
+    app.set_fixed_dt(0.125);
+    app.get_di_container().insert(Log::default());
+    app.add_system(Stage::Startup, Priority::Normal, startup)
+        .add_system(Stage::Update, Priority::Normal, update)
+        .add_system(Stage::FixedUpdate, Priority::Normal, fixed_update)
+        .add_system(Stage::Render, Priority::Normal, render);
+    app
+}
+
+fn log(app: &mut App) -> &Log {
+    app.get_di_container().get::<Log>().unwrap()
+}
+
+fn count(app: &mut App, phase: &str) -> usize {
+    log(app).phases.iter().filter(|p| **p == phase).count()
+}
+
+#[test]
+fn headless_runner_accumulates_fixed_steps_and_exposes_alpha() {
+    let mut app = app();
+    app.set_runner(
+        HeadlessRunner::new()
+            .frames(2)
+            .frame_time(Duration::from_secs_f64(0.1875)),
+    )
+    .run();
+
+    assert_eq!(
+        log(&mut app).phases,
+        vec!["startup", "update", "fixed", "update", "fixed", "fixed"]
+    );
+    assert_eq!(log(&mut app).alphas, vec![0.5, 0.0]);
+    let time = *app.get_di_container().get::<Time>().unwrap();
+    assert_eq!(time.elapsed_seconds(), 0.375);
+}
+
+#[test]
+fn long_frames_are_clamped_before_accumulating() {
+    let mut app = app();
+    app.run_frame(Duration::from_secs(1));
+    // 0.25 秒にクランプされるので 2 ステップ
+    assert_eq!(count(&mut app, "fixed"), 2);
+    let time = *app.get_di_container().get::<Time>().unwrap();
+    assert_eq!(time.elapsed_seconds(), 1.0);
+}
+
+#[test]
+fn max_steps_drops_the_backlog_but_keeps_the_remainder() {
+    let mut app = app();
+    app.set_fixed_step_policy(FixedStepPolicy {
+        max_frame_time: Duration::from_secs(1),
+        max_steps: Some(1),
+        ..FixedStepPolicy::default()
+    });
+    app.run_frame(Duration::from_secs_f64(0.4375));
+    assert_eq!(count(&mut app, "fixed"), 1);
+    assert_eq!(log(&mut app).alphas, vec![0.5]);
+
+    app.run_frame(Duration::from_secs_f64(0.0625));
+    assert_eq!(count(&mut app, "fixed"), 2);
+}
+
+#[test]
+fn fixed_first_runs_fixed_update_before_update() {
+    let mut app = app();
+    app.set_fixed_step_policy(FixedStepPolicy {
+        order: FixedStepOrder::FixedFirst,
+        ..FixedStepPolicy::default()
+    });
+    app.run_frame(Duration::from_secs_f64(0.125));
+    assert_eq!(log(&mut app).phases, vec!["fixed", "update"]);
+}
+
+fn exit_after_three_updates(log: Res<Log>, mut exit: ResMut<AppExit>) {
+    if log.phases.iter().filter(|p| **p == "update").count() >= 3 {
+        exit.request();
+    }
+}
+
+#[test]
+fn systems_can_stop_the_default_runner() {
+    let mut app = app();
+    app.add_system(
+        Stage::LateUpdate,
+        Priority::Normal,
+        exit_after_three_updates,
+    );
+    app.run();
+    assert!(app.exit_requested());
+    assert_eq!(count(&mut app, "update"), 3);
+}
+
+#[test]
+fn closures_can_be_used_as_runners() {
+    let mut app = app();
+    app.set_runner(|app: &mut App| app.run_frame(Duration::from_secs_f64(0.125)));
+    app.run();
+    assert_eq!(log(&mut app).phases, vec!["startup", "update", "fixed"]);
+}
+
+#[test]
+fn headless_runner_paces_frames() {
+    let mut app = app();
+    let started = Instant::now();
+    app.set_runner(
+        HeadlessRunner::new()
+            .frames(3)
+            .frame_interval(Duration::from_millis(20)),
+    )
+    .run();
+    assert_eq!(count(&mut app, "update"), 3);
+    // 最初のフレームは待たないので、3 フレームで 2 回分待つ
+    assert!(started.elapsed() >= Duration::from_millis(40));
+}
+
+#[test]
+fn headless_runner_with_a_fixed_frame_time_does_not_wait() {
+    let mut app = app();
+    let started = Instant::now();
+    app.set_runner(
+        HeadlessRunner::new()
+            .frames(120)
+            .frame_time(Duration::from_millis(16)),
+    )
+    .run();
+    assert_eq!(count(&mut app, "update"), 120);
+    // 実時間に合わせると 2 秒近くかかる
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,208 @@
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{
+    in_state, IntoSystem, OnEnter, OnExit, OnUpdate, Priority, Res, ResMut, Stage, State,
+};
+use rust_engine::core::{App, DiContainer};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum GameState {
+    Menu,
+    Playing,
+    Paused,
+}
+
+#[derive(Default)]
+struct Log(Vec<String>);
+
+fn log(di: &mut DiContainer, entry: &str) {
+    di.get_mut::<Log>().unwrap().0.push(entry.to_string());
+}
+
+fn take_log(app: &mut App) -> Vec<String> {
+    std::mem::take(&mut app.get_di_container().get_mut::<Log>().unwrap().0)
+}
+
+fn frame(app: &mut App) {
+    app.update_logic();
+    app.late_update();
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.get_di_container().insert(Log::default());
+    app.add_state(GameState::Menu)
+        .add_state_system(
+            OnEnter(GameState::Menu),
+            Priority::Normal,
+            |di: &mut DiContainer, _world: &mut ecs::World| log(di, "enter menu"),
+        )
+        .add_state_system(
+            OnExit(GameState::Menu),
+            Priority::Normal,
+            |di: &mut DiContainer, _world: &mut ecs::World| {
+                let current = di.get::<State<GameState>>().unwrap().get().clone();
+                log(di, &format!("exit menu from {current:?}"));
+            },
+        )
+        .add_state_system(
+            OnUpdate(GameState::Menu),
+            Priority::Normal,
+            |di: &mut DiContainer, _world: &mut ecs::World| log(di, "menu"),
+        )
+        .add_state_system(
+            OnEnter(GameState::Playing),
+            Priority::Normal,
+            |di: &mut DiContainer, _world: &mut ecs::World| log(di, "enter playing"),
+        )
+        .add_state_system(
+            OnUpdate(GameState::Playing),
+            Priority::Normal,
+            |di: &mut DiContainer, _world: &mut ecs::World| log(di, "playing"),
+        );
+    app
+}
+
+fn start_game(mut state: ResMut<State<GameState>>) {
+    if *state.get() == GameState::Menu {
+        state.set(GameState::Playing);
+    }
+}
+
+#[test]
+fn initial_state_is_entered_at_startup() {
+    let mut app = app();
+    app.startup();
+    assert_eq!(take_log(&mut app), vec!["enter menu"]);
+    frame(&mut app);
+    assert_eq!(take_log(&mut app), vec!["menu"]);
+}
+
+#[test]
+fn initial_state_is_entered_before_the_first_frame_without_startup() {
+    let mut app = app();
+    frame(&mut app);
+    assert_eq!(take_log(&mut app), vec!["enter menu", "menu"]);
+}
+
+#[test]
+fn initial_state_is_entered_before_the_first_transition_without_startup() {
+    let mut app = app();
+    app.get_di_container()
+        .get_mut::<State<GameState>>()
+        .unwrap()
+        .set(GameState::Playing);
+    app.late_update();
+    assert_eq!(
+        take_log(&mut app),
+        vec!["enter menu", "exit menu from Menu", "enter playing"]
+    );
+    app.startup();
+    assert!(take_log(&mut app).is_empty());
+}
+
+#[test]
+fn transitions_are_applied_between_frames() {
+    let mut app = app();
+    app.add_system(Stage::Update, Priority::Normal, start_game);
+    app.startup();
+    take_log(&mut app);
+
+    app.update_logic();
+    // 要求はまだ適用されていないので、このフレームはメニューのまま
+    assert_eq!(take_log(&mut app), vec!["menu"]);
+    let state = app.get_di_container().get::<State<GameState>>().unwrap();
+    assert_eq!(state.get(), &GameState::Menu);
+    assert_eq!(state.pending(), Some(&GameState::Playing));
+
+    app.late_update();
+    assert_eq!(
+        take_log(&mut app),
+        vec!["exit menu from Menu", "enter playing"]
+    );
+    frame(&mut app);
+    assert_eq!(take_log(&mut app), vec!["playing"]);
+}
+
+#[test]
+fn the_last_request_wins_and_same_state_requests_are_ignored() {
+    let mut app = app();
+    app.startup();
+    take_log(&mut app);
+
+    let state = app
+        .get_di_container()
+        .get_mut::<State<GameState>>()
+        .unwrap();
+    state.set(GameState::Paused);
+    state.set(GameState::Menu);
+    app.late_update();
+    assert!(take_log(&mut app).is_empty());
+
+    app.get_di_container()
+        .get_mut::<State<GameState>>()
+        .unwrap()
+        .set(GameState::Paused);
+    app.late_update();
+    assert_eq!(take_log(&mut app), vec!["exit menu from Menu"]);
+    frame(&mut app);
+    assert!(take_log(&mut app).is_empty());
+}
+
+#[test]
+fn in_state_limits_regular_systems() {
+    let mut app = app();
+    app.add_system(
+        Stage::Update,
+        Priority::Normal,
+        (|di: &mut DiContainer, _world: &mut ecs::World| log(di, "gameplay"))
+            .run_if(in_state(GameState::Playing)),
+    );
+    app.startup();
+    take_log(&mut app);
+    frame(&mut app);
+    assert_eq!(take_log(&mut app), vec!["menu"]);
+
+    app.get_di_container()
+        .get_mut::<State<GameState>>()
+        .unwrap()
+        .set(GameState::Playing);
+    app.late_update();
+    take_log(&mut app);
+    frame(&mut app);
+    assert_eq!(take_log(&mut app), vec!["gameplay", "playing"]);
+}
+
+#[test]
+#[should_panic(expected = "call App::add_state first")]
+fn state_systems_require_a_registered_state() {
+    let mut app = App::new();
+    app.add_state_system(
+        OnEnter(GameState::Menu),
+        Priority::Normal,
+        |_di: &mut DiContainer, _world: &mut ecs::World| {},
+    );
+}
+
+#[derive(Default)]
+struct Score(i32);
+
+fn add_score(mut score: ResMut<Score>) {
+    score.0 += 1;
+}
+
+fn read_score(score: Res<Score>) {
+    let _ = score.0;
+}
+
+#[test]
+#[should_panic(expected = "without an explicit order")]
+fn denying_ambiguities_after_the_first_frame_rechecks_state_schedules() {
+    let mut app = app();
+    app.get_di_container().insert(Score::default());
+    app.add_state_system(OnUpdate(GameState::Menu), Priority::High, add_score)
+        .add_state_system(OnUpdate(GameState::Menu), Priority::Low, read_score);
+    frame(&mut app);
+
+    app.set_deny_ambiguities(true);
+    frame(&mut app);
+}
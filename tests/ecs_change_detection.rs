@@ -0,0 +1,216 @@
+use rust_engine::core::ecs::{self, Added, Changed, Component, World};
+use rust_engine::core::schedule::{Priority, Query, Schedule, Stage};
+use rust_engine::core::DiContainer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Health(i32);
+impl Component for Health {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32);
+impl Component for Velocity {}
+
+/// `Schedule::run_stage` と同じ手順で `f` を 1 つのシステムとして実行します。
+fn run_as_system<R>(world: &mut World, last_run: &mut u64, f: impl FnOnce(&mut World) -> R) -> R {
+    let tick = world.change_tick();
+    world.set_last_change_tick(*last_run);
+    let result = f(world);
+    *last_run = tick;
+    world.increment_change_tick();
+    result
+}
+
+fn entities_matching<F: ecs::QueryFilter>(world: &mut World) -> Vec<ecs::Entity> {
+    let mut entities: Vec<_> = world
+        .query_ref::<&Health>()
+        .filter::<F>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    entities.sort_by_key(|e| world.get::<Health>(*e).unwrap().0);
+    entities
+}
+
+#[test]
+fn added_matches_only_components_added_since_last_run() {
+    let mut world = World::new();
+    let mut reader = 0;
+    let a = world.spawn(Health(1));
+
+    let added = run_as_system(&mut world, &mut reader, entities_matching::<Added<Health>>);
+    assert_eq!(added, vec![a]);
+
+    let b = world.spawn(Health(2));
+    world.insert(a, Velocity(0.0));
+    let added = run_as_system(&mut world, &mut reader, entities_matching::<Added<Health>>);
+    // アーキタイプを移動しても追加ティックは引き継がれる
+    assert_eq!(added, vec![b]);
+
+    let added = run_as_system(&mut world, &mut reader, entities_matching::<Added<Health>>);
+    assert!(added.is_empty());
+}
+
+#[test]
+fn changed_tracks_get_mut_insert_and_mutable_queries() {
+    let mut world = World::new();
+    let mut reader = 0;
+    let a = world.spawn(Health(1));
+    let b = world.spawn(Health(2));
+    let c = world.spawn(Health(3));
+    run_as_system(&mut world, &mut reader, |_| ());
+
+    world.get_mut::<Health>(a).unwrap().0 += 10;
+    world.insert(b, Health(20));
+    // 読み取りだけでは変更にならない
+    assert_eq!(world.query_ref::<&Health>().iter().count(), 3);
+    let changed = run_as_system(
+        &mut world,
+        &mut reader,
+        entities_matching::<Changed<Health>>,
+    );
+    assert_eq!(changed, vec![a, b]);
+    let ticks = world.component_ticks::<Health>(b).unwrap();
+    assert!(ticks.changed() > ticks.added());
+
+    let mut writer = 0;
+    run_as_system(&mut world, &mut writer, |world| {
+        for (_e, health) in world.query_mut::<&mut Health>().iter() {
+            health.0 *= 2;
+        }
+    });
+    let changed = run_as_system(
+        &mut world,
+        &mut reader,
+        entities_matching::<Changed<Health>>,
+    );
+    assert_eq!(changed, vec![c, a, b]);
+
+    // 自分自身の書き込みは次の実行では検出しない
+    let changed = run_as_system(
+        &mut world,
+        &mut writer,
+        entities_matching::<Changed<Health>>,
+    );
+    assert!(changed.is_empty());
+}
+
+#[test]
+fn removed_components_include_remove_and_despawn() {
+    let mut world = World::new();
+    let mut reader = 0;
+    let a = world.spawn((Health(1), Velocity(1.0)));
+    let b = world.spawn(Health(2));
+    run_as_system(&mut world, &mut reader, |_| ());
+
+    assert_eq!(world.remove::<Velocity>(a), Some(Velocity(1.0)));
+    world.despawn(b);
+    let (velocity, health) = run_as_system(&mut world, &mut reader, |world| {
+        (
+            world.removed::<Velocity>().iter().collect::<Vec<_>>(),
+            world.removed::<Health>().iter().collect::<Vec<_>>(),
+        )
+    });
+    assert_eq!(velocity, vec![a]);
+    assert_eq!(health, vec![b]);
+
+    // 記録は 2 回の clear_trackers で破棄される
+    let mut late_reader = 0;
+    world.clear_trackers();
+    let seen = run_as_system(&mut world, &mut late_reader, |world| {
+        !world.removed::<Health>().is_empty()
+    });
+    assert!(seen);
+    world.clear_trackers();
+    let mut later_reader = 0;
+    let seen = run_as_system(&mut world, &mut later_reader, |world| {
+        !world.removed::<Health>().is_empty()
+    });
+    assert!(!seen);
+}
+
+fn spawn_once(di: &mut DiContainer, world: &mut World) {
+    if di.get_mut::<Vec<ecs::Entity>>().is_none() {
+        let e = world.spawn(Health(0));
+        di.insert(vec![e]);
+    }
+}
+
+fn count_changed(di: &mut DiContainer, world: &mut World) {
+    let n = world
+        .query_ref::<&Health>()
+        .filter::<Changed<Health>>()
+        .iter()
+        .count();
+    di.get_mut::<Vec<usize>>().unwrap().push(n);
+}
+
+#[test]
+fn schedule_tracks_last_run_per_system() {
+    let mut schedule = Schedule::new();
+    let mut di = DiContainer::new();
+    di.insert(Vec::<usize>::new());
+    let mut world = World::new();
+    schedule.add_system(Stage::Update, Priority::High, spawn_once);
+    schedule.add_system(Stage::Update, Priority::Normal, count_changed);
+
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+
+    let e = di.get_mut::<Vec<ecs::Entity>>().unwrap()[0];
+    world.get_mut::<Health>(e).unwrap().0 = 5;
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+
+    assert_eq!(&*di.get_mut::<Vec<usize>>().unwrap(), &[1, 0, 1]);
+}
+
+#[test]
+fn mutable_queries_can_filter_on_their_own_ticks() {
+    let mut world = World::new();
+    let mut reader = 0;
+    let a = world.spawn(Health(1));
+    let b = world.spawn(Health(2));
+    run_as_system(&mut world, &mut reader, |_| ());
+
+    world.get_mut::<Health>(b).unwrap().0 = 20;
+    let changed = run_as_system(&mut world, &mut reader, |world| {
+        let mut query = world.query_mut::<&mut Health>().filter::<Changed<Health>>();
+        query
+            .iter()
+            .map(|(e, health)| {
+                health.0 += 1;
+                e
+            })
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(changed, vec![b]);
+    assert_eq!(world.get::<Health>(b).unwrap().0, 21);
+
+    let c = world.spawn(Health(3));
+    let added = run_as_system(&mut world, &mut reader, |world| {
+        let mut query = world.query_mut::<&mut Health>().filter::<Added<Health>>();
+        query.iter().map(|(e, _)| e).collect::<Vec<_>>()
+    });
+    assert_eq!(added, vec![c]);
+    // 取り出しただけでも変更として記録される
+    let ticks = world.component_ticks::<Health>(a).unwrap();
+    assert_eq!(ticks.changed(), ticks.added());
+}
+
+fn double_changed(mut query: Query<&mut Health, Changed<Health>>) {
+    for (_, health) in query.iter() {
+        health.0 *= 2;
+    }
+}
+
+#[test]
+fn system_params_can_write_and_filter_the_same_component() {
+    let mut schedule = Schedule::new();
+    let mut di = DiContainer::new();
+    let mut world = World::new();
+    let e = world.spawn(Health(1));
+    schedule.add_system(Stage::Update, Priority::Normal, double_changed);
+
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(world.get::<Health>(e).unwrap().0, 2);
+}
@@ -0,0 +1,137 @@
+use rust_engine::core::ecs::{self, Commands, Component, World};
+use rust_engine::core::schedule::{Priority, Schedule, Stage};
+use rust_engine::core::DiContainer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Health(i32);
+impl Component for Health {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Corpse;
+impl Component for Corpse {}
+
+#[test]
+fn spawn_reserves_an_id_that_is_used_on_apply() {
+    let mut world = World::new();
+    let mut commands = Commands::new(&world);
+
+    let e = commands.spawn(Health(1));
+    commands.insert(e, Corpse);
+    // 予約中の ID は直接 spawn したエンティティと衝突しない
+    let direct = world.spawn(Health(2));
+    assert_ne!(e, direct);
+    assert!(!world.contains(e));
+    assert_eq!(commands.len(), 2);
+
+    commands.apply(&mut world);
+
+    assert!(commands.is_empty());
+    assert_eq!(*world.get::<Health>(e).unwrap(), Health(1));
+    assert!(world.get::<Corpse>(e).is_some());
+    assert_eq!(*world.get::<Health>(direct).unwrap(), Health(2));
+}
+
+#[test]
+fn commands_apply_in_recorded_order() {
+    let mut world = World::new();
+    let e = world.spawn((Health(1), Corpse));
+    let mut commands = Commands::new(&world);
+
+    commands.remove::<Corpse>(e);
+    commands.insert(e, Health(5));
+    let temp = commands.spawn(Health(0));
+    commands.despawn(temp);
+    commands.add(move |world| {
+        world.get_mut::<Health>(e).unwrap().0 += 1;
+    });
+    commands.apply(&mut world);
+
+    assert!(world.get::<Corpse>(e).is_none());
+    assert_eq!(*world.get::<Health>(e).unwrap(), Health(6));
+    assert!(!world.contains(temp));
+}
+
+#[test]
+fn spawn_reserved_rejects_live_and_stale_ids() {
+    let mut world = World::new();
+    let live = world.spawn(Health(1));
+    let stale = world.spawn(Health(2));
+    world.despawn(stale);
+
+    assert!(!world.spawn_reserved(live, Health(3)));
+    assert!(!world.spawn_reserved(stale, Health(3)));
+    assert_eq!(*world.get::<Health>(live).unwrap(), Health(1));
+
+    let reserved = world.reserve_entity();
+    assert!(world.spawn_reserved(reserved, Health(4)));
+    assert!(!world.spawn_reserved(reserved, Health(5)));
+    assert_eq!(*world.get::<Health>(reserved).unwrap(), Health(4));
+}
+
+#[test]
+fn dropping_an_unapplied_buffer_releases_its_reservations() {
+    let mut world = World::new();
+    let mut commands = Commands::new(&world);
+    let dropped = commands.spawn(Health(1));
+    drop(commands);
+
+    // 解放された ID にはもう配置できない
+    assert!(!world.spawn_reserved(dropped, Health(2)));
+    let reused = world.spawn(Health(3));
+    assert_ne!(reused, dropped);
+    assert_eq!(world.entity_count(), 1);
+
+    let mut commands = Commands::new(&world);
+    let applied = commands.spawn(Health(4));
+    commands.apply(&mut world);
+    drop(commands);
+    assert_eq!(*world.get::<Health>(applied).unwrap(), Health(4));
+    let next = world.spawn(Health(5));
+    assert_ne!(next, applied);
+    assert!(world.contains(applied));
+}
+
+#[test]
+#[should_panic(expected = "other than the one")]
+fn apply_to_a_different_world_panics() {
+    let world = World::new();
+    let mut other = World::new();
+    let mut commands = Commands::new(&world);
+    commands.apply(&mut other);
+}
+
+fn reap_system(di: &mut DiContainer, world: &mut ecs::World) {
+    let commands = di.get_mut::<Commands>().unwrap();
+    for (entity, health) in world.query_mut::<&mut Health>().iter() {
+        health.0 -= 1;
+        if health.0 <= 0 {
+            commands.despawn(entity);
+            commands.spawn(Corpse);
+        }
+    }
+}
+
+fn count_system(di: &mut DiContainer, world: &mut ecs::World) {
+    // 同じステージ内ではまだ適用されていない
+    let corpses = world.query_ref::<&Corpse>().iter().count();
+    di.get_mut::<Vec<usize>>().unwrap().push(corpses);
+}
+
+#[test]
+fn schedule_applies_commands_at_the_end_of_the_stage() {
+    let mut schedule = Schedule::new();
+    let mut di = DiContainer::new();
+    di.insert(Vec::<usize>::new());
+    let mut world = World::new();
+    let weak = world.spawn(Health(1));
+    let strong = world.spawn(Health(3));
+    schedule.add_system(Stage::Update, Priority::High, reap_system);
+    schedule.add_system(Stage::Update, Priority::Low, count_system);
+
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+
+    assert!(!world.contains(weak));
+    assert!(world.contains(strong));
+    assert_eq!(world.query_ref::<&Corpse>().iter().count(), 1);
+    assert_eq!(di.get::<Vec<usize>>().unwrap(), &[0]);
+}
@@ -0,0 +1,175 @@
+use rust_engine::core::ecs::{
+    Commands, Component, ComponentAdded, ComponentInserted, ComponentRemoved, Entity, World,
+};
+use rust_engine::core::events::Events;
+use rust_engine::core::{App, DiContainer};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Health(i32);
+impl Component for Health {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Armor(i32);
+impl Component for Armor {}
+
+/// フックの呼び出し回数（add, insert）。
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct HookCount(u32, u32);
+impl Component for HookCount {}
+
+/// on_remove で取り除かれる直前の値を残します。
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tombstone(Entity, i32);
+impl Component for Tombstone {}
+
+fn count_add(world: &mut World, entity: Entity) {
+    if world.get::<HookCount>(entity).is_none() {
+        world.insert(entity, HookCount::default());
+    }
+    world.get_mut::<HookCount>(entity).unwrap().0 += 1;
+}
+
+fn count_insert(world: &mut World, entity: Entity) {
+    world.get_mut::<HookCount>(entity).unwrap().1 += 1;
+}
+
+fn bury(world: &mut World, entity: Entity) {
+    let health = world.get::<Health>(entity).unwrap().0;
+    world.spawn(Tombstone(entity, health));
+}
+
+fn hooked_world() -> World {
+    let mut world = World::new();
+    world
+        .on_add::<Health>(count_add)
+        .on_insert::<Health>(count_insert)
+        .on_remove::<Health>(bury);
+    world
+}
+
+fn tombstones(world: &World) -> Vec<Tombstone> {
+    world
+        .query_ref::<&Tombstone>()
+        .iter()
+        .map(|(_, tombstone)| *tombstone)
+        .collect()
+}
+
+#[test]
+fn add_runs_once_and_insert_runs_on_every_write() {
+    let mut world = hooked_world();
+    let e = world.spawn((Health(3), Armor(1)));
+    assert_eq!(*world.get::<HookCount>(e).unwrap(), HookCount(1, 1));
+
+    world.insert(e, Health(5));
+    world.insert(e, Armor(2));
+    assert_eq!(*world.get::<HookCount>(e).unwrap(), HookCount(1, 2));
+
+    let bare = world.spawn(Armor(0));
+    assert!(world.get::<HookCount>(bare).is_none());
+    world.insert(bare, Health(1));
+    assert_eq!(*world.get::<HookCount>(bare).unwrap(), HookCount(1, 1));
+}
+
+#[test]
+fn remove_hooks_see_the_value_before_it_is_removed() {
+    let mut world = hooked_world();
+    let removed = world.spawn(Health(1));
+    let bundled = world.spawn((Health(2), Armor(0)));
+    let despawned = world.spawn((Health(3), Armor(0)));
+    let untouched = world.spawn(Armor(0));
+
+    assert_eq!(world.remove::<Health>(removed), Some(Health(1)));
+    assert_eq!(
+        world.remove_bundle::<(Armor, Health)>(bundled),
+        Some((Armor(0), Health(2)))
+    );
+    assert!(world.despawn(despawned));
+    assert_eq!(world.remove::<Health>(untouched), None);
+    world.despawn(untouched);
+
+    let mut found = tombstones(&world);
+    found.sort_by_key(|tombstone| tombstone.1);
+    assert_eq!(
+        found,
+        vec![
+            Tombstone(removed, 1),
+            Tombstone(bundled, 2),
+            Tombstone(despawned, 3)
+        ]
+    );
+}
+
+#[test]
+fn commands_trigger_hooks_when_applied() {
+    let mut world = hooked_world();
+    let mut commands = Commands::new(&world);
+    let e = commands.spawn(Health(4));
+    commands.despawn(e);
+    assert!(tombstones(&world).is_empty());
+
+    commands.apply(&mut world);
+    assert_eq!(tombstones(&world), vec![Tombstone(e, 4)]);
+}
+
+#[test]
+fn observers_send_component_events_to_the_container() {
+    let mut app = App::new();
+    app.add_component_observer::<Health>();
+    // 2 回登録しても通知は重複しない
+    app.add_component_observer::<Health>();
+
+    let world = app.get_world();
+    let e = world.spawn(Health(1));
+    world.insert(e, Health(2));
+    world.despawn(e);
+
+    app.update_logic();
+    app.late_update();
+
+    let di = app.get_di_container();
+    let added: Vec<_> = di
+        .get_mut::<Events<ComponentAdded<Health>>>()
+        .unwrap()
+        .drain()
+        .collect();
+    assert_eq!(added, vec![ComponentAdded::new(e)]);
+    let inserted = di
+        .get_mut::<Events<ComponentInserted<Health>>>()
+        .unwrap()
+        .drain()
+        .count();
+    assert_eq!(inserted, 2);
+    let removed: Vec<_> = di
+        .get_mut::<Events<ComponentRemoved<Health>>>()
+        .unwrap()
+        .drain()
+        .map(|event| event.entity)
+        .collect();
+    assert_eq!(removed, vec![e]);
+}
+
+/// `Health` が取り除かれた回数。フックから `World::defer` で数えます。
+#[derive(Default)]
+struct Deaths(u32);
+
+fn count_death(world: &mut World, _entity: Entity) {
+    world.defer(|di| di.get_mut::<Deaths>().unwrap().0 += 1);
+}
+
+#[test]
+fn hooks_reach_resources_through_deferred_work() {
+    let mut world = World::new();
+    let mut di = DiContainer::new();
+    di.insert(Deaths::default());
+    world.on_remove::<Health>(count_death);
+
+    let a = world.spawn(Health(1));
+    let b = world.spawn(Health(2));
+    world.despawn(a);
+    world.remove::<Health>(b);
+    assert_eq!(di.get::<Deaths>().unwrap().0, 0);
+
+    world.flush_observers(&mut di);
+    assert_eq!(di.get::<Deaths>().unwrap().0, 2);
+}
@@ -0,0 +1,101 @@
+use rust_engine::core::ecs::{Component, ComponentTicks, StorageType, World};
+use std::mem::size_of;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position(f32, f32);
+impl Component for Position {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32, f32);
+impl Component for Velocity {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Selected;
+impl Component for Selected {
+    const STORAGE: StorageType = StorageType::SparseSet;
+}
+
+fn short(names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .map(|name| name.rsplit("::").next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn entity_count_tracks_spawn_and_despawn() {
+    let mut world = World::new();
+    assert_eq!(world.entity_count(), 0);
+    let a = world.spawn(Position(0.0, 0.0));
+    world.spawn(());
+    let _reserved = world.reserve_entity();
+    assert_eq!(world.entity_count(), 2);
+    world.despawn(a);
+    assert_eq!(world.entity_count(), 1);
+}
+
+#[test]
+fn component_names_list_table_and_sparse_components() {
+    let mut world = World::new();
+    let e = world.spawn((Velocity(1.0, 0.0), Position(0.0, 0.0), Selected));
+    assert_eq!(
+        short(&world.component_names(e).unwrap()),
+        vec!["Position", "Selected", "Velocity"]
+    );
+    world.remove::<Velocity>(e);
+    assert_eq!(
+        short(&world.component_names(e).unwrap()),
+        vec!["Position", "Selected"]
+    );
+    world.despawn(e);
+    assert!(world.component_names(e).is_none());
+
+    let info = world.component_info::<Selected>().unwrap();
+    assert_eq!(info.storage, StorageType::SparseSet);
+    assert_eq!(info.size, 0);
+}
+
+#[test]
+fn archetypes_report_sizes_and_memory_estimates() {
+    let mut world = World::new();
+    for i in 0..3 {
+        world.spawn(Position(i as f32, 0.0));
+    }
+    for i in 0..2 {
+        world.spawn((Position(i as f32, 0.0), Velocity(0.0, 0.0), Selected));
+    }
+
+    let mut archetypes: Vec<_> = world
+        .archetypes()
+        .into_iter()
+        .filter(|archetype| archetype.entity_count > 0)
+        .collect();
+    archetypes.sort_by_key(|archetype| archetype.components.len());
+    assert_eq!(archetypes.len(), 2);
+    assert_eq!(short(&archetypes[0].components), vec!["Position"]);
+    assert_eq!(archetypes[0].entity_count, 3);
+    // 疎集合のコンポーネントはアーキタイプに含まれない
+    assert_eq!(
+        short(&archetypes[1].components),
+        vec!["Position", "Velocity"]
+    );
+    assert_eq!(archetypes[1].entity_count, 2);
+
+    let ticks = size_of::<ComponentTicks>();
+    assert_eq!(
+        archetypes[0].estimated_bytes,
+        3 * (size_of::<Position>() + ticks)
+    );
+    let sparse = world.sparse_sets();
+    assert_eq!(sparse.len(), 1);
+    assert_eq!(sparse[0].entity_count, 2);
+
+    let stats = world.stats();
+    assert_eq!(stats.entity_count, 5);
+    assert_eq!(stats.archetype_count, 2);
+    assert_eq!(stats.component_type_count, 3);
+    assert_eq!(
+        stats.estimated_bytes,
+        archetypes[0].estimated_bytes + archetypes[1].estimated_bytes + sparse[0].estimated_bytes
+    );
+}
@@ -0,0 +1,144 @@
+use rust_engine::core::config::TextureConfig;
+use rust_engine::core::ecs::{Commands, Name, QueryError, Tags, World};
+use rust_engine::core::schedule::{Priority, Query, Stage, SystemError};
+use rust_engine::core::App;
+use rust_engine::core::TextureManager;
+use rust_engine::scene::{Scene, SceneFormat};
+use rust_engine::Transform2D;
+
+#[test]
+fn names_are_indexed_on_spawn_and_rename() {
+    let mut world = World::new();
+    let player = world.spawn(Name::new("Player"));
+    let camera = world.spawn((Name::new("MainCamera"), Transform2D::identity()));
+    assert_eq!(world.find_by_name("Player"), Some(player));
+    assert_eq!(world.find_by_name("MainCamera"), Some(camera));
+    assert_eq!(world.find_by_name("Enemy"), None);
+
+    // insert で付け替えると古い名前では見つからない
+    world.insert(player, Name::new("Hero"));
+    assert_eq!(world.find_by_name("Player"), None);
+    assert_eq!(world.find_by_name("Hero"), Some(player));
+
+    world.remove::<Name>(camera);
+    assert_eq!(world.find_by_name("MainCamera"), None);
+}
+
+#[test]
+fn renaming_onto_an_existing_name_keeps_both_in_naming_order() {
+    let mut world = World::new();
+    let guard = world.spawn(Name::new("Guard"));
+    let scout = world.spawn(Name::new("Scout"));
+    world.insert(scout, Name::new("Guard"));
+    assert_eq!(world.find_by_name("Scout"), None);
+    assert_eq!(
+        world.iter_named("Guard").collect::<Vec<_>>(),
+        vec![guard, scout]
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot be mutated in place; replace it with World::insert")]
+fn names_cannot_be_changed_through_get_mut() {
+    let mut world = World::new();
+    let player = world.spawn(Name::new("Player"));
+    world.get_mut::<Name>(player);
+}
+
+#[test]
+fn names_and_tags_cannot_be_borrowed_mutably_by_queries() {
+    let mut world = World::new();
+    assert!(matches!(
+        world.try_query_mut::<&mut Name>(),
+        Err(QueryError::Immutable { .. })
+    ));
+    assert!(matches!(
+        world.try_query_mut::<(&Name, Option<&mut Tags>)>(),
+        Err(QueryError::Immutable { .. })
+    ));
+
+    fn rename_all(_query: Query<&mut Name>) {}
+    assert!(matches!(
+        App::new().try_add_system(Stage::Update, Priority::Normal, rename_all),
+        Err(SystemError::Immutable { .. })
+    ));
+}
+
+#[test]
+fn duplicate_names_are_returned_in_naming_order() {
+    let mut world = World::new();
+    let first = world.spawn(Name::new("Enemy"));
+    let second = world.spawn(Name::new("Enemy"));
+    assert_eq!(world.find_by_name("Enemy"), Some(first));
+    assert_eq!(
+        world.iter_named("Enemy").collect::<Vec<_>>(),
+        vec![first, second]
+    );
+
+    world.despawn(first);
+    assert_eq!(world.find_by_name("Enemy"), Some(second));
+}
+
+#[test]
+fn tags_are_indexed_and_follow_despawn() {
+    let mut world = World::new();
+    let a = world.spawn(Tags::new(["enemy", "flying"]));
+    let b = world.spawn((Name::new("Boss"), Tags::new(["enemy", "enemy"])));
+    world.spawn(Tags::new(["friend"]));
+    assert_eq!(world.get::<Tags>(b).unwrap().len(), 1);
+
+    assert_eq!(world.iter_tagged("enemy").collect::<Vec<_>>(), vec![a, b]);
+    assert_eq!(world.iter_tagged("flying").collect::<Vec<_>>(), vec![a]);
+
+    world.insert(a, Tags::new(["grounded"]));
+    assert_eq!(world.iter_tagged("flying").count(), 0);
+    assert_eq!(world.iter_tagged("grounded").collect::<Vec<_>>(), vec![a]);
+
+    world.despawn(b);
+    assert_eq!(world.iter_tagged("enemy").count(), 0);
+    assert_eq!(world.find_by_name("Boss"), None);
+}
+
+#[test]
+fn commands_and_despawned_ids_do_not_leave_stale_entries() {
+    let mut world = World::new();
+    let mut commands = Commands::new(&world);
+    let e = commands.spawn(Name::new("Spawned"));
+    assert_eq!(world.find_by_name("Spawned"), None);
+    commands.apply(&mut world);
+    assert_eq!(world.find_by_name("Spawned"), Some(e));
+
+    world.despawn(e);
+    // 解放された ID が再利用されても古い名前には一致しない
+    let reused = world.spawn(Name::new("Other"));
+    assert_eq!(world.find_by_name("Spawned"), None);
+    assert_eq!(world.find_by_name("Other"), Some(reused));
+}
+
+#[test]
+fn names_and_tags_survive_scene_round_trip() {
+    let mut world = World::new();
+    world.spawn((
+        Name::new("Player"),
+        Tags::new(["hero"]),
+        Transform2D::identity(),
+    ));
+    world.spawn(Tags::new(["marker"]));
+    let textures = TextureManager::new(TextureConfig::default());
+    let scene = Scene::from_world(&world, &textures).unwrap();
+    let text = scene.encode(SceneFormat::Toml).unwrap();
+    assert!(text.contains("name = \"Player\""), "{text}");
+
+    let mut loaded = World::new();
+    Scene::decode(&text, SceneFormat::Toml)
+        .unwrap()
+        .spawn_into(
+            &mut loaded,
+            &mut TextureManager::new(TextureConfig::default()),
+        )
+        .unwrap();
+    let player = loaded.find_by_name("Player").unwrap();
+    assert!(loaded.get::<Transform2D>(player).is_some());
+    assert_eq!(loaded.iter_tagged("hero").collect::<Vec<_>>(), vec![player]);
+    assert_eq!(loaded.iter_tagged("marker").count(), 1);
+}
@@ -0,0 +1,112 @@
+use rust_engine::core::ecs::{
+    Changed, Component, Entity, ParallelIterator, StorageType, World, DEFAULT_BATCH_SIZE,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position(f32);
+impl Component for Position {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32);
+impl Component for Velocity {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Boost(f32);
+impl Component for Boost {
+    const STORAGE: StorageType = StorageType::SparseSet;
+}
+
+fn populate(world: &mut World, count: usize) {
+    for i in 0..count {
+        let e = world.spawn((Position(i as f32), Velocity(1.0 + (i % 7) as f32)));
+        if i % 3 == 0 {
+            world.insert(e, Boost(2.0));
+        }
+    }
+}
+
+fn step(position: &mut Position, velocity: &Velocity, boost: Option<&Boost>) {
+    position.0 += velocity.0 * boost.map_or(1.0, |b| b.0);
+}
+
+fn positions(world: &World) -> Vec<(Entity, f32)> {
+    world
+        .query_ref::<&Position>()
+        .iter()
+        .map(|(e, p)| (e, p.0))
+        .collect()
+}
+
+#[test]
+fn par_for_each_matches_the_serial_iterator() {
+    for batch_size in [0, 1, 7, DEFAULT_BATCH_SIZE, 100_000] {
+        let mut serial = World::new();
+        let mut parallel = World::new();
+        populate(&mut serial, 5_000);
+        populate(&mut parallel, 5_000);
+
+        for (_, (p, v, b)) in serial
+            .query_mut::<(&mut Position, &Velocity, Option<&Boost>)>()
+            .iter()
+        {
+            step(p, v, b);
+        }
+        parallel
+            .query_mut::<(&mut Position, &Velocity, Option<&Boost>)>()
+            .par_for_each(batch_size, |_, (p, v, b)| step(p, v, b));
+
+        assert_eq!(
+            positions(&serial),
+            positions(&parallel),
+            "batch size {batch_size}"
+        );
+    }
+}
+
+#[test]
+fn par_iter_collects_in_serial_order() {
+    let mut world = World::new();
+    populate(&mut world, 2_000);
+    let serial: Vec<(Entity, f32)> = world
+        .query_ref::<(&Position, &Boost)>()
+        .iter()
+        .map(|(e, (p, b))| (e, p.0 * b.0))
+        .collect();
+    let parallel: Vec<(Entity, f32)> = world
+        .query_ref::<(&Position, &Boost)>()
+        .par_iter(16)
+        .map(|(e, (p, b))| (e, p.0 * b.0))
+        .collect();
+    assert_eq!(serial.len(), 667);
+    assert_eq!(serial, parallel);
+
+    let visited = AtomicUsize::new(0);
+    world
+        .query_ref::<&Velocity>()
+        .without::<Boost>()
+        .par_for_each(32, |_, _| {
+            visited.fetch_add(1, Ordering::Relaxed);
+        });
+    assert_eq!(visited.into_inner(), 2_000 - 667);
+}
+
+#[test]
+fn par_for_each_records_changes_like_the_serial_iterator() {
+    let mut world = World::new();
+    populate(&mut world, 100);
+    world.increment_change_tick();
+    let last_run = world.change_tick();
+    world.set_last_change_tick(last_run);
+    world.increment_change_tick();
+
+    world
+        .query_mut::<&mut Boost>()
+        .par_for_each(8, |_, boost| boost.0 += 1.0);
+    let changed = world
+        .query_ref::<&Position>()
+        .filter::<Changed<Boost>>()
+        .iter()
+        .count();
+    assert_eq!(changed, 34);
+}
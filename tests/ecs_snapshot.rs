@@ -0,0 +1,139 @@
+use glam::Vec2;
+use rust_engine::core::ecs::{Added, Component, Entity, Name, SnapshotRegistry, World};
+use rust_engine::core::{DiContainer, Time, TimeFixed};
+use rust_engine::{Children, Parent, Transform2D};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32);
+impl Component for Velocity {}
+
+/// 登録しないコンポーネント。
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scratch;
+impl Component for Scratch {}
+
+fn registry() -> SnapshotRegistry {
+    let mut registry = SnapshotRegistry::new();
+    registry
+        .register::<Transform2D>()
+        .register::<Velocity>()
+        .register::<Parent>()
+        .register::<Children>()
+        .register_resource::<Time>()
+        .register_resource::<TimeFixed>();
+    registry
+}
+
+fn at(x: f32) -> Transform2D {
+    let mut transform = Transform2D::identity();
+    transform.set_position(Vec2::new(x, 0.0));
+    transform
+}
+
+fn step(world: &mut World) {
+    for (_, (transform, velocity)) in world.query_mut::<(&mut Transform2D, &Velocity)>().iter() {
+        let position = transform.get_position();
+        transform.set_position(position + Vec2::new(velocity.0, 0.0));
+    }
+}
+
+#[test]
+fn restore_brings_back_entities_with_the_same_ids() {
+    let registry = registry();
+    let mut world = World::new();
+    let mut di = DiContainer::new();
+    di.insert(TimeFixed::new(0.5));
+    let player = world.spawn((Name::new("Player"), at(0.0), Velocity(1.0)));
+    let weapon = world.spawn(at(2.0));
+    world.set_parent(weapon, player);
+    let doomed = world.spawn((at(9.0), Scratch));
+    let snapshot = registry.capture(&world, &di);
+    assert_eq!(snapshot.entity_count(), 3);
+
+    step(&mut world);
+    world.despawn(doomed);
+    world.despawn_recursive(player);
+    let extra = world.spawn(at(5.0));
+    di.get_mut::<TimeFixed>().unwrap().delta_seconds = 1.0;
+
+    snapshot.restore(&mut world, &mut di);
+
+    assert!(!world.contains(extra));
+    assert_eq!(world.find_by_name("Player"), Some(player));
+    assert_eq!(*world.get::<Transform2D>(player).unwrap(), at(0.0));
+    assert_eq!(world.get::<Parent>(weapon).unwrap().get(), player);
+    assert!(world.get::<Children>(player).unwrap().contains(weapon));
+    assert_eq!(*world.get::<Transform2D>(doomed).unwrap(), at(9.0));
+    // 登録していないコンポーネントは戻らない
+    assert!(world.get::<Scratch>(doomed).is_none());
+    assert_eq!(di.get::<TimeFixed>().unwrap().delta_seconds, 0.5);
+
+    // 復元後に生成したエンティティは復元した ID と衝突しない
+    let fresh = world.spawn(at(1.0));
+    assert!(![player, weapon, doomed].contains(&fresh));
+    assert_eq!(world.query_ref::<&Transform2D>().iter().count(), 4);
+}
+
+#[test]
+fn the_same_snapshot_can_be_restored_repeatedly() {
+    let registry = registry();
+    let mut world = World::new();
+    let mut di = DiContainer::new();
+    let e = world.spawn((at(0.0), Velocity(2.0)));
+    let snapshot = registry.capture(&world, &di);
+
+    for _ in 0..3 {
+        step(&mut world);
+        step(&mut world);
+        assert_eq!(*world.get::<Transform2D>(e).unwrap(), at(4.0));
+        snapshot.restore(&mut world, &mut di);
+        assert_eq!(*world.get::<Transform2D>(e).unwrap(), at(0.0));
+    }
+    // DiContainer に無かったリソースは復元で追加されない
+    assert!(di.get::<Time>().is_none());
+}
+
+static HOOK_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_hook(_world: &mut World, _entity: Entity) {
+    HOOK_CALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn restore_does_not_look_like_gameplay_changes() {
+    let registry = registry();
+    let mut world = World::new();
+    let mut di = DiContainer::new();
+    let e = world.spawn((Velocity(1.0), Name::new("Runner")));
+    world.increment_change_tick();
+    let snapshot = registry.capture(&world, &di);
+    let captured = world.component_ticks::<Velocity>(e).unwrap();
+
+    world.get_mut::<Velocity>(e).unwrap().0 = 3.0;
+    world.increment_change_tick();
+    world.clear_trackers();
+    world.clear_trackers();
+    world
+        .on_insert::<Velocity>(count_hook)
+        .on_remove::<Velocity>(count_hook);
+    let before = world.change_tick();
+    world.set_last_change_tick(before);
+    snapshot.restore(&mut world, &mut di);
+
+    assert_eq!(*world.get::<Velocity>(e).unwrap(), Velocity(1.0));
+    assert_eq!(world.component_ticks::<Velocity>(e), Some(captured));
+    assert!(world.removed::<Velocity>().is_empty());
+    assert!(world.removed::<Name>().is_empty());
+    assert_eq!(
+        world
+            .query_ref::<&Velocity>()
+            .filter::<Added<Velocity>>()
+            .iter()
+            .count(),
+        0
+    );
+    assert_eq!(HOOK_CALLS.load(Ordering::SeqCst), 0);
+    // 索引は作り直される
+    assert_eq!(world.find_by_name("Runner"), Some(e));
+}
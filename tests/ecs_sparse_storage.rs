@@ -0,0 +1,190 @@
+use rust_engine::core::ecs::{
+    Added, Changed, Component, Entity, SnapshotRegistry, StorageType, World,
+};
+use rust_engine::core::DiContainer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Health(i32);
+impl Component for Health {}
+
+/// 頻繁に付け外しするマーカー。
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Selected;
+impl Component for Selected {
+    const STORAGE: StorageType = StorageType::SparseSet;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Poison(i32);
+impl Component for Poison {
+    const STORAGE: StorageType = StorageType::SparseSet;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Grave(Entity, i32);
+impl Component for Grave {}
+
+fn sorted(mut entities: Vec<Entity>, world: &World) -> Vec<Entity> {
+    entities.sort_by_key(|e| world.get::<Health>(*e).map_or(i32::MAX, |h| h.0));
+    entities
+}
+
+#[test]
+fn sparse_components_are_read_written_and_removed_like_table_components() {
+    let mut world = World::new();
+    let e = world.spawn((Health(1), Poison(3)));
+    assert_eq!(*world.get::<Poison>(e).unwrap(), Poison(3));
+
+    world.get_mut::<Poison>(e).unwrap().0 += 1;
+    world.insert(e, Selected);
+    assert_eq!(*world.get::<Poison>(e).unwrap(), Poison(4));
+    assert!(world.get::<Selected>(e).is_some());
+
+    assert_eq!(world.remove::<Selected>(e), Some(Selected));
+    assert_eq!(world.remove::<Selected>(e), None);
+    assert_eq!(
+        world.remove_bundle::<(Health, Poison)>(e),
+        Some((Health(1), Poison(4)))
+    );
+    assert!(world.contains(e));
+    assert!(world.get::<Poison>(e).is_none());
+
+    // 解放したインデックスを再利用しても、古いエンティティの値は残らない
+    let doomed = world.spawn(Poison(7));
+    assert!(world.despawn(doomed));
+    let reused = world.spawn(Health(2));
+    assert!(world.get::<Poison>(reused).is_none());
+    assert!(world.get::<Poison>(doomed).is_none());
+}
+
+#[test]
+fn queries_span_table_and_sparse_components() {
+    let mut world = World::new();
+    let a = world.spawn((Health(1), Selected));
+    let b = world.spawn((Health(2), Poison(5)));
+    let c = world.spawn((Health(3), Selected, Poison(1)));
+    let lone = world.spawn(Selected);
+
+    let selected: Vec<Entity> = world
+        .query_ref::<(&Health, &Selected)>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(sorted(selected, &world), vec![a, c]);
+
+    let unselected: Vec<Entity> = world
+        .query_ref::<&Health>()
+        .without::<Selected>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(unselected, vec![b]);
+
+    let poisoned: Vec<Entity> = world
+        .query_ref::<&Health>()
+        .with::<Poison>()
+        .with::<Selected>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(poisoned, vec![c]);
+
+    let mut only_sparse: Vec<Entity> = world
+        .query_ref::<&Selected>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    only_sparse.sort_by_key(|e| *e == lone);
+    assert_eq!(only_sparse.len(), 3);
+    assert_eq!(only_sparse[2], lone);
+
+    let poison: Vec<(Entity, Option<i32>)> = world
+        .query_ref::<(&Health, Option<&Poison>)>()
+        .iter()
+        .map(|(e, (_, poison))| (e, poison.map(|p| p.0)))
+        .collect();
+    let mut poison = poison;
+    poison.sort_by_key(|(e, _)| world.get::<Health>(*e).unwrap().0);
+    assert_eq!(poison, vec![(a, None), (b, Some(5)), (c, Some(1))]);
+
+    for (_, (health, poison)) in world.query_mut::<(&mut Health, &mut Poison)>().iter() {
+        health.0 -= poison.0;
+        poison.0 = 0;
+    }
+    assert_eq!(*world.get::<Health>(b).unwrap(), Health(-3));
+    assert_eq!(*world.get::<Health>(c).unwrap(), Health(2));
+    assert_eq!(*world.get::<Poison>(c).unwrap(), Poison(0));
+    assert_eq!(*world.get::<Health>(a).unwrap(), Health(1));
+}
+
+#[test]
+fn change_detection_and_removed_cover_sparse_components() {
+    let mut world = World::new();
+    let a = world.spawn((Health(1), Poison(1)));
+    let b = world.spawn((Health(2), Poison(2)));
+    world.increment_change_tick();
+    let last_run = world.change_tick();
+    world.set_last_change_tick(last_run);
+    world.increment_change_tick();
+
+    let c = world.spawn((Health(3), Poison(3)));
+    world.get_mut::<Poison>(a).unwrap().0 = 10;
+    let added: Vec<Entity> = world
+        .query_ref::<&Health>()
+        .filter::<Added<Poison>>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(added, vec![c]);
+    let changed: Vec<Entity> = world
+        .query_ref::<&Health>()
+        .filter::<Changed<Poison>>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(sorted(changed, &world), vec![a, c]);
+    assert!(world.component_ticks::<Poison>(b).unwrap().added() < last_run + 1);
+
+    world.remove::<Poison>(b);
+    world.despawn(c);
+    let mut removed: Vec<Entity> = world.removed::<Poison>().iter().collect();
+    removed.sort_by_key(|e| *e == c);
+    assert_eq!(removed, vec![b, c]);
+}
+
+#[test]
+fn toggling_a_sparse_marker_runs_hooks() {
+    fn bury(world: &mut World, entity: Entity) {
+        let poison = world.get::<Poison>(entity).unwrap().0;
+        world.spawn(Grave(entity, poison));
+    }
+    let mut world = World::new();
+    world.on_remove::<Poison>(bury);
+    let a = world.spawn((Health(1), Poison(4)));
+    let b = world.spawn((Health(2), Poison(9)));
+    world.remove::<Poison>(a);
+    world.despawn(b);
+
+    let mut graves: Vec<Grave> = world
+        .query_ref::<&Grave>()
+        .iter()
+        .map(|(_, g)| *g)
+        .collect();
+    graves.sort_by_key(|g| g.1);
+    assert_eq!(graves, vec![Grave(a, 4), Grave(b, 9)]);
+}
+
+#[test]
+fn snapshots_restore_sparse_components() {
+    let mut registry = SnapshotRegistry::new();
+    registry.register::<Health>().register::<Poison>();
+    let mut world = World::new();
+    let mut di = DiContainer::new();
+    let e = world.spawn((Health(1), Poison(2)));
+    let snapshot = registry.capture(&world, &di);
+
+    world.remove::<Poison>(e);
+    snapshot.restore(&mut world, &mut di);
+    assert_eq!(*world.get::<Poison>(e).unwrap(), Poison(2));
+    assert_eq!(world.query_ref::<(&Health, &Poison)>().iter().count(), 1);
+}
@@ -0,0 +1,244 @@
+use rust_engine::core::ecs::{component, Component, QueryError, StorageType, With, Without, World};
+use rust_engine::core::TextureHandle;
+use rust_engine::{Sprite, Transform2D};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Health(i32);
+impl Component for Health {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32, f32);
+impl Component for Velocity {}
+
+#[test]
+fn world_spawn_get_and_despawn() {
+    let mut world = World::new();
+    let e = world.spawn(Health(10));
+
+    assert_eq!(*world.get::<Health>(e).unwrap(), Health(10));
+    assert!(world.get::<Velocity>(e).is_none());
+
+    assert!(world.despawn(e));
+    assert!(world.get::<Health>(e).is_none());
+    // 二重の despawn は失敗する
+    assert!(!world.despawn(e));
+}
+
+#[test]
+fn world_stale_entity_is_rejected_after_slot_reuse() {
+    let mut world = World::new();
+    let old = world.spawn(Health(1));
+    world.despawn(old);
+    let new = world.spawn(Health(2));
+
+    assert_ne!(old, new);
+    assert!(world.get::<Health>(old).is_none());
+    assert!(!world.insert(old, Velocity(0.0, 0.0)));
+    assert_eq!(*world.get::<Health>(new).unwrap(), Health(2));
+}
+
+#[test]
+fn world_insert_and_remove_keep_other_components() {
+    let mut world = World::new();
+    let a = world.spawn(Health(1));
+    let b = world.spawn(Health(2));
+
+    assert!(world.insert(a, Velocity(1.0, 2.0)));
+    assert_eq!(*world.get::<Health>(a).unwrap(), Health(1));
+    assert_eq!(*world.get::<Velocity>(a).unwrap(), Velocity(1.0, 2.0));
+    assert_eq!(*world.get::<Health>(b).unwrap(), Health(2));
+
+    // 既存のコンポーネントへの insert は上書き
+    assert!(world.insert(a, Health(5)));
+    assert_eq!(*world.get::<Health>(a).unwrap(), Health(5));
+
+    assert_eq!(world.remove::<Velocity>(a), Some(Velocity(1.0, 2.0)));
+    assert_eq!(world.remove::<Velocity>(a), None);
+    assert_eq!(*world.get::<Health>(a).unwrap(), Health(5));
+    assert_eq!(*world.get::<Health>(b).unwrap(), Health(2));
+}
+
+#[test]
+fn world_get_mut_and_query_mut_write_through() {
+    let mut world = World::new();
+    let e = world.spawn(Transform2D::identity());
+    let other = world.spawn(Health(3));
+
+    world
+        .get_mut::<Transform2D>(e)
+        .unwrap()
+        .set_position(glam::Vec2::new(1.0, 2.0));
+
+    for (_e, health) in world.query_mut::<&mut Health>().iter() {
+        health.0 += 1;
+    }
+
+    assert_eq!(
+        world.get::<Transform2D>(e).unwrap().get_position(),
+        glam::Vec2::new(1.0, 2.0)
+    );
+    assert_eq!(*world.get::<Health>(other).unwrap(), Health(4));
+}
+
+#[test]
+fn world_query_ref_visits_every_matching_entity() {
+    let mut world = World::new();
+    let a = world.spawn(Health(1));
+    let b = world.spawn(Velocity(0.0, 0.0));
+    world.insert(b, Health(2));
+    let c = world.spawn(Velocity(1.0, 1.0));
+    // 途中のエンティティを消しても残りの行は正しく辿れる
+    let d = world.spawn(Health(4));
+    world.despawn(a);
+
+    let mut seen: Vec<_> = world
+        .query_ref::<&Health>()
+        .iter()
+        .map(|(e, h)| (e, h.0))
+        .collect();
+    seen.sort_by_key(|(_, h)| *h);
+
+    assert_eq!(seen, vec![(b, 2), (d, 4)]);
+    assert!(!seen.iter().any(|(e, _)| *e == c));
+}
+
+#[test]
+fn world_spawn_tuple_stores_each_component_separately() {
+    let mut world = World::new();
+    let e = world.spawn((
+        Transform2D::identity(),
+        Sprite::new(TextureHandle::invalid()),
+    ));
+
+    assert!(world.get::<Transform2D>(e).is_some());
+    assert!(world.get::<Sprite>(e).is_some());
+    assert_eq!(world.query_ref::<&Transform2D>().iter().count(), 1);
+    assert_eq!(world.query_ref::<&Sprite>().iter().count(), 1);
+}
+
+#[test]
+fn world_insert_bundle_adds_and_overwrites() {
+    let mut world = World::new();
+    let e = world.spawn(Health(1));
+
+    assert!(world.insert(e, (Health(2), Velocity(3.0, 4.0))));
+    assert_eq!(*world.get::<Health>(e).unwrap(), Health(2));
+    assert_eq!(*world.get::<Velocity>(e).unwrap(), Velocity(3.0, 4.0));
+}
+
+#[test]
+fn world_remove_bundle_is_all_or_nothing() {
+    let mut world = World::new();
+    let e = world.spawn((Health(1), Velocity(2.0, 3.0), Transform2D::identity()));
+    let partial = world.spawn(Health(5));
+
+    assert_eq!(
+        world.remove_bundle::<(Health, Velocity)>(e),
+        Some((Health(1), Velocity(2.0, 3.0)))
+    );
+    assert!(world.get::<Health>(e).is_none());
+    assert!(world.get::<Transform2D>(e).is_some());
+
+    // 1 つでも欠けていれば何も取り除かない
+    assert_eq!(world.remove_bundle::<(Health, Velocity)>(partial), None);
+    assert_eq!(*world.get::<Health>(partial).unwrap(), Health(5));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hidden;
+impl Component for Hidden {}
+
+#[test]
+fn query_mut_tuple_reads_and_writes_different_components() {
+    let mut world = World::new();
+    let a = world.spawn((Health(1), Velocity(1.0, 0.0)));
+    let b = world.spawn((Health(2), Velocity(2.0, 0.0), Hidden));
+    world.spawn(Health(100));
+
+    for (_e, (health, velocity)) in world.query_mut::<(&mut Health, &Velocity)>().iter() {
+        health.0 += velocity.0 as i32;
+    }
+
+    assert_eq!(*world.get::<Health>(a).unwrap(), Health(2));
+    assert_eq!(*world.get::<Health>(b).unwrap(), Health(4));
+}
+
+#[test]
+fn query_optional_component_matches_with_and_without() {
+    let mut world = World::new();
+    let a = world.spawn(Health(1));
+    let b = world.spawn((Health(2), Velocity(5.0, 0.0)));
+
+    let mut seen: Vec<_> = world
+        .query_ref::<(&Health, Option<&Velocity>)>()
+        .iter()
+        .map(|(e, (_, velocity))| (e, velocity.copied()))
+        .collect();
+    seen.sort_by_key(|(e, _)| world.get::<Health>(*e).unwrap().0);
+
+    assert_eq!(seen, vec![(a, None), (b, Some(Velocity(5.0, 0.0)))]);
+}
+
+#[test]
+fn query_filters_with_and_without() {
+    let mut world = World::new();
+    let visible = world.spawn((Health(1), Velocity(0.0, 0.0)));
+    world.spawn((Health(2), Velocity(0.0, 0.0), Hidden));
+    world.spawn(Health(3));
+
+    let seen: Vec<_> = world
+        .query_ref::<&Health>()
+        .with::<Velocity>()
+        .without::<Hidden>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(seen, vec![visible]);
+
+    let hidden_count = world
+        .query_mut::<&mut Health>()
+        .filter::<(With<Hidden>, Without<Transform2D>)>()
+        .iter()
+        .count();
+    assert_eq!(hidden_count, 1);
+}
+
+#[test]
+fn query_mut_conflicting_borrows_are_rejected_at_construction() {
+    let mut world = World::new();
+    world.spawn(Health(1));
+
+    let err = world.try_query_mut::<(&mut Health, &Health)>().err();
+    assert!(matches!(err, Some(QueryError::Conflict { .. })));
+    assert!(world
+        .try_query_mut::<(&mut Health, Option<&mut Health>)>()
+        .is_err());
+    assert!(world.try_query_mut::<(&mut Health, &Velocity)>().is_ok());
+}
+
+#[test]
+#[should_panic(expected = "Health")]
+fn query_mut_conflict_panics_before_iteration() {
+    let mut world = World::new();
+    let _ = world.query_mut::<(&Health, &mut Health)>();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Mass(f32);
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Wrapper<T>(T);
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Frozen;
+component!(Mass, Wrapper<u8>, Frozen: SparseSet);
+
+#[test]
+fn component_macro_declares_components_and_storage() {
+    assert_eq!(Mass::STORAGE, StorageType::Table);
+    assert_eq!(Frozen::STORAGE, StorageType::SparseSet);
+
+    let mut world = World::new();
+    let e = world.spawn((Mass(2.0), Wrapper(7u8), Frozen));
+    assert_eq!(*world.get::<Mass>(e).unwrap(), Mass(2.0));
+    assert_eq!(*world.get::<Wrapper<u8>>(e).unwrap(), Wrapper(7));
+    assert!(world.get::<Frozen>(e).is_some());
+}
@@ -0,0 +1,185 @@
+use glam::Vec2;
+use rust_engine::core::config::{PrefabConfig, TextureConfig};
+use rust_engine::core::ecs::World;
+use rust_engine::core::{ConfigContainer, TextureManager};
+use rust_engine::scene::{Prefab, PrefabError, PrefabLibrary, PrefabOverrides, Scene, SceneFormat};
+use rust_engine::{Children, Parent, Sprite, Transform2D};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ENEMY: &str = r#"
+[[entities]]
+id = 1
+
+[entities.transform]
+position = [0.0, 0.0]
+scale = [2.0, 2.0]
+
+[entities.sprite]
+tint = [1.0, 0.0, 0.0, 1.0]
+
+[[entities]]
+id = 2
+parent = 1
+
+[entities.transform]
+position = [4.0, 0.0]
+"#;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!(
+        "rust_engine_prefab_{name}_{}_{}",
+        std::process::id(),
+        unique
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn no_textures() -> TextureManager {
+    TextureManager::new(TextureConfig::default())
+}
+
+/// config.toml の `[paths] prefab_dir` を経由してライブラリを作ります。
+fn library_in(dir: &Path) -> PrefabLibrary {
+    let prefab_dir = dir.join("prefabs");
+    std::fs::create_dir_all(&prefab_dir).unwrap();
+    std::fs::write(prefab_dir.join("enemy.toml"), ENEMY).unwrap();
+    let config_path = dir.join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[paths]\nprefab_dir = {:?}\n",
+            prefab_dir.display().to_string()
+        ),
+    )
+    .unwrap();
+
+    let container = ConfigContainer::load_from_file(&config_path).unwrap();
+    PrefabLibrary::new(container.get_config().prefab_config())
+}
+
+#[test]
+fn instances_get_their_own_hierarchy_and_overrides() {
+    let dir = temp_dir("instances");
+    let mut library = library_in(&dir);
+    let mut world = World::new();
+    let mut textures = no_textures();
+
+    let a = library
+        .instantiate(
+            "enemy.toml",
+            &mut world,
+            &mut textures,
+            &PrefabOverrides::new().position(Vec2::new(10.0, 20.0)),
+        )
+        .unwrap();
+    let b = library
+        .instantiate(
+            "enemy.toml",
+            &mut world,
+            &mut textures,
+            &PrefabOverrides::new()
+                .position(Vec2::new(-5.0, 0.0))
+                .tint([0.0, 1.0, 0.0, 1.0]),
+        )
+        .unwrap();
+    assert_ne!(a, b);
+
+    let transform_a = *world.get::<Transform2D>(a).unwrap();
+    assert_eq!(transform_a.get_position(), Vec2::new(10.0, 20.0));
+    // 上書きしていない値はプレハブの既定値のまま
+    assert_eq!(transform_a.get_scale(), Vec2::new(2.0, 2.0));
+    assert_eq!(
+        world.get::<Transform2D>(b).unwrap().get_position(),
+        Vec2::new(-5.0, 0.0)
+    );
+    assert_eq!(
+        world.get::<Sprite>(a).unwrap().get_tint(),
+        [1.0, 0.0, 0.0, 1.0]
+    );
+    assert_eq!(
+        world.get::<Sprite>(b).unwrap().get_tint(),
+        [0.0, 1.0, 0.0, 1.0]
+    );
+
+    for root in [a, b] {
+        let children = world.get::<Children>(root).unwrap();
+        assert_eq!(children.len(), 1);
+        let child = children.iter().next().unwrap();
+        assert_eq!(world.get::<Parent>(child).unwrap().get(), root);
+        assert_eq!(
+            world.get::<Transform2D>(child).unwrap().get_position(),
+            Vec2::new(4.0, 0.0)
+        );
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reload_picks_up_edited_files() {
+    let dir = temp_dir("reload");
+    let mut library = library_in(&dir);
+    library.load("enemy.toml").unwrap();
+    assert!(library.get("enemy.toml").unwrap().scene().entities[0]
+        .sprite
+        .is_some());
+
+    let edited = "[[entities]]\nid = 1\n[entities.transform]\nscale = [3.0, 3.0]\n";
+    let path = dir.join("prefabs/enemy.toml");
+    std::fs::write(&path, edited).unwrap();
+    // キャッシュ済みの間はファイルを読み直さない
+    assert_eq!(
+        library.load("enemy.toml").unwrap().scene().entities.len(),
+        2
+    );
+
+    library.reload("enemy.toml").unwrap();
+    let mut world = World::new();
+    let prefab = library.get("enemy.toml").unwrap();
+    let root = world
+        .spawn_prefab(prefab, &mut no_textures(), &PrefabOverrides::new())
+        .unwrap();
+    assert_eq!(
+        world.get::<Transform2D>(root).unwrap().get_scale(),
+        Vec2::new(3.0, 3.0)
+    );
+    assert!(world.get::<Sprite>(root).is_none());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn invalid_prefabs_are_reported() {
+    let mut library = PrefabLibrary::new(PrefabConfig::default());
+    assert!(matches!(
+        library.load("enemy.toml"),
+        Err(PrefabError::MissingPrefabDir)
+    ));
+
+    let dir = temp_dir("invalid");
+    let mut library = library_in(&dir);
+    std::fs::write(
+        dir.join("prefabs/pair.toml"),
+        "[[entities]]\nid = 1\n[[entities]]\nid = 2\n",
+    )
+    .unwrap();
+    assert!(matches!(
+        library.load("pair.toml"),
+        Err(PrefabError::RootCount { count: 2, .. })
+    ));
+    assert!(matches!(
+        library.load("missing.toml"),
+        Err(PrefabError::Load { .. })
+    ));
+
+    let empty = Scene::decode("entities = []", SceneFormat::Toml).unwrap();
+    assert_eq!(Prefab::from_scene(empty), Err(0));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,210 @@
+use glam::Vec2;
+use rust_engine::core::config::TextureConfig;
+use rust_engine::core::ecs::World;
+use rust_engine::core::{TextureHandle, TextureManager};
+use rust_engine::scene::{Scene, SceneError, SceneFormat};
+use rust_engine::{Camera2D, Children, Parent, Sprite, Transform2D};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!(
+        "rust_engine_scene_{name}_{}_{}",
+        std::process::id(),
+        unique
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn no_textures() -> TextureManager {
+    TextureManager::new(TextureConfig::default())
+}
+
+fn at(x: f32, y: f32) -> Transform2D {
+    let mut transform = Transform2D::identity();
+    transform.set_position(Vec2::new(x, y));
+    transform
+}
+
+fn sample_world() -> World {
+    let mut world = World::new();
+    let mut camera = Camera2D::new(800.0, 600.0);
+    camera.set_zoom(2.0);
+    camera.set_transform(at(5.0, 5.0));
+    world.spawn(camera);
+
+    let mut player_transform = at(100.0, 50.0);
+    player_transform.set_rotation(0.5);
+    let player = world.spawn(player_transform);
+    let mut sprite = Sprite::new(TextureHandle::invalid());
+    sprite.set_tint([1.0, 0.0, 0.0, 1.0]);
+    sprite.set_visible(false);
+    let weapon = world.spawn((at(5.0, 0.0), sprite));
+    world.set_parent(weapon, player);
+    world
+}
+
+fn assert_matches_sample(world: &World) {
+    let cameras: Vec<_> = world
+        .query_ref::<&Camera2D>()
+        .iter()
+        .map(|(_, camera)| (camera.get_zoom(), camera.get_viewport()))
+        .collect();
+    assert_eq!(cameras, vec![(2.0, (800.0, 600.0))]);
+
+    let (weapon, (transform, sprite, parent)) = {
+        let mut query = world.query_ref::<(&Transform2D, &Sprite, &Parent)>();
+        let mut found: Vec<_> = query
+            .iter()
+            .map(|(e, (t, s, p))| (e, (*t, s.clone(), *p)))
+            .collect();
+        assert_eq!(found.len(), 1);
+        found.pop().unwrap()
+    };
+    assert_eq!(transform, at(5.0, 0.0));
+    assert_eq!(sprite.get_tint(), [1.0, 0.0, 0.0, 1.0]);
+    assert!(!sprite.is_visible());
+    assert!(!sprite.handle().is_valid());
+
+    // 親の参照は新しい World のエンティティに付け替えられている
+    let player = parent.get();
+    assert_eq!(
+        world.get::<Transform2D>(player).unwrap().get_rotation(),
+        0.5
+    );
+    assert!(world.get::<Children>(player).unwrap().contains(weapon));
+}
+
+#[test]
+fn scene_round_trips_through_toml_and_ron() {
+    let world = sample_world();
+    let scene = Scene::from_world(&world, &no_textures()).unwrap();
+    assert_eq!(scene.entities.len(), 3);
+
+    for format in [SceneFormat::Toml, SceneFormat::Ron] {
+        let text = scene.encode(format).unwrap();
+        let decoded = Scene::decode(&text, format).unwrap();
+        assert_eq!(decoded, scene, "{format:?}:\n{text}");
+
+        let mut loaded = World::new();
+        let map = decoded.spawn_into(&mut loaded, &mut no_textures()).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_matches_sample(&loaded);
+    }
+}
+
+#[test]
+fn scene_files_pick_the_format_from_the_extension() {
+    let dir = temp_dir("files");
+    let scene = Scene::from_world(&sample_world(), &no_textures()).unwrap();
+
+    for name in ["level.toml", "level.ron"] {
+        let path = dir.join(name);
+        scene.save(&path).unwrap();
+        assert_eq!(Scene::load(&path).unwrap(), scene);
+    }
+    assert!(matches!(
+        scene.save(dir.join("level.json")),
+        Err(SceneError::UnsupportedFormat { .. })
+    ));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn hand_written_scene_uses_defaults() {
+    let text = r#"
+        [[entities]]
+        id = 1
+
+        [entities.transform]
+        position = [10.0, 20.0]
+
+        [[entities]]
+        id = 2
+        parent = 1
+
+        [entities.sprite]
+        pivot = [0.0, 0.0]
+    "#;
+    let scene = Scene::decode(text, SceneFormat::Toml).unwrap();
+    let mut world = World::new();
+    let map = scene.spawn_into(&mut world, &mut no_textures()).unwrap();
+
+    let root = map[&1];
+    let child = map[&2];
+    let transform = *world.get::<Transform2D>(root).unwrap();
+    assert_eq!(transform.get_position(), Vec2::new(10.0, 20.0));
+    assert_eq!(transform.get_scale(), Vec2::ONE);
+    let sprite = world.get::<Sprite>(child).unwrap();
+    assert_eq!(sprite.get_tint(), [1.0; 4]);
+    assert!(sprite.is_visible());
+    assert_eq!(world.get::<Parent>(child).unwrap().get(), root);
+}
+
+#[test]
+fn invalid_scenes_spawn_nothing() {
+    let cases = [
+        (
+            "[[entities]]\nid = 1\n[[entities]]\nid = 1\n",
+            "DuplicateId",
+        ),
+        ("[[entities]]\nid = 1\nparent = 7\n", "UnknownParent"),
+        (
+            "[[entities]]\nid = 1\nparent = 2\n[[entities]]\nid = 2\nparent = 1\n",
+            "ParentCycle",
+        ),
+        (
+            "[[entities]]\nid = 1\n[entities.sprite]\ntexture = \"missing.png\"\n",
+            "Texture",
+        ),
+    ];
+    for (text, expected) in cases {
+        let scene = Scene::decode(text, SceneFormat::Toml).unwrap();
+        let mut world = World::new();
+        let err = scene
+            .spawn_into(&mut world, &mut no_textures())
+            .unwrap_err();
+        assert!(format!("{err:?}").starts_with(expected), "{err:?}");
+        assert_eq!(world.query_ref::<Option<&Transform2D>>().iter().count(), 0);
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn sprite_textures_are_saved_as_paths_and_resolved_on_load() {
+    let dir = temp_dir("textures");
+    std::fs::create_dir_all(dir.join("assets")).unwrap();
+    image::RgbImage::new(2, 2)
+        .save(dir.join("assets/tex1.png"))
+        .unwrap();
+    let config = TextureConfig {
+        texture_dir: Some(dir.display().to_string()),
+    };
+
+    let mut textures = TextureManager::new(config.clone());
+    let handle = textures.load("assets/tex1.png").unwrap();
+    let mut world = World::new();
+    world.spawn((Transform2D::identity(), Sprite::new(handle)));
+    let scene = Scene::from_world(&world, &textures).unwrap();
+    let text = scene.encode(SceneFormat::Toml).unwrap();
+    assert!(text.contains("texture = \"assets/tex1.png\""), "{text}");
+
+    // 別の TextureManager でもパスから読み込み直される
+    let mut fresh = TextureManager::new(config);
+    let mut loaded = World::new();
+    Scene::decode(&text, SceneFormat::Toml)
+        .unwrap()
+        .spawn_into(&mut loaded, &mut fresh)
+        .unwrap();
+    let mut query = loaded.query_ref::<&Sprite>();
+    let (_, sprite) = query.iter().next().unwrap();
+    assert_eq!(fresh.path(sprite.handle()), Some("assets/tex1.png"));
+    assert!(fresh.get(sprite.handle()).is_some());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
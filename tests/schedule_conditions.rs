@@ -0,0 +1,181 @@
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{
+    every_seconds, not, resource_exists, Condition, IntoSystem, Priority, Schedule, Stage,
+};
+use rust_engine::core::{DiContainer, Time};
+use std::time::Duration;
+
+struct Paused(bool);
+
+#[derive(Default)]
+struct Runs(Vec<&'static str>);
+
+fn record(di: &mut DiContainer, name: &'static str) {
+    di.get_mut::<Runs>().unwrap().0.push(name);
+}
+
+fn movement(di: &mut DiContainer, _world: &mut ecs::World) {
+    record(di, "movement");
+}
+fn physics(di: &mut DiContainer, _world: &mut ecs::World) {
+    record(di, "physics");
+}
+fn ui(di: &mut DiContainer, _world: &mut ecs::World) {
+    record(di, "ui");
+}
+
+fn not_paused(di: &DiContainer) -> bool {
+    !di.get::<Paused>().is_some_and(|paused| paused.0)
+}
+
+fn take_runs(di: &mut DiContainer) -> Vec<&'static str> {
+    std::mem::take(&mut di.get_mut::<Runs>().unwrap().0)
+}
+
+fn setup() -> (DiContainer, ecs::World) {
+    let mut di = DiContainer::new();
+    di.insert(Runs::default());
+    di.insert(Paused(false));
+    (di, ecs::World::new())
+}
+
+#[test]
+fn run_if_skips_systems_while_the_condition_is_false() {
+    let (mut di, mut world) = setup();
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Update, Priority::High, movement.run_if(not_paused))
+        .add_system(Stage::Update, Priority::Low, ui);
+
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["movement", "ui"]);
+
+    di.get_mut::<Paused>().unwrap().0 = true;
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["ui"]);
+}
+
+#[test]
+fn conditions_compose() {
+    struct Level;
+    let (mut di, mut world) = setup();
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(
+            Stage::Update,
+            Priority::High,
+            movement.run_if(resource_exists::<Level>().and(not_paused)),
+        )
+        .add_system(
+            Stage::Update,
+            Priority::Normal,
+            physics.run_if(not(resource_exists::<Level>()).or(not_paused.not())),
+        )
+        .add_system(
+            Stage::Update,
+            Priority::Low,
+            ui.run_if(not_paused)
+                .run_if(|di: &DiContainer| di.get::<Level>().is_some()),
+        );
+
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["physics"]);
+
+    di.insert(Level);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["movement", "ui"]);
+
+    di.get_mut::<Paused>().unwrap().0 = true;
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["physics"]);
+}
+
+#[test]
+fn label_conditions_apply_to_every_system_with_the_label() {
+    let (mut di, mut world) = setup();
+    let evaluations = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = evaluations.clone();
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Update, Priority::High, movement.label("gameplay"))
+        .add_system(Stage::Update, Priority::Normal, physics.label("gameplay"))
+        .add_system(Stage::Update, Priority::Low, ui)
+        .add_label_condition(Stage::Update, "gameplay", move |di: &DiContainer| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            not_paused(di)
+        });
+
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["movement", "physics", "ui"]);
+
+    di.get_mut::<Paused>().unwrap().0 = true;
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["ui"]);
+    // ステージの実行ごとに 1 回だけ評価する
+    assert_eq!(evaluations.load(std::sync::atomic::Ordering::Relaxed), 2);
+
+    // 他のステージには影響しない
+    schedule.add_system(
+        Stage::LateUpdate,
+        Priority::Normal,
+        movement.label("gameplay"),
+    );
+    schedule.run_stage(Stage::LateUpdate, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["movement"]);
+}
+
+#[test]
+fn every_seconds_runs_once_per_period() {
+    let (mut di, mut world) = setup();
+    di.insert(Time::default());
+    let mut schedule = Schedule::new();
+    schedule.add_system(
+        Stage::Update,
+        Priority::Normal,
+        movement.run_if(every_seconds(1.0)),
+    );
+
+    let mut runs = Vec::new();
+    for delta in [0.4, 0.4, 0.4, 0.4, 2.5, 0.1] {
+        di.get_mut::<Time>()
+            .unwrap()
+            .advance(Duration::from_secs_f32(delta));
+        schedule.run_stage(Stage::Update, &mut di, &mut world);
+        runs.push(take_runs(&mut di).len());
+    }
+    // 1.2 秒で 1 回、4.1 秒で 1 回（何周期進んでも 1 回）、4.2 秒ではまだ次の周期ではない
+    assert_eq!(runs, vec![0, 0, 1, 0, 1, 0]);
+}
+
+#[test]
+fn skipped_systems_see_changes_from_the_frames_they_missed() {
+    struct Health;
+    impl ecs::Component for Health {}
+
+    let (mut di, mut world) = setup();
+    let mut schedule = Schedule::new();
+    schedule.add_system(
+        Stage::Update,
+        Priority::Normal,
+        (|di: &mut DiContainer, world: &mut ecs::World| {
+            let changed = world
+                .query_ref::<&Health>()
+                .filter::<ecs::Changed<Health>>()
+                .iter()
+                .count();
+            if changed > 0 {
+                record(di, "changed");
+            }
+        })
+        .run_if(not_paused),
+    );
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    di.get_mut::<Paused>().unwrap().0 = true;
+    world.spawn(Health);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert!(take_runs(&mut di).is_empty());
+
+    di.get_mut::<Paused>().unwrap().0 = false;
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_runs(&mut di), vec!["changed"]);
+}
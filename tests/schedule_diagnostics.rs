@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{Priority, Schedule, ScheduleDiagnostics, Stage};
+use rust_engine::core::{App, DiContainer};
+
+fn slow(_di: &mut DiContainer, _world: &mut ecs::World) {
+    std::thread::sleep(Duration::from_millis(20));
+}
+
+fn fast(_di: &mut DiContainer, _world: &mut ecs::World) {}
+
+fn render(_di: &mut DiContainer, _world: &mut ecs::World) {}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.get_di_container().insert(ScheduleDiagnostics::new(8));
+    app.add_system(Stage::Update, Priority::High, fast)
+        .add_system(Stage::Update, Priority::Normal, slow)
+        .add_system(Stage::Render, Priority::Normal, render);
+    app
+}
+
+fn frame(app: &mut App) {
+    app.update_logic();
+    app.render(0.0);
+    app.late_update();
+}
+
+#[test]
+fn records_per_system_and_per_stage_durations() {
+    let mut app = app();
+    frame(&mut app);
+    frame(&mut app);
+
+    let diagnostics = app.get_di_container().get::<ScheduleDiagnostics>().unwrap();
+    let slow = diagnostics.system(Stage::Update, "slow").unwrap();
+    assert_eq!(slow.len(), 2);
+    assert!(slow.average() >= Duration::from_millis(20));
+    assert!(slow.max() >= slow.percentile(50.0));
+    let update = diagnostics.stage(Stage::Update).unwrap();
+    assert!(update.last().unwrap() >= slow.last().unwrap());
+    // システムのないステージは記録しない
+    assert!(diagnostics.stage(Stage::FixedUpdate).is_none());
+
+    let slowest = diagnostics.slowest_systems(1);
+    assert!(slowest[0].name().ends_with("slow"));
+    assert!(diagnostics.summary().contains("Update avg"));
+}
+
+#[test]
+fn last_frame_lists_stages_and_systems_in_run_order() {
+    let mut app = app();
+    frame(&mut app);
+
+    let diagnostics = app.get_di_container().get::<ScheduleDiagnostics>().unwrap();
+    let last = diagnostics.last_frame().unwrap();
+    assert_eq!(last.frame, 0);
+    let stages: Vec<_> = last.stages.iter().map(|s| s.stage.as_str()).collect();
+    assert_eq!(stages, vec!["Update", "Render"]);
+    let systems: Vec<_> = last.stages[0]
+        .systems
+        .iter()
+        .map(|(name, _)| name.rsplit("::").next().unwrap())
+        .collect();
+    assert_eq!(systems, vec!["fast", "slow"]);
+    assert!(last.total() >= Duration::from_millis(20));
+    assert!(last.to_string().starts_with("frame 0"));
+}
+
+#[test]
+fn window_keeps_only_recent_samples() {
+    let mut schedule = Schedule::new();
+    let mut di = DiContainer::new();
+    let mut world = ecs::World::new();
+    di.insert(ScheduleDiagnostics::new(3));
+    schedule.add_system(Stage::Update, Priority::Normal, fast);
+
+    for _ in 0..5 {
+        schedule.run_stage(Stage::Update, &mut di, &mut world);
+    }
+    let diagnostics = di.get::<ScheduleDiagnostics>().unwrap();
+    assert_eq!(diagnostics.system(Stage::Update, "fast").unwrap().len(), 3);
+    assert_eq!(diagnostics.stage(Stage::Update).unwrap().len(), 3);
+}
+
+#[test]
+fn nothing_is_recorded_without_the_resource() {
+    let mut app = App::new();
+    app.add_system(Stage::Update, Priority::Normal, fast);
+    frame(&mut app);
+    assert!(app
+        .get_di_container()
+        .get::<ScheduleDiagnostics>()
+        .is_none());
+}
+
+#[test]
+fn custom_stages_are_recorded_under_their_name() {
+    let mut app = app();
+    let physics = Stage::Custom("Physics");
+    app.add_stage_after(Stage::Update, physics)
+        .add_system(physics, Priority::Normal, fast);
+    frame(&mut app);
+    frame(&mut app);
+
+    let diagnostics = app.get_di_container().get::<ScheduleDiagnostics>().unwrap();
+    assert_eq!(diagnostics.stage(physics).unwrap().stage(), "Physics");
+    assert_eq!(diagnostics.system(physics, "fast").unwrap().len(), 2);
+    let last = diagnostics.last_frame().unwrap();
+    let stages: Vec<_> = last.stages.iter().map(|s| s.stage.as_str()).collect();
+    assert_eq!(stages, vec!["Update", "Physics", "Render"]);
+    assert!(diagnostics.summary().contains("Physics/"));
+}
@@ -0,0 +1,255 @@
+use rust_engine::core::ecs::{self, Changed, Component};
+use rust_engine::core::schedule::{
+    Executor, IntoSystem, Priority, Query, Res, ResMut, Schedule, Stage, System, SystemAccess,
+};
+use rust_engine::core::DiContainer;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// 同時に実行されているシステムの数を記録するリソース。
+#[derive(Default)]
+struct Overlap {
+    active: AtomicUsize,
+    max_active: AtomicUsize,
+}
+
+impl Overlap {
+    /// 他のシステムが同時に入ってくるのを少しの間待ちます。
+    fn enter(&self) {
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_active.fetch_max(active, Ordering::SeqCst);
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while self.max_active.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+            std::thread::yield_now();
+        }
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn max(&self) -> usize {
+        self.max_active.load(Ordering::SeqCst)
+    }
+}
+
+struct Gravity(f32);
+struct Wind;
+
+fn first(overlap: Res<Overlap>, _gravity: Res<Gravity>) {
+    overlap.enter();
+}
+
+fn second(overlap: Res<Overlap>, _wind: Res<Wind>) {
+    overlap.enter();
+}
+
+fn setup(executor: Executor) -> (Schedule, DiContainer, ecs::World) {
+    // 1 コアの環境でも並列に実行できるようにスレッドを用意します。
+    let _ = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build_global();
+    let mut schedule = Schedule::new();
+    schedule.set_executor(executor);
+    let mut di = DiContainer::new();
+    di.insert(Overlap::default());
+    di.insert(Gravity(-9.8));
+    di.insert(Wind);
+    (schedule, di, ecs::World::new())
+}
+
+#[test]
+fn non_conflicting_systems_run_in_parallel() {
+    let (mut schedule, mut di, mut world) = setup(Executor::MultiThreaded);
+    schedule
+        .add_system(Stage::Update, Priority::Normal, first)
+        .add_system(Stage::Update, Priority::Normal, second);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(di.get::<Overlap>().unwrap().max(), 2);
+}
+
+#[test]
+fn single_threaded_executor_runs_one_system_at_a_time() {
+    let (mut schedule, mut di, mut world) = setup(Executor::SingleThreaded);
+    schedule
+        .add_system(Stage::Update, Priority::Normal, first)
+        .add_system(Stage::Update, Priority::Normal, second);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(di.get::<Overlap>().unwrap().max(), 1);
+}
+
+#[test]
+fn conflicting_and_legacy_systems_run_alone() {
+    fn writes_gravity(overlap: Res<Overlap>, mut gravity: ResMut<Gravity>) {
+        gravity.0 *= 2.0;
+        overlap.enter();
+    }
+    fn legacy(di: &mut DiContainer, _world: &mut ecs::World) {
+        di.get::<Overlap>().unwrap().enter();
+    }
+
+    let (mut schedule, mut di, mut world) = setup(Executor::MultiThreaded);
+    schedule
+        .add_system(Stage::Update, Priority::Normal, first)
+        .add_system(Stage::Update, Priority::Normal, writes_gravity)
+        .add_system(Stage::Update, Priority::Normal, legacy);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(di.get::<Overlap>().unwrap().max(), 1);
+    assert_eq!(di.get::<Gravity>().unwrap().0, -19.6);
+}
+
+/// 借用を宣言するが `prepare` を実装しないシステム。
+struct DeclaresAccessOnly(SystemAccess);
+
+impl System for DeclaresAccessOnly {
+    fn access(&self) -> Option<&SystemAccess> {
+        Some(&self.0)
+    }
+
+    fn run(&mut self, di: &mut DiContainer, _world: &mut ecs::World) {
+        di.get::<Overlap>().unwrap().enter();
+    }
+}
+
+#[test]
+fn systems_without_prepare_run_after_the_parallel_batch() {
+    let (mut schedule, mut di, mut world) = setup(Executor::MultiThreaded);
+    let mut access = SystemAccess::default();
+    access.read_resource::<Overlap>();
+    access.write_resource::<Wind>();
+    schedule
+        .add_system(Stage::Update, Priority::Normal, first)
+        .add_system(Stage::Update, Priority::Normal, DeclaresAccessOnly(access));
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(di.get::<Overlap>().unwrap().max(), 1);
+}
+
+#[derive(Default)]
+struct Steps {
+    loaded: AtomicBool,
+    saw_loaded: AtomicBool,
+}
+
+#[test]
+fn ordering_constraints_are_respected_in_parallel() {
+    fn load(steps: Res<Steps>) {
+        std::thread::sleep(Duration::from_millis(20));
+        steps.loaded.store(true, Ordering::SeqCst);
+    }
+    fn use_loaded(steps: Res<Steps>) {
+        let loaded = steps.loaded.load(Ordering::SeqCst);
+        steps.saw_loaded.store(loaded, Ordering::SeqCst);
+    }
+
+    let (mut schedule, mut di, mut world) = setup(Executor::MultiThreaded);
+    di.insert(Steps::default());
+    schedule
+        .add_system(Stage::Update, Priority::Highest, use_loaded.after("load"))
+        .add_system(Stage::Update, Priority::Lowest, load.label("load"));
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert!(di.get::<Steps>().unwrap().saw_loaded.load(Ordering::SeqCst));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position(f32);
+impl Component for Position {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32);
+impl Component for Velocity {}
+
+#[derive(Default)]
+struct Moved(usize);
+
+#[test]
+fn parallel_queries_write_disjoint_components_and_track_changes() {
+    fn integrate(mut query: Query<(&mut Position, &Velocity)>) {
+        for (_, (position, velocity)) in query.iter() {
+            position.0 += velocity.0;
+        }
+    }
+    fn damp(mut query: Query<&mut Velocity>) {
+        for (_, velocity) in query.iter() {
+            velocity.0 *= 0.5;
+        }
+    }
+    fn count_moved(mut moved: ResMut<Moved>, query: Query<&Position, Changed<Position>>) {
+        let mut query = query;
+        moved.0 += query.iter().count();
+    }
+
+    for executor in [Executor::MultiThreaded, Executor::SingleThreaded] {
+        let (mut schedule, mut di, mut world) = setup(executor);
+        di.insert(Moved::default());
+        let entities: Vec<_> = (0..100)
+            .map(|i| world.spawn((Position(0.0), Velocity(i as f32))))
+            .collect();
+        schedule
+            .add_system(Stage::Update, Priority::High, integrate.label("integrate"))
+            .add_system(Stage::Update, Priority::High, damp.after("integrate"))
+            .add_system(Stage::Update, Priority::Low, count_moved.after("integrate"));
+        schedule.run_stage(Stage::Update, &mut di, &mut world);
+        schedule.run_stage(Stage::Update, &mut di, &mut world);
+
+        for (i, entity) in entities.into_iter().enumerate() {
+            let expected = i as f32 * 1.5;
+            assert_eq!(*world.get::<Position>(entity).unwrap(), Position(expected));
+        }
+        // 書き込んだエンティティは値が同じでも変更として数える
+        assert_eq!(di.get::<Moved>().unwrap().0, 200);
+    }
+}
+
+#[test]
+fn change_filters_conflict_with_writes_to_the_filtered_component() {
+    fn watch(overlap: Res<Overlap>, _query: Query<&Velocity, Changed<Position>>) {
+        overlap.enter();
+    }
+    fn nudge(overlap: Res<Overlap>, _query: Query<&mut Position>) {
+        overlap.enter();
+    }
+    // 同じシステム内では、書き込む型を変更検出で絞り込めます。
+    fn reset_moved(mut query: Query<&mut Position, Changed<Position>>) {
+        for (_, position) in query.iter() {
+            position.0 = 0.0;
+        }
+    }
+
+    let (mut schedule, mut di, mut world) = setup(Executor::MultiThreaded);
+    world.spawn((Position(0.0), Velocity(1.0)));
+    schedule
+        .add_system(Stage::Update, Priority::High, watch)
+        .add_system(Stage::Update, Priority::High, nudge)
+        .add_system(Stage::Update, Priority::Low, reset_moved);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(di.get::<Overlap>().unwrap().max(), 1);
+}
+
+struct Enabled(bool);
+
+#[derive(Default)]
+struct Counted(usize);
+
+#[test]
+fn conditions_see_the_results_of_systems_ordered_before_them() {
+    fn enable(mut enabled: ResMut<Enabled>) {
+        enabled.0 = true;
+    }
+    fn count(mut counted: ResMut<Counted>) {
+        counted.0 += 1;
+    }
+
+    for executor in [Executor::MultiThreaded, Executor::SingleThreaded] {
+        let (mut schedule, mut di, mut world) = setup(executor);
+        di.insert(Enabled(false));
+        di.insert(Counted::default());
+        schedule
+            .add_system(Stage::Update, Priority::High, enable.label("enable"))
+            .add_system(
+                Stage::Update,
+                Priority::Low,
+                count
+                    .after("enable")
+                    .run_if(|di: &DiContainer| di.get::<Enabled>().unwrap().0),
+            );
+        schedule.run_stage(Stage::Update, &mut di, &mut world);
+        assert_eq!(di.get::<Counted>().unwrap().0, 1, "{executor:?}");
+    }
+}
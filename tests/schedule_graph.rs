@@ -0,0 +1,143 @@
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{IntoSystem, OnEnter, OnUpdate, Priority, Stage};
+use rust_engine::core::{App, DiContainer, Plugin};
+
+fn physics(_di: &mut DiContainer, _world: &mut ecs::World) {}
+fn collisions(_di: &mut DiContainer, _world: &mut ecs::World) {}
+fn hud(_di: &mut DiContainer, _world: &mut ecs::World) {}
+fn spawn_menu(_di: &mut DiContainer, _world: &mut ecs::World) {}
+fn play_music(_di: &mut DiContainer, _world: &mut ecs::World) {}
+fn animate_menu(_di: &mut DiContainer, _world: &mut ecs::World) {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum GameState {
+    Menu,
+}
+
+struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(Stage::Update, Priority::Normal, physics.label("physics"))
+            .add_system(Stage::Update, Priority::High, collisions.after("physics"));
+    }
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugin(&PhysicsPlugin);
+    app.add_system(Stage::Update, Priority::Highest, hud);
+    app
+}
+
+#[test]
+fn graph_lists_systems_with_priorities_plugins_and_labels() {
+    let mut app = app();
+    let graph = app.schedule_graph().unwrap();
+    let update = graph.stage(Stage::Update).unwrap();
+
+    let names: Vec<_> = update
+        .systems
+        .iter()
+        .map(|system| system.name.rsplit("::").next().unwrap())
+        .collect();
+    assert_eq!(names, vec!["hud", "physics", "collisions"]);
+    assert_eq!(update.systems[0].plugin, None);
+    assert!(update.systems[1]
+        .plugin
+        .is_some_and(|plugin| plugin.ends_with("PhysicsPlugin")));
+    assert_eq!(update.systems[1].priority, usize::from(Priority::Normal));
+    assert_eq!(update.systems[1].labels, vec!["physics".into()]);
+    assert_eq!(update.constraints, vec![(1, 2)]);
+
+    graph.assert_order(Stage::Update, &["hud", "physics", "collisions"]);
+    let stages: Vec<_> = graph.stages.iter().map(|info| info.stage).collect();
+    assert_eq!(stages.first(), Some(&Stage::Startup));
+    assert_eq!(stages.last(), Some(&Stage::LateUpdate));
+}
+
+#[test]
+fn graph_exports_text_and_dot() {
+    let mut app = app();
+    let graph = app.schedule_graph().unwrap();
+
+    let text = graph.to_text();
+    let update = text.split("Update:\n").nth(1).unwrap();
+    let lines: Vec<_> = update.lines().take(3).collect();
+    assert!(lines[0].starts_with("  1. schedule_graph::hud (priority 0)"));
+    assert!(lines[1].contains("physics (priority 4, plugin"));
+    assert!(lines[1].ends_with("PhysicsPlugin, labels physics)"));
+    assert!(lines[2].starts_with("  3. schedule_graph::collisions"));
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph schedule {"));
+    assert!(dot.contains("label=\"Update\";"));
+    assert!(dot.contains("[label=\"schedule_graph::hud\\npriority 0\"];"));
+    // Update は 3 番目のステージ
+    assert!(dot.contains("s2_0 -> s2_1 [style=dotted];"));
+    assert!(dot.contains("s2_1 -> s2_2;\n"));
+    assert!(dot.trim_end().ends_with('}'));
+}
+
+#[test]
+#[should_panic(expected = "system missing is not in stage Update")]
+fn assert_order_reports_missing_systems() {
+    app()
+        .schedule_graph()
+        .unwrap()
+        .assert_order(Stage::Update, &["hud", "missing"]);
+}
+
+#[test]
+fn graph_includes_state_schedules() {
+    let mut app = app();
+    app.add_state(GameState::Menu)
+        .add_state_system(
+            OnEnter(GameState::Menu),
+            Priority::Normal,
+            play_music.after("spawn_menu"),
+        )
+        .add_state_system(
+            OnEnter(GameState::Menu),
+            Priority::Low,
+            spawn_menu.label("spawn_menu"),
+        )
+        .add_state_system(OnUpdate(GameState::Menu), Priority::Normal, animate_menu);
+    let graph = app.schedule_graph().unwrap();
+
+    let schedules: Vec<_> = graph
+        .state_schedules
+        .iter()
+        .map(|info| info.schedule.as_str())
+        .collect();
+    assert_eq!(schedules, vec!["OnEnter(Menu)", "OnUpdate(Menu)"]);
+    let enter = graph.state_schedule("OnEnter(Menu)").unwrap();
+    assert_eq!(enter.constraints, vec![(0, 1)]);
+    graph.assert_state_order("OnEnter(Menu)", &["spawn_menu", "play_music"]);
+
+    let text = graph.to_text();
+    let enter = text.split("OnEnter(Menu):\n").nth(1).unwrap();
+    assert!(enter.starts_with("  1. schedule_graph::spawn_menu"));
+    assert!(text.contains("OnUpdate(Menu):\n  1. schedule_graph::animate_menu"));
+
+    let dot = graph.to_dot();
+    let clusters = graph.stages.len();
+    assert!(dot.contains(&format!(
+        "subgraph cluster_{clusters} {{\n        label=\"OnEnter(Menu)\";"
+    )));
+    assert!(dot.contains(&format!("s{clusters}_0 -> s{clusters}_1;\n")));
+}
+
+#[test]
+#[should_panic(expected = "system missing is not in state schedule OnEnter(Menu)")]
+fn assert_state_order_reports_missing_systems() {
+    let mut app = app();
+    app.add_state(GameState::Menu).add_state_system(
+        OnEnter(GameState::Menu),
+        Priority::Normal,
+        spawn_menu,
+    );
+    app.schedule_graph()
+        .unwrap()
+        .assert_state_order("OnEnter(Menu)", &["spawn_menu", "missing"]);
+}
@@ -0,0 +1,141 @@
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{
+    OnUpdate, Priority, Res, ResMut, Schedule, ScheduleCommands, Stage,
+};
+use rust_engine::core::{App, DiContainer, Plugin};
+
+#[derive(Default)]
+struct Log(Vec<&'static str>);
+
+fn physics(mut log: ResMut<Log>) {
+    log.0.push("physics");
+}
+
+fn ai(mut log: ResMut<Log>) {
+    log.0.push("ai");
+}
+
+fn ai_in_game(mut log: ResMut<Log>) {
+    log.0.push("ai_in_game");
+}
+
+fn take_log(di: &mut DiContainer) -> Vec<&'static str> {
+    std::mem::take(&mut di.get_mut::<Log>().unwrap().0)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum GameState {
+    Playing,
+}
+
+struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(Stage::Update, Priority::Normal, ai)
+            .add_state_system(OnUpdate(GameState::Playing), Priority::Normal, ai_in_game);
+    }
+}
+
+/// `DebugMenu` が開いている間は `AiPlugin` を止めます。
+struct DebugMenu(bool);
+
+fn debug_menu(menu: Res<DebugMenu>, mut commands: ResMut<ScheduleCommands>) {
+    if menu.0 {
+        commands.disable_plugin::<AiPlugin>();
+    } else {
+        commands.enable_plugin::<AiPlugin>();
+    }
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.get_di_container().insert(Log::default());
+    app.add_state(GameState::Playing);
+    app.add_plugin(&AiPlugin);
+    app
+}
+
+#[test]
+fn disabled_systems_are_skipped_until_enabled() {
+    let mut schedule = Schedule::new();
+    let mut di = DiContainer::new();
+    let mut world = ecs::World::new();
+    di.insert(Log::default());
+    let handle = schedule.register_system(Stage::Update, Priority::Normal, physics);
+    schedule.add_system(Stage::Update, Priority::High, ai);
+
+    assert!(schedule.disable_system(handle));
+    assert_eq!(schedule.is_system_enabled(handle), Some(false));
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_log(&mut di), vec!["ai"]);
+    assert!(schedule.graph().unwrap().to_text().contains(", disabled)"));
+
+    assert!(schedule.enable_system(handle));
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_log(&mut di), vec!["ai", "physics"]);
+}
+
+#[test]
+fn removed_systems_leave_the_schedule() {
+    let mut schedule = Schedule::new();
+    let mut di = DiContainer::new();
+    let mut world = ecs::World::new();
+    di.insert(Log::default());
+    let handle = schedule.register_system(Stage::Update, Priority::Normal, physics);
+    schedule.add_system(Stage::Update, Priority::High, ai);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    take_log(&mut di);
+
+    assert!(schedule.remove_system(handle));
+    assert!(!schedule.remove_system(handle));
+    assert_eq!(schedule.is_system_enabled(handle), None);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(take_log(&mut di), vec!["ai"]);
+    let graph = schedule.graph().unwrap();
+    assert_eq!(graph.stage(Stage::Update).unwrap().systems.len(), 1);
+}
+
+#[test]
+fn plugin_groups_cover_stage_and_state_systems() {
+    let mut app = app();
+    let physics = app.register_system(Stage::Update, Priority::High, physics);
+
+    assert_eq!(app.disable_plugin::<AiPlugin>(), 2);
+    app.update_logic();
+    assert_eq!(take_log(app.get_di_container()), vec!["physics"]);
+
+    assert_eq!(app.enable_plugin::<AiPlugin>(), 2);
+    app.update_logic();
+    assert_eq!(
+        take_log(app.get_di_container()),
+        vec!["physics", "ai", "ai_in_game"]
+    );
+
+    assert_eq!(app.remove_plugin::<AiPlugin>(), 2);
+    assert_eq!(app.is_system_enabled(physics), Some(true));
+    app.update_logic();
+    assert_eq!(take_log(app.get_di_container()), vec!["physics"]);
+}
+
+#[test]
+fn systems_can_toggle_plugins_through_schedule_commands() {
+    let mut app = app();
+    app.get_di_container().insert(DebugMenu(false));
+    app.add_system(Stage::ProcessInput, Priority::Normal, debug_menu);
+
+    app.process_input();
+    app.update_logic();
+    assert_eq!(take_log(app.get_di_container()), vec!["ai", "ai_in_game"]);
+
+    // メニューを開くと、その位相の終わりに AI が止まる
+    app.get_di_container().get_mut::<DebugMenu>().unwrap().0 = true;
+    app.process_input();
+    app.update_logic();
+    assert!(take_log(app.get_di_container()).is_empty());
+
+    app.get_di_container().get_mut::<DebugMenu>().unwrap().0 = false;
+    app.process_input();
+    app.update_logic();
+    assert_eq!(take_log(app.get_di_container()), vec!["ai", "ai_in_game"]);
+}
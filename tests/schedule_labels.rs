@@ -0,0 +1,148 @@
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{
+    IntoSystem, Priority, Res, ResMut, Schedule, ScheduleError, Stage,
+};
+use rust_engine::core::DiContainer;
+
+fn push(di: &mut DiContainer, value: i32) {
+    di.get_mut::<Vec<i32>>().unwrap().push(value);
+}
+
+fn sys_a(di: &mut DiContainer, _world: &mut ecs::World) {
+    push(di, 1);
+}
+fn sys_b(di: &mut DiContainer, _world: &mut ecs::World) {
+    push(di, 2);
+}
+fn sys_c(di: &mut DiContainer, _world: &mut ecs::World) {
+    push(di, 3);
+}
+fn sys_d(di: &mut DiContainer, _world: &mut ecs::World) {
+    push(di, 4);
+}
+
+fn run(schedule: &mut Schedule) -> Vec<i32> {
+    let mut di = DiContainer::new();
+    di.insert(Vec::<i32>::new());
+    let mut world = ecs::World::new();
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    di.get::<Vec<i32>>().unwrap().clone()
+}
+
+#[test]
+fn before_and_after_override_priorities() {
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Update, Priority::Highest, sys_a.after("physics"))
+        .add_system(Stage::Update, Priority::Lowest, sys_b.label("physics"))
+        .add_system(Stage::Update, Priority::Normal, sys_c.before("physics"))
+        .add_system(Stage::Update, Priority::High, sys_d);
+    // 制約のない sys_d と sys_c は優先度順、sys_b は sys_c の後、sys_a は sys_b の後
+    assert_eq!(run(&mut schedule), vec![4, 3, 2, 1]);
+}
+
+#[test]
+fn labels_can_group_several_systems() {
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Update, Priority::Highest, sys_a.after("input"))
+        .add_system(Stage::Update, Priority::Low, sys_b.label("input"))
+        .add_system(
+            Stage::Update,
+            Priority::Lowest,
+            sys_c.label("input").label("late"),
+        )
+        .add_system(Stage::Update, Priority::Highest, sys_d.after("late"));
+    assert_eq!(run(&mut schedule), vec![2, 3, 1, 4]);
+}
+
+#[test]
+fn cycles_are_reported_with_the_systems_involved() {
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(
+            Stage::Update,
+            Priority::Normal,
+            sys_a.label("a").before("b"),
+        )
+        .add_system(
+            Stage::Update,
+            Priority::Normal,
+            sys_b.label("b").before("c"),
+        )
+        .add_system(
+            Stage::Update,
+            Priority::Normal,
+            sys_c.label("c").before("a"),
+        )
+        .add_system(Stage::Update, Priority::Normal, sys_d.after("a"));
+    match schedule.build() {
+        Err(ScheduleError::Cycle { stage, systems }) => {
+            assert_eq!(stage, "Update");
+            let short: Vec<_> = systems
+                .iter()
+                .map(|name| name.rsplit("::").next().unwrap())
+                .collect();
+            assert_eq!(short.len(), 4);
+            assert_eq!(short.first(), short.last());
+            for name in ["sys_a", "sys_b", "sys_c"] {
+                assert!(short.contains(&name));
+            }
+            assert!(!short.contains(&"sys_d"));
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn unknown_labels_are_reported() {
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Update, Priority::Normal, sys_a.after("phyiscs"));
+    let err = schedule.build().unwrap_err();
+    assert!(matches!(err, ScheduleError::UnknownLabel { .. }));
+    assert!(err.to_string().contains("phyiscs"));
+}
+
+#[test]
+#[should_panic(expected = "ordering cycle")]
+fn run_stage_panics_on_cycles() {
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Update, Priority::Normal, sys_a.label("a").after("b"))
+        .add_system(Stage::Update, Priority::Normal, sys_b.label("b").after("a"));
+    run(&mut schedule);
+}
+
+#[derive(Default)]
+struct Score(i32);
+
+fn add_score(mut score: ResMut<Score>) {
+    score.0 += 1;
+}
+
+fn read_score(score: Res<Score>) {
+    let _ = score.0;
+}
+
+#[test]
+fn conflicting_unordered_systems_are_ambiguous_when_denied() {
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Update, Priority::High, add_score)
+        .add_system(Stage::Update, Priority::Low, read_score);
+    assert!(schedule.build().is_ok());
+
+    schedule.set_deny_ambiguities(true);
+    match schedule.build() {
+        Err(ScheduleError::Ambiguous { name, .. }) => assert!(name.ends_with("Score")),
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    let mut ordered = Schedule::new();
+    ordered
+        .set_deny_ambiguities(true)
+        .add_system(Stage::Update, Priority::High, add_score.label("score"))
+        .add_system(Stage::Update, Priority::Low, read_score.after("score"))
+        .add_system(Stage::Update, Priority::Normal, sys_a);
+    assert!(ordered.build().is_ok());
+}
@@ -37,3 +37,27 @@ fn schedule_respects_priority_buckets() {
     // Expect order: sys_a(10), sys_b(20), sys_c(30)
     assert_eq!(&*v, &[10, 20, 30]);
 }
+
+#[test]
+fn schedule_graph_reports_the_resolved_order() {
+    let mut sched = Schedule::new();
+    sched.add_system(Stage::Update, Priority::Low, sys_c);
+    sched.add_system(Stage::Update, Priority::Normal, sys_b);
+    sched.add_system(Stage::Update, Priority::High, sys_a);
+
+    let graph = sched.graph().unwrap();
+    graph.assert_order(Stage::Update, &["sys_a", "sys_b", "sys_c"]);
+    graph.assert_order(Stage::Update, &["schedule_order::sys_a", "sys_c"]);
+}
+
+#[test]
+#[should_panic(expected = "expected sys_c to run before sys_a")]
+fn assert_order_reports_the_wrong_order() {
+    let mut sched = Schedule::new();
+    sched.add_system(Stage::Update, Priority::High, sys_a);
+    sched.add_system(Stage::Update, Priority::Low, sys_c);
+    sched
+        .graph()
+        .unwrap()
+        .assert_order(Stage::Update, &["sys_c", "sys_a"]);
+}
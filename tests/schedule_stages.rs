@@ -0,0 +1,88 @@
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{Priority, Schedule, Stage};
+use rust_engine::core::{App, DiContainer};
+
+const POST_PHYSICS: Stage = Stage::Custom("PostPhysics");
+const PRE_UPDATE: Stage = Stage::Custom("PreUpdate");
+const PRE_PHYSICS: Stage = Stage::Custom("PrePhysics");
+
+fn recorder(name: &'static str) -> impl FnMut(&mut DiContainer, &mut ecs::World) + Send {
+    move |di: &mut DiContainer, _world: &mut ecs::World| {
+        di.get_mut::<Vec<&'static str>>().unwrap().push(name);
+    }
+}
+
+fn take(app: &mut App) -> Vec<&'static str> {
+    std::mem::take(
+        app.get_di_container()
+            .get_mut::<Vec<&'static str>>()
+            .unwrap(),
+    )
+}
+
+#[test]
+fn custom_stages_are_listed_in_run_order() {
+    let mut schedule = Schedule::new();
+    schedule
+        .add_stage_after(Stage::FixedUpdate, POST_PHYSICS)
+        .add_stage_before(Stage::FixedUpdate, PRE_PHYSICS)
+        .add_stage_before(Stage::Update, PRE_UPDATE);
+    let stages: Vec<_> = schedule.stages().collect();
+    assert_eq!(
+        stages,
+        vec![
+            Stage::Startup,
+            Stage::ProcessInput,
+            PRE_UPDATE,
+            Stage::Update,
+            PRE_PHYSICS,
+            Stage::FixedUpdate,
+            POST_PHYSICS,
+            Stage::PreRender,
+            Stage::Render,
+            Stage::LateUpdate,
+        ]
+    );
+}
+
+#[test]
+fn app_phases_run_custom_stages_next_to_their_anchor() {
+    let mut app = App::new();
+    app.get_di_container().insert(Vec::<&'static str>::new());
+    app.add_stage_after(Stage::FixedUpdate, POST_PHYSICS)
+        .add_stage_after(POST_PHYSICS, Stage::Custom("Cleanup"))
+        .add_stage_before(Stage::Update, PRE_UPDATE)
+        .add_system(Stage::FixedUpdate, Priority::Normal, recorder("physics"))
+        .add_system(POST_PHYSICS, Priority::Normal, recorder("post physics"))
+        .add_system(
+            Stage::Custom("Cleanup"),
+            Priority::Normal,
+            recorder("cleanup"),
+        )
+        .add_system(PRE_UPDATE, Priority::Normal, recorder("pre update"))
+        .add_system(Stage::Update, Priority::Normal, recorder("update"))
+        .add_system(Stage::PreRender, Priority::Normal, recorder("pre render"));
+
+    app.update_logic();
+    assert_eq!(take(&mut app), vec!["pre update", "update"]);
+    app.fixed_update();
+    assert_eq!(take(&mut app), vec!["physics", "post physics", "cleanup"]);
+    app.render(0.0);
+    assert_eq!(take(&mut app), vec!["pre render"]);
+}
+
+#[test]
+#[should_panic(expected = "is not registered")]
+fn systems_need_a_registered_stage() {
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Custom("Missing"), Priority::Normal, recorder("x"));
+}
+
+#[test]
+#[should_panic(expected = "already registered")]
+fn stages_cannot_be_registered_twice() {
+    let mut schedule = Schedule::new();
+    schedule
+        .add_stage_after(Stage::Update, POST_PHYSICS)
+        .add_stage_before(Stage::Render, POST_PHYSICS);
+}
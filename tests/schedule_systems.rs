@@ -0,0 +1,96 @@
+use rust_engine::core::ecs;
+use rust_engine::core::schedule::{BoxedSystemFn, Priority, Schedule, Stage, System};
+use rust_engine::core::{App, DiContainer};
+
+fn push(di: &mut DiContainer, value: i32) {
+    di.get_mut::<Vec<i32>>().unwrap().push(value);
+}
+
+fn fn_system(di: &mut DiContainer, _world: &mut ecs::World) {
+    push(di, 0);
+}
+
+/// `initialize` で初期値を読み、実行ごとに加算するシステム。
+struct Counter {
+    step: i32,
+    current: i32,
+    initialized: u32,
+}
+
+impl System for Counter {
+    fn initialize(&mut self, di: &mut DiContainer, _world: &mut ecs::World) {
+        self.initialized += 1;
+        self.current = *di.get::<i32>().unwrap();
+    }
+
+    fn run(&mut self, di: &mut DiContainer, _world: &mut ecs::World) {
+        assert_eq!(self.initialized, 1);
+        self.current += self.step;
+        push(di, self.current);
+    }
+}
+
+#[test]
+fn closures_keep_captured_state_between_runs() {
+    let mut schedule = Schedule::new();
+    let mut di = DiContainer::new();
+    di.insert(Vec::<i32>::new());
+    let mut world = ecs::World::new();
+
+    let multiplier = 10;
+    let mut calls = 0;
+    schedule.add_system(
+        Stage::Update,
+        Priority::Normal,
+        move |di: &mut DiContainer, _world: &mut ecs::World| {
+            calls += 1;
+            push(di, calls * multiplier);
+        },
+    );
+    let boxed: BoxedSystemFn = Box::new(|di, world| {
+        let e = world.spawn(());
+        push(di, -(world.contains(e) as i32));
+    });
+    schedule.add_system(Stage::Update, Priority::Low, boxed);
+    schedule.add_system(Stage::Update, Priority::High, fn_system);
+
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    schedule.run_stage(Stage::Update, &mut di, &mut world);
+    assert_eq!(*di.get::<Vec<i32>>().unwrap(), vec![0, 10, -1, 0, 20, -1]);
+}
+
+#[test]
+fn system_trait_is_initialized_once_before_the_first_run() {
+    let mut app = App::new();
+    app.get_di_container().insert(Vec::<i32>::new());
+    app.get_di_container().insert(100_i32);
+    app.add_system(
+        Stage::Update,
+        Priority::Normal,
+        Counter {
+            step: 5,
+            current: 0,
+            initialized: 0,
+        },
+    );
+    let boxed: Box<dyn System> = Box::new(Counter {
+        step: 1,
+        current: 0,
+        initialized: 0,
+    });
+    app.add_system(Stage::Update, Priority::Low, boxed);
+
+    app.update_logic();
+    app.update_logic();
+    assert_eq!(
+        *app.get_di_container().get::<Vec<i32>>().unwrap(),
+        vec![105, 101, 110, 102]
+    );
+}
+
+#[test]
+fn function_systems_are_named_after_the_function() {
+    use rust_engine::core::schedule::IntoSystem;
+    let system = IntoSystem::into_system(fn_system);
+    assert!(system.name().ends_with("fn_system"));
+}
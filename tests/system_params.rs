@@ -0,0 +1,158 @@
+use rust_engine::core::ecs::{self, Component, With};
+use rust_engine::core::events::Events;
+use rust_engine::core::schedule::{
+    EventReader, EventWriter, Priority, Query, Res, ResMut, Stage, SystemError,
+};
+use rust_engine::core::{App, DiContainer, TimeFixed};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position(f32);
+impl Component for Position {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(f32);
+impl Component for Velocity {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Player;
+impl Component for Player {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hit(i32);
+
+#[derive(Default)]
+struct Score(i32);
+
+fn movement(time: Res<TimeFixed>, mut query: Query<(&mut Position, &Velocity)>) {
+    for (_, (position, velocity)) in query.iter() {
+        position.0 += velocity.0 * time.delta_seconds;
+    }
+}
+
+fn hit_players(query: Query<&Position, With<Player>>, mut hits: EventWriter<Hit>) {
+    let mut query = query;
+    for (_, position) in query.iter() {
+        hits.send(Hit(position.0 as i32));
+    }
+}
+
+fn count_hits(mut score: ResMut<Score>, mut hits: EventReader<Hit>) {
+    assert_eq!(hits.iter().count(), hits.len());
+    for hit in hits.drain() {
+        score.0 += hit.0;
+    }
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.set_fixed_dt(0.5);
+    app.get_di_container().insert(Score::default());
+    app.add_event(Events::<Hit>::new(), Stage::LateUpdate, Priority::Normal);
+    app
+}
+
+#[test]
+fn systems_borrow_their_parameters_together() {
+    let mut app = app();
+    app.add_system(Stage::Update, Priority::High, movement)
+        .add_system(Stage::Update, Priority::Normal, hit_players)
+        .add_system(Stage::LateUpdate, Priority::Highest, count_hits);
+    let world = app.get_world();
+    let player = world.spawn((Position(0.0), Velocity(4.0), Player));
+    let rock = world.spawn((Position(10.0), Velocity(0.0)));
+
+    app.update_logic();
+    // 送ったイベントは LateUpdate で切り替わり、次のフレームで読める
+    app.late_update();
+    app.update_logic();
+    app.late_update();
+
+    assert_eq!(
+        *app.get_world().get::<Position>(player).unwrap(),
+        Position(4.0)
+    );
+    assert_eq!(
+        *app.get_world().get::<Position>(rock).unwrap(),
+        Position(10.0)
+    );
+    assert_eq!(app.get_di_container().get::<Score>().unwrap().0, 2);
+}
+
+#[test]
+fn closures_can_take_parameters() {
+    let mut app = app();
+    let bonus = 5;
+    app.add_system(
+        Stage::Update,
+        Priority::Normal,
+        move |mut score: ResMut<Score>, time: Res<TimeFixed>| {
+            score.0 += bonus + (time.delta_seconds * 2.0) as i32;
+        },
+    );
+    app.update_logic();
+    app.update_logic();
+    assert_eq!(app.get_di_container().get::<Score>().unwrap().0, 12);
+}
+
+#[test]
+fn missing_resources_are_reported_by_type_name_at_registration() {
+    let mut app = App::new();
+    let err = app
+        .try_add_system(Stage::Update, Priority::Normal, count_hits)
+        .err()
+        .unwrap();
+    match &err {
+        SystemError::MissingResource { system, resource } => {
+            assert!(system.ends_with("count_hits"));
+            assert!(resource.ends_with("Score"));
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert!(err.to_string().contains("Score"));
+
+    // 足りないものが揃えば登録できる
+    app.get_di_container().insert(Score::default());
+    app.get_di_container().insert(Events::<Hit>::new());
+    assert!(app
+        .try_add_system(Stage::Update, Priority::Normal, count_hits)
+        .is_ok());
+}
+
+#[test]
+fn conflicting_parameters_are_rejected() {
+    fn twice(_a: ResMut<Score>, _b: Res<Score>) {}
+    fn aliasing(_a: Query<&mut Position>, _b: Query<&Position>) {}
+    fn disjoint(_a: Query<&mut Position>, _b: Query<&mut Velocity>) {}
+
+    let mut app = app();
+    assert!(matches!(
+        app.try_add_system(Stage::Update, Priority::Normal, twice),
+        Err(SystemError::Conflict { .. })
+    ));
+    assert!(matches!(
+        app.try_add_system(Stage::Update, Priority::Normal, aliasing),
+        Err(SystemError::Conflict { .. })
+    ));
+    assert!(app
+        .try_add_system(Stage::Update, Priority::Normal, disjoint)
+        .is_ok());
+}
+
+#[test]
+#[should_panic(expected = "Score")]
+fn add_system_panics_with_the_missing_type_name() {
+    let mut app = App::new();
+    app.add_system(Stage::Update, Priority::Normal, count_hits);
+}
+
+#[test]
+fn legacy_function_systems_still_work() {
+    fn legacy(di: &mut DiContainer, world: &mut ecs::World) {
+        di.get_mut::<Score>().unwrap().0 += world.entity_count() as i32;
+    }
+    let mut app = app();
+    app.get_world().spawn(Position(0.0));
+    app.add_system(Stage::Update, Priority::Normal, legacy);
+    app.update_logic();
+    assert_eq!(app.get_di_container().get::<Score>().unwrap().0, 1);
+}
@@ -0,0 +1,164 @@
+use glam::Vec2;
+use rust_engine::core::app::App;
+use rust_engine::core::ecs::World;
+use rust_engine::core::DiContainer;
+use rust_engine::core::TextureHandle;
+use rust_engine::events::system::{RenderCommand, RenderQueue};
+use rust_engine::plugin::system::render::Render2D;
+use rust_engine::plugin::system::transform::propagate_transforms;
+use rust_engine::{Children, GlobalTransform2D, Parent, Sprite, Transform2D};
+
+fn at(x: f32, y: f32) -> Transform2D {
+    let mut transform = Transform2D::identity();
+    transform.set_position(Vec2::new(x, y));
+    transform
+}
+
+#[test]
+fn set_parent_keeps_parent_and_children_in_sync() {
+    let mut world = World::new();
+    let a = world.spawn(at(0.0, 0.0));
+    let b = world.spawn(at(0.0, 0.0));
+    let child = world.spawn(at(1.0, 0.0));
+
+    assert!(world.set_parent(child, a));
+    assert_eq!(world.get::<Parent>(child).unwrap().get(), a);
+    assert!(world.get::<Children>(a).unwrap().contains(child));
+
+    // 付け替えると元の親からは外れる
+    assert!(world.set_parent(child, b));
+    assert!(world.get::<Children>(a).is_none());
+    assert_eq!(world.get::<Children>(b).unwrap().len(), 1);
+
+    assert_eq!(world.remove_parent(child), Some(b));
+    assert!(world.get::<Parent>(child).is_none());
+    assert!(world.get::<Children>(b).is_none());
+    assert_eq!(world.remove_parent(child), None);
+}
+
+#[test]
+fn set_parent_rejects_cycles() {
+    let mut world = World::new();
+    let root = world.spawn(at(0.0, 0.0));
+    let mid = world.spawn(at(0.0, 0.0));
+    let leaf = world.spawn(at(0.0, 0.0));
+    world.set_parent(mid, root);
+    world.set_parent(leaf, mid);
+
+    assert!(!world.set_parent(root, leaf));
+    assert!(!world.set_parent(mid, mid));
+    assert!(world.get::<Parent>(root).is_none());
+}
+
+#[test]
+fn despawn_recursive_removes_descendants_only() {
+    let mut world = World::new();
+    let root = world.spawn(at(0.0, 0.0));
+    let mid = world.spawn(at(0.0, 0.0));
+    let leaf = world.spawn(at(0.0, 0.0));
+    let sibling = world.spawn(at(0.0, 0.0));
+    world.set_parent(mid, root);
+    world.set_parent(leaf, mid);
+    world.set_parent(sibling, root);
+
+    assert!(world.despawn_recursive(mid));
+    assert!(!world.contains(mid));
+    assert!(!world.contains(leaf));
+    assert!(world.contains(sibling));
+    let children: Vec<_> = world.get::<Children>(root).unwrap().iter().collect();
+    assert_eq!(children, vec![sibling]);
+}
+
+#[test]
+fn propagation_composes_parent_and_child_matrices() {
+    let mut world = World::new();
+    let mut di = DiContainer::new();
+    let mut parent_transform = at(10.0, 0.0);
+    parent_transform.set_rotation(std::f32::consts::FRAC_PI_2);
+    parent_transform.set_scale(Vec2::splat(2.0));
+    let parent = world.spawn(parent_transform);
+    let child = world.spawn(at(1.0, 0.0));
+    // Transform2D を持たない中間のエンティティは変換をそのまま通す
+    let group = world.spawn(());
+    let grandchild = world.spawn(at(0.0, 3.0));
+    world.set_parent(child, parent);
+    world.set_parent(group, child);
+    world.set_parent(grandchild, group);
+
+    propagate_transforms(&mut di, &mut world);
+
+    let global = *world.get::<GlobalTransform2D>(child).unwrap();
+    assert!(global.translation().abs_diff_eq(Vec2::new(10.0, 2.0), 1e-5));
+    assert_eq!(
+        global.matrix(),
+        parent_transform.matrix() * at(1.0, 0.0).matrix()
+    );
+    assert!(world.get::<GlobalTransform2D>(group).is_none());
+    let global = *world.get::<GlobalTransform2D>(grandchild).unwrap();
+    assert!(global.translation().abs_diff_eq(Vec2::new(4.0, 2.0), 1e-5));
+
+    world.remove_parent(child);
+    propagate_transforms(&mut di, &mut world);
+    let global = *world.get::<GlobalTransform2D>(child).unwrap();
+    assert!(global.translation().abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5));
+}
+
+#[test]
+fn roots_without_transform_pass_identity_to_their_children() {
+    let mut world = World::new();
+    let mut di = DiContainer::new();
+    let group = world.spawn(());
+    let child = world.spawn(at(1.0, 2.0));
+    let grandchild = world.spawn(at(3.0, 0.0));
+    world.set_parent(child, group);
+    world.set_parent(grandchild, child);
+
+    propagate_transforms(&mut di, &mut world);
+
+    assert!(world.get::<GlobalTransform2D>(group).is_none());
+    let global = *world.get::<GlobalTransform2D>(child).unwrap();
+    assert!(global.translation().abs_diff_eq(Vec2::new(1.0, 2.0), 1e-5));
+    let global = *world.get::<GlobalTransform2D>(grandchild).unwrap();
+    assert!(global.translation().abs_diff_eq(Vec2::new(4.0, 2.0), 1e-5));
+}
+
+#[test]
+fn render2d_draws_children_in_world_space() {
+    let mut app = App::new();
+    app.add_plugin(&Render2D::new());
+    let world = app.get_world();
+    let player = world.spawn(at(100.0, 50.0));
+    let weapon = world.spawn((at(5.0, 0.0), Sprite::new(TextureHandle::invalid())));
+    world.set_parent(weapon, player);
+
+    app.render(0.0);
+    app.late_update();
+
+    let queue = app.get_di_container().get_mut::<RenderQueue>().unwrap();
+    queue.update();
+    let positions: Vec<_> = queue
+        .drain()
+        .map(|command| match command {
+            RenderCommand::DrawSprite { transform, .. } => transform.translation(),
+        })
+        .collect();
+    assert_eq!(positions, vec![Vec2::new(105.0, 50.0)]);
+}
+
+#[test]
+fn despawning_a_child_removes_it_from_its_parent() {
+    let mut world = World::new();
+    let parent = world.spawn(at(0.0, 0.0));
+    let first = world.spawn(at(1.0, 0.0));
+    let second = world.spawn(at(2.0, 0.0));
+    world.set_parent(first, parent);
+    world.set_parent(second, parent);
+
+    assert!(world.despawn(first));
+    let children: Vec<_> = world.get::<Children>(parent).unwrap().iter().collect();
+    assert_eq!(children, vec![second]);
+
+    // 最後の子がいなくなると `Children` も外れる
+    world.remove::<Parent>(second);
+    assert!(world.get::<Children>(parent).is_none());
+}